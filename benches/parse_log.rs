@@ -0,0 +1,100 @@
+//! Log parsing throughput on a synthetic profile table.
+//!
+//! ```bash
+//! cargo bench --bench parse_log                 # 10M rows
+//! BENCH_ROWS=1000000 cargo bench --bench parse_log
+//! ```
+//!
+//! The log is generated once into the target directory and reused across runs.
+
+use flowlog_profile_viz::log::{self, ParseOptions};
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+const DEFAULT_ROWS: usize = 10_000_000;
+
+const NAMES: &[&str] = &[
+    "Arrange: ThresholdTotal",
+    "Join",
+    "FlatMap",
+    "Concatenate",
+    "Reduce: Distinct",
+    "Map",
+];
+
+fn main() -> flowlog_profile_viz::Result<()> {
+    let rows = std::env::var("BENCH_ROWS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_ROWS);
+    let path = synthetic_log(rows)?;
+    let path = path.to_str().expect("target dir path is UTF-8");
+    let bytes = fs::metadata(path)?.len() as f64;
+
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut jobs = vec![1];
+    jobs.extend([2, 4, cpus].into_iter().filter(|&j| j > 1 && j <= cpus));
+    jobs.dedup();
+
+    println!("{} rows, {:.1} MiB", rows, bytes / (1 << 20) as f64);
+    for jobs in jobs {
+        let start = Instant::now();
+        let index = log::parse_log_file_with(
+            path,
+            &ParseOptions {
+                jobs,
+                ..Default::default()
+            },
+        )?
+        .index;
+        let secs = start.elapsed().as_secs_f64();
+        assert_eq!(index.len(), rows);
+        println!(
+            "jobs={:<3} {:>7.2} s  {:>8.1} MiB/s  {:>6.2} M rows/s",
+            jobs,
+            secs,
+            bytes / (1 << 20) as f64 / secs,
+            rows as f64 / 1e6 / secs
+        );
+    }
+    Ok(())
+}
+
+/// Write (or reuse) a log with `rows` distinct addresses.
+fn synthetic_log(rows: usize) -> std::io::Result<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let path = dir.join(format!("bench-profile-{}.log", rows));
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let tmp = path.with_extension("partial");
+    let mut out = BufWriter::new(File::create(&tmp)?);
+    writeln!(
+        out,
+        "addr                 activations  total_active_ms  name"
+    )?;
+    for i in 0..rows {
+        let addr = format!(
+            "[0, {}, {}, {}]",
+            i / 1_000_000,
+            (i / 1000) % 1000,
+            i % 1000
+        );
+        writeln!(
+            out,
+            "{:<20} {:<12} {:<16.3} {}",
+            addr,
+            i % 977,
+            (i % 10_007) as f64 * 0.137,
+            NAMES[i % NAMES.len()]
+        )?;
+    }
+    out.flush()?;
+    drop(out);
+    fs::rename(&tmp, &path)?;
+    Ok(path)
+}
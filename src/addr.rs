@@ -6,10 +6,12 @@
 
 use serde::Deserialize;
 
+/// An operator address: its path of scope indices, e.g. `[0, 8, 3]`.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 pub struct Addr(pub Vec<u32>);
 
 impl Addr {
+    /// Wrap an address path.
     pub fn new(path: Vec<u32>) -> Self {
         Self(path)
     }
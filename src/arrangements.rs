@@ -0,0 +1,403 @@
+//! Differential Dataflow arrangement memory (`--arrangements`), joined onto
+//! operators and rolled up to nodes, rules and blocks.
+//!
+//! Two inputs are accepted. A size table, one operator per line, with an
+//! optional header and `#` comments:
+//!
+//! ```text
+//! # addr     records  batches
+//! [0, 8, 3]  1250000  7
+//! [0, 8, 9]  40210    3
+//! ```
+//!
+//! or a JSON-lines capture of Differential's logging events, in the same
+//! `[time, worker, event]` layout as Timely event captures (see
+//! [`crate::events`]). `Batch`, `Merge` (completed) and `Drop` events track the
+//! records and batches each arrangement holds; their operator ids are resolved
+//! through Timely `Operates` events in the same file, so log both streams into
+//! one capture (or concatenate the two). Other events are ignored.
+//!
+//! For captures, `records` and `batches` are what the arrangement held when
+//! the capture ended, and `peak_records` is the most it held at any point
+//! (summed over workers, so an upper bound of the simultaneous peak).
+
+use crate::Result;
+use crate::addr::Addr;
+use crate::diagnostics::Diagnostic;
+use crate::error::Error;
+use crate::input;
+use crate::log::parse_addr;
+use crate::ops::ValidatedOps;
+use crate::view::ReportData;
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Arrangement size of one operator, or summed over several.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ArrangementView {
+    /// Records held, summed over batches.
+    pub records: u64,
+    /// Batches (spine layers) held.
+    pub batches: u64,
+    /// Highest record count seen; equal to `records` for size tables.
+    pub peak_records: u64,
+}
+
+impl ArrangementView {
+    fn add(&mut self, other: &ArrangementView) {
+        self.records += other.records;
+        self.batches += other.batches;
+        self.peak_records += other.peak_records;
+    }
+}
+
+/// Program-wide and per-block sums.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArrangementSummary {
+    /// Summed over every mapped operator.
+    pub total: ArrangementView,
+    /// Summed per block.
+    pub blocks: BTreeMap<String, ArrangementView>,
+}
+
+/// Parsed `--arrangements` input, keyed by operator address.
+#[derive(Debug, Clone, Default)]
+pub struct Arrangements {
+    /// Arrangement size per operator address.
+    pub operators: BTreeMap<Addr, ArrangementView>,
+    /// Capture events for operator ids no `Operates` event described.
+    pub unknown_operators: usize,
+}
+
+/// Read a size table or an event capture (`-` for stdin, may be .gz/.zst).
+pub fn read_arrangements(path: &str) -> Result<Arrangements> {
+    let text = input::read_to_string(path, "read arrangements file")?;
+    let name = input::display_name(path);
+    let capture = text
+        .lines()
+        .map(str::trim_start)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .is_some_and(|l| l.starts_with("[{"));
+    if capture {
+        parse_capture(&text, name)
+    } else {
+        parse_table(&text, name)
+    }
+}
+
+fn parse_table(text: &str, name: &str) -> Result<Arrangements> {
+    let mut operators = BTreeMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() || line.starts_with("addr") {
+            continue;
+        }
+        let Some(end) = line.find(']').filter(|_| line.starts_with('[')) else {
+            bail!(Error::parse(
+                name,
+                i + 1,
+                format!("expected `[ADDR] RECORDS [BATCHES]`, got {:?}", line)
+            ));
+        };
+        let addr = parse_addr(&line[..=end]).map_err(|e| Error::parse(name, i + 1, e))?;
+        let counts = line[end + 1..]
+            .split_whitespace()
+            .map(|s| {
+                s.parse::<u64>().map_err(|_| {
+                    Error::parse(name, i + 1, format!("expected a count, got {:?}", s))
+                })
+            })
+            .collect::<std::result::Result<Vec<u64>, _>>()?;
+        let (records, batches) = match counts[..] {
+            [records] => (records, 0),
+            [records, batches] => (records, batches),
+            _ => bail!(Error::parse(
+                name,
+                i + 1,
+                format!("expected `[ADDR] RECORDS [BATCHES]`, got {:?}", line)
+            )),
+        };
+        let entry: &mut ArrangementView = operators.entry(addr).or_default();
+        entry.add(&ArrangementView {
+            records,
+            batches,
+            peak_records: records,
+        });
+    }
+    Ok(Arrangements {
+        operators,
+        unknown_operators: 0,
+    })
+}
+
+// Mirrors of the differential_dataflow::logging events we consume, plus the
+// Timely `Operates` event for id -> address resolution.
+
+#[derive(Debug, Deserialize)]
+struct BatchEvent {
+    operator: usize,
+    length: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct MergeEvent {
+    operator: usize,
+    length1: usize,
+    length2: usize,
+    /// `None` when the merge starts, the merged length when it completes.
+    complete: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DropEvent {
+    operator: usize,
+    length: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct OperatesEvent {
+    id: usize,
+    addr: Vec<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+enum Event {
+    Batch(BatchEvent),
+    Merge(MergeEvent),
+    Drop(DropEvent),
+    Operates(OperatesEvent),
+}
+
+const CONSUMED: &[&str] = &["Batch", "Merge", "Drop", "Operates"];
+
+#[derive(Default)]
+struct Held {
+    records: i64,
+    batches: i64,
+    peak: i64,
+}
+
+fn parse_capture(text: &str, name: &str) -> Result<Arrangements> {
+    let mut addrs: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    // Keyed by (worker, operator id); events may precede the Operates event.
+    let mut held: HashMap<(usize, usize), Held> = HashMap::new();
+
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (_, worker, event): (Duration, usize, serde_json::Value) =
+            serde_json::from_str(line)
+                .map_err(|e| Error::parse(name, i + 1, format!("bad event record: {}", e)))?;
+        let consumed = matches!(&event, serde_json::Value::Object(map)
+            if map.len() == 1 && map.keys().all(|k| CONSUMED.contains(&k.as_str())));
+        if !consumed {
+            continue;
+        }
+        let event = Event::deserialize(event)
+            .map_err(|e| Error::parse(name, i + 1, format!("bad event: {}", e)))?;
+        let (operator, records, batches) = match event {
+            Event::Operates(op) => {
+                addrs.insert((worker, op.id), op.addr);
+                continue;
+            }
+            Event::Batch(b) => (b.operator, b.length as i64, 1),
+            Event::Merge(MergeEvent {
+                operator,
+                length1,
+                length2,
+                complete: Some(merged),
+            }) => (operator, merged as i64 - (length1 + length2) as i64, -1),
+            Event::Merge(_) => continue,
+            Event::Drop(d) => (d.operator, -(d.length as i64), -1),
+        };
+        let h = held.entry((worker, operator)).or_default();
+        h.records += records;
+        h.batches += batches;
+        h.peak = h.peak.max(h.records);
+    }
+
+    let mut operators: BTreeMap<Addr, ArrangementView> = BTreeMap::new();
+    let mut unknown_operators = 0;
+    for (key, h) in held {
+        let Some(addr) = addrs.get(&key) else {
+            unknown_operators += 1;
+            continue;
+        };
+        let addr = Addr::new(addr.iter().map(|&a| a as u32).collect());
+        operators.entry(addr).or_default().add(&ArrangementView {
+            records: h.records.max(0) as u64,
+            batches: h.batches.max(0) as u64,
+            peak_records: h.peak.max(0) as u64,
+        });
+    }
+    Ok(Arrangements {
+        operators,
+        unknown_operators,
+    })
+}
+
+/// Set `arrangement` on operators, nodes and rules of `data`, and the
+/// program/block summary.
+///
+/// Nodes sum the arrangements of every operator ops.json assigns them, also
+/// those missing from the profile log; rules sum their distinct plan nodes.
+pub fn attach_arrangements(
+    data: &mut ReportData,
+    ops: &ValidatedOps,
+    arrangements: &Arrangements,
+) -> Vec<Diagnostic> {
+    let mut findings = Vec::new();
+    let mut summary = ArrangementSummary::default();
+    let mut mapped = 0;
+
+    for (id, spec) in &ops.nodes {
+        let Some(node) = data.nodes.get_mut(&id.to_string()) else {
+            continue;
+        };
+        let mut sum: Option<ArrangementView> = None;
+        for addr in &spec.operators {
+            if let Some(a) = arrangements.operators.get(addr) {
+                sum.get_or_insert_default().add(a);
+                mapped += 1;
+            }
+        }
+        for op in &mut node.operators {
+            op.arrangement = arrangements
+                .operators
+                .get(&Addr::new(op.addr.clone()))
+                .copied();
+        }
+        if let Some(sum) = &sum {
+            summary.total.add(sum);
+            summary
+                .blocks
+                .entry(node.block.clone())
+                .or_default()
+                .add(sum);
+        }
+        node.arrangement = sum;
+    }
+
+    for rule in &mut data.rules {
+        let mut owned: Vec<&String> = rule
+            .nodes
+            .values()
+            .filter_map(|pn| pn.node.as_ref())
+            .collect();
+        owned.sort();
+        owned.dedup();
+        let mut sum: Option<ArrangementView> = None;
+        for a in owned.iter().filter_map(|n| data.nodes.get(*n)?.arrangement) {
+            sum.get_or_insert_default().add(&a);
+        }
+        rule.arrangement = sum;
+    }
+    data.arrangements = Some(summary);
+
+    let unmapped = arrangements.operators.len() - mapped;
+    if unmapped > 0 {
+        findings.push(Diagnostic::warning(
+            "arrangements-unmapped",
+            format!(
+                "{} arrangement operator(s) are not assigned to any node in ops.json",
+                unmapped
+            ),
+        ));
+    }
+    if arrangements.unknown_operators > 0 {
+        findings.push(Diagnostic::warning(
+            "arrangements-unknown-operators",
+            format!(
+                "{} arrangement(s) belong to operator ids no Operates event described; log Timely events into the same capture",
+                arrangements.unknown_operators
+            ),
+        ));
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn view(records: u64, batches: u64, peak_records: u64) -> ArrangementView {
+        ArrangementView {
+            records,
+            batches,
+            peak_records,
+        }
+    }
+
+    fn addr(parts: &[u32]) -> Addr {
+        Addr::new(parts.to_vec())
+    }
+
+    #[test]
+    fn merges_and_drops_track_held_records() {
+        let capture = r#"
+[{"secs":0,"nanos":1},0,{"Operates":{"id":3,"addr":[0,8,3],"name":"Arrange"}}]
+[{"secs":0,"nanos":2},0,{"Batch":{"operator":3,"length":100}}]
+[{"secs":0,"nanos":3},0,{"Batch":{"operator":3,"length":50}}]
+[{"secs":0,"nanos":4},0,{"Merge":{"operator":3,"scale":1,"length1":100,"length2":50,"complete":null}}]
+[{"secs":0,"nanos":5},0,{"Merge":{"operator":3,"scale":1,"length1":100,"length2":50,"complete":140}}]
+[{"secs":0,"nanos":6},0,{"Batch":{"operator":3,"length":20}}]
+[{"secs":0,"nanos":7},0,{"Drop":{"operator":3,"length":140}}]
+[{"secs":0,"nanos":8},0,{"Schedule":{"id":3,"start_stop":"Start"}}]
+"#;
+        let parsed = parse_capture(capture, "capture.json").unwrap();
+        // The merge compacts 150 records to 140; the next batch peaks at 160
+        // and the drop of the merged batch leaves 20.
+        assert_eq!(parsed.operators[&addr(&[0, 8, 3])], view(20, 1, 160));
+        assert_eq!(parsed.unknown_operators, 0);
+    }
+
+    #[test]
+    fn workers_sum_into_one_operator() {
+        let capture = r#"
+[{"secs":0,"nanos":1},0,{"Operates":{"id":3,"addr":[0,8,3],"name":"Arrange"}}]
+[{"secs":0,"nanos":1},1,{"Operates":{"id":3,"addr":[0,8,3],"name":"Arrange"}}]
+[{"secs":0,"nanos":2},0,{"Batch":{"operator":3,"length":100}}]
+[{"secs":0,"nanos":3},0,{"Drop":{"operator":3,"length":100}}]
+[{"secs":0,"nanos":4},1,{"Batch":{"operator":3,"length":30}}]
+"#;
+        let parsed = parse_capture(capture, "capture.json").unwrap();
+        // Peaks are per worker, so their sum bounds the simultaneous peak.
+        assert_eq!(parsed.operators[&addr(&[0, 8, 3])], view(30, 1, 130));
+    }
+
+    #[test]
+    fn events_before_operates_are_kept() {
+        let capture = r#"
+[{"secs":0,"nanos":1},0,{"Batch":{"operator":5,"length":7}}]
+[{"secs":0,"nanos":2},0,{"Batch":{"operator":9,"length":3}}]
+[{"secs":0,"nanos":2},1,{"Batch":{"operator":9,"length":3}}]
+[{"secs":0,"nanos":3},0,{"Operates":{"id":5,"addr":[0,5],"name":"Arrange"}}]
+[{"secs":0,"nanos":3},1,{"Operates":{"id":5,"addr":[0,5],"name":"Arrange"}}]
+"#;
+        let parsed = parse_capture(capture, "capture.json").unwrap();
+        assert_eq!(
+            parsed.operators.into_iter().collect::<Vec<_>>(),
+            [(addr(&[0, 5]), view(7, 1, 7))]
+        );
+        // Operator 9 on both workers was never described.
+        assert_eq!(parsed.unknown_operators, 2);
+    }
+
+    #[test]
+    fn malformed_events_report_their_line() {
+        for (capture, line) in [
+            ("[{\"secs\":0,\"nanos\":1},0,{\"Batch\":{}}]\n", 1),
+            ("\n[{\"secs\":0,\"nanos\":1},0,{\"Batch\"\n", 2),
+        ] {
+            let err = parse_capture(capture, "capture.json").unwrap_err();
+            let err = crate::error::find(&err).unwrap();
+            assert_eq!(err.kind(), "parse");
+            assert_eq!(err.location().and_then(|l| l.line), Some(line));
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! Noise-aware comparison of two sets of runs of the same program.
+//!
+//! Each node and rule is compared by its mean ms in the base and head runs.
+//! A change is flagged only if it is statistically significant (Welch's
+//! t-test, when both sides have at least two runs) *and* large enough to
+//! matter, so run-to-run wobble does not show up as a regression.
+
+use crate::stats::{self, Spread};
+use crate::view::ReportData;
+
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Thresholds a change must pass to be flagged.
+#[derive(Debug, Clone, Copy)]
+pub struct CompareOptions {
+    /// Significance level for Welch's t-test.
+    pub alpha: f64,
+    /// Minimum relative change of the mean, e.g. 0.05 for 5%.
+    pub min_change: f64,
+    /// Minimum absolute change of the mean in ms, so tiny nodes are not flagged.
+    pub min_ms: f64,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            alpha: 0.05,
+            min_change: 0.05,
+            min_ms: 1.0,
+        }
+    }
+}
+
+/// What a [`Change`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Subject {
+    /// A node, by ops.json id.
+    Node,
+    /// A rule, by text.
+    Rule,
+    /// The total mapped ms.
+    Total,
+}
+
+/// How a node or rule moved from base to head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    /// Significantly and materially slower in head.
+    Slower,
+    /// Significantly and materially faster in head.
+    Faster,
+    /// Within noise, below the thresholds, or not measurable.
+    Unchanged,
+}
+
+/// One node, rule or the total, compared across the two sides.
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    /// What was compared.
+    pub subject: Subject,
+    /// Node name or rule text.
+    pub key: String,
+    /// Node label or rule text, for display.
+    pub label: String,
+    /// ms over the base runs.
+    pub base: Spread,
+    /// ms over the head runs.
+    pub head: Spread,
+    /// Relative change of the mean (head / base - 1); infinite from a zero base.
+    pub change: f64,
+    /// `None` when a side has a single run: only the thresholds apply then.
+    pub p_value: Option<f64>,
+    /// Whether the change is flagged, and in which direction.
+    pub verdict: Verdict,
+}
+
+/// The result of [`compare`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    /// Profile logs on the base side.
+    pub base_runs: usize,
+    /// Profile logs on the head side.
+    pub head_runs: usize,
+    /// Total mapped ms, judged like the nodes and rules.
+    pub total: Change,
+    /// Flagged nodes and rules, largest absolute change first.
+    pub changes: Vec<Change>,
+    /// Nodes and rules present on both sides but not flagged.
+    pub unchanged: usize,
+}
+
+impl Comparison {
+    /// Number of nodes and rules flagged as slower.
+    pub fn regressions(&self) -> usize {
+        self.changes
+            .iter()
+            .filter(|c| c.verdict == Verdict::Slower)
+            .count()
+    }
+}
+
+/// Compare `head` against `base`; both built from the same ops.json.
+pub fn compare(base: &ReportData, head: &ReportData, opts: &CompareOptions) -> Comparison {
+    let total = judge(
+        Subject::Total,
+        "total",
+        "total mapped ms",
+        spread_or(
+            base.totals.total_mapped_ms_spread,
+            base.totals.total_mapped_ms,
+        ),
+        spread_or(
+            head.totals.total_mapped_ms_spread,
+            head.totals.total_mapped_ms,
+        ),
+        opts,
+    );
+
+    let mut all = Vec::new();
+    for (name, b) in &base.nodes {
+        let Some(h) = head.nodes.get(name) else {
+            continue;
+        };
+        all.push(judge(
+            Subject::Node,
+            name,
+            &b.label,
+            spread_or(b.ms_spread, b.self_total_active_ms),
+            spread_or(h.ms_spread, h.self_total_active_ms),
+            opts,
+        ));
+    }
+    for b in &base.rules {
+        let Some(h) = head.rules.iter().find(|r| r.text == b.text) else {
+            continue;
+        };
+        all.push(judge(
+            Subject::Rule,
+            &b.text,
+            &b.text,
+            spread_or(b.ms_spread, b.total_active_ms),
+            spread_or(h.ms_spread, h.total_active_ms),
+            opts,
+        ));
+    }
+
+    let (mut changes, unchanged): (Vec<Change>, Vec<Change>) = all
+        .into_iter()
+        .partition(|c| c.verdict != Verdict::Unchanged);
+    changes.sort_by(|a, b| {
+        let delta = |c: &Change| (c.head.mean - c.base.mean).abs();
+        delta(b).total_cmp(&delta(a))
+    });
+
+    Comparison {
+        base_runs: base.totals.runs,
+        head_runs: head.totals.runs,
+        total,
+        changes,
+        unchanged: unchanged.len(),
+    }
+}
+
+/// A single run has no spread; treat its value as a one-sample spread.
+fn spread_or(spread: Option<Spread>, value: f64) -> Spread {
+    spread.unwrap_or_else(|| Spread::of(&[value]).expect("one value"))
+}
+
+fn judge(
+    subject: Subject,
+    key: &str,
+    label: &str,
+    base: Spread,
+    head: Spread,
+    opts: &CompareOptions,
+) -> Change {
+    let delta = head.mean - base.mean;
+    let change = if base.mean > 0.0 {
+        delta / base.mean
+    } else if delta > 0.0 {
+        f64::INFINITY
+    } else {
+        0.0
+    };
+    let p_value = stats::welch_p_value(&base, &head);
+    let significant = p_value.is_none_or(|p| p < opts.alpha);
+    let large = change.abs() >= opts.min_change && delta.abs() >= opts.min_ms;
+    let verdict = match (significant && large, delta > 0.0) {
+        (false, _) => Verdict::Unchanged,
+        (true, true) => Verdict::Slower,
+        (true, false) => Verdict::Faster,
+    };
+    Change {
+        subject,
+        key: key.to_string(),
+        label: label.to_string(),
+        base,
+        head,
+        change,
+        p_value,
+        verdict,
+    }
+}
+
+/// Plain-text summary: the total, then regressions and improvements.
+pub fn render_text(cmp: &Comparison, opts: &CompareOptions) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "base: {} run(s), head: {} run(s)",
+        cmp.base_runs, cmp.head_runs
+    );
+    let _ = writeln!(out, "{}", describe(&cmp.total));
+    if cmp.base_runs < 2 || cmp.head_runs < 2 {
+        let _ = writeln!(
+            out,
+            "note: with a single run on a side there is no noise estimate; only the thresholds apply"
+        );
+    }
+
+    for (title, verdict) in [
+        ("Regressions", Verdict::Slower),
+        ("Improvements", Verdict::Faster),
+    ] {
+        let matching: Vec<&Change> = cmp
+            .changes
+            .iter()
+            .filter(|c| c.verdict == verdict)
+            .collect();
+        if matching.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "\n{} ({}):", title, matching.len());
+        for c in matching {
+            let kind = match c.subject {
+                Subject::Node => "node",
+                Subject::Rule => "rule",
+                Subject::Total => "total",
+            };
+            let _ = writeln!(out, "  {:<4} {}", kind, describe(c));
+        }
+    }
+    let _ = writeln!(
+        out,
+        "\n{} node(s)/rule(s) within noise or below thresholds (alpha {}, min change {:.0}%, min {} ms)",
+        cmp.unchanged,
+        opts.alpha,
+        opts.min_change * 100.0,
+        opts.min_ms
+    );
+    out
+}
+
+fn describe(c: &Change) -> String {
+    let side = |s: &Spread| {
+        if s.runs > 1 {
+            format!("{:.3} ± {:.3}", s.mean, (s.ci_high - s.ci_low) / 2.0)
+        } else {
+            format!("{:.3}", s.mean)
+        }
+    };
+    let p = c
+        .p_value
+        .map(|p| format!(", p={:.3}", p))
+        .unwrap_or_default();
+    format!(
+        "{}: {} → {} ms ({:+.1}%{})",
+        c.label,
+        side(&c.base),
+        side(&c.head),
+        c.change * 100.0,
+        p
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::{self, LogIndex};
+    use crate::ops::OpsSpec;
+    use crate::profile::Profile;
+    use crate::view;
+
+    /// Report data over runs of a two-node program, given each run's
+    /// (node 0 ms, node 1 ms), built the way `Profile::load_runs` does.
+    fn side(runs: &[(f64, f64)]) -> ReportData {
+        let ops: OpsSpec = serde_json::from_str(
+            r#"{"nodes": [
+                {"id": 0, "name": "steady", "block": "b", "operators": [[0, 1]], "parents": []},
+                {"id": 1, "name": "noisy", "block": "b", "operators": [[0, 2]], "parents": [0]}
+            ]}"#,
+        )
+        .unwrap();
+        let indexes: Vec<LogIndex> = runs
+            .iter()
+            .map(|(a, b)| {
+                let text = format!("[0, 1] 1 {} A\n[0, 2] 1 {} B\n", a, b);
+                log::parse_log(text.as_bytes(), "run.log").unwrap()
+            })
+            .collect();
+        let per_run: Vec<ReportData> = indexes
+            .iter()
+            .map(|index| Profile::from_parts(&ops, index.clone()).unwrap().data)
+            .collect();
+        let mut data = Profile::from_parts(&ops, log::mean_index(&indexes))
+            .unwrap()
+            .data;
+        view::attach_spreads(&mut data, &per_run);
+        data
+    }
+
+    fn change<'a>(cmp: &'a Comparison, key: &str) -> Option<&'a Change> {
+        cmp.changes.iter().find(|c| c.key == key)
+    }
+
+    #[test]
+    fn flags_changes_beyond_noise_only() {
+        let base = side(&[(100.0, 100.0), (101.0, 130.0), (99.0, 70.0)]);
+        // Node 0 moves 20% well outside its confidence interval; node 1 moves
+        // 10% but its runs are all over the place.
+        let head = side(&[(120.0, 110.0), (121.0, 80.0), (119.0, 140.0)]);
+        let cmp = compare(&base, &head, &CompareOptions::default());
+
+        assert_eq!((cmp.base_runs, cmp.head_runs), (3, 3));
+        let steady = change(&cmp, "0").unwrap();
+        assert_eq!(steady.verdict, Verdict::Slower);
+        assert!(steady.p_value.unwrap() < 0.001);
+        assert!((steady.change - 0.2).abs() < 1e-9);
+        assert!(steady.head.ci_low > steady.base.ci_high);
+
+        assert!(change(&cmp, "1").is_none());
+        assert_eq!(cmp.unchanged, 1);
+        assert_eq!(cmp.regressions(), 1);
+
+        // The same move the other way is an improvement.
+        let cmp = compare(&head, &base, &CompareOptions::default());
+        assert_eq!(change(&cmp, "0").unwrap().verdict, Verdict::Faster);
+        assert_eq!(cmp.regressions(), 0);
+    }
+
+    #[test]
+    fn single_runs_are_judged_by_thresholds() {
+        let opts = CompareOptions::default();
+        let judged = |base: f64, head: f64| {
+            let cmp = compare(&side(&[(base, 0.0)]), &side(&[(head, 0.0)]), &opts);
+            assert_eq!(cmp.total.p_value, None);
+            change(&cmp, "0").map_or(Verdict::Unchanged, |c| c.verdict)
+        };
+        assert_eq!(judged(100.0, 110.0), Verdict::Slower);
+        assert_eq!(judged(100.0, 90.0), Verdict::Faster);
+        // Below `min_change`.
+        assert_eq!(judged(100.0, 104.0), Verdict::Unchanged);
+        // Below `min_ms`, despite doubling.
+        assert_eq!(judged(0.5, 1.0), Verdict::Unchanged);
+        assert_eq!(judged(0.0, 5.0), Verdict::Slower);
+    }
+}
@@ -0,0 +1,453 @@
+//! Critical path over the logical DAG (`dag_parents`): the root-to-sink chain
+//! of nodes with the most cumulative self time, for the whole program and
+//! within each block.
+//!
+//! Summed self time says where work goes; the critical path says which chain
+//! of dependent nodes bounds end-to-end latency if everything off it ran in
+//! parallel. A node's slack is how much shorter the heaviest path through it
+//! is than the critical path: zero on the path, large for side branches
+//! whose cost hides behind it.
+
+use crate::diagnostics::Diagnostic;
+use crate::view::NameNodeView;
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// The heaviest root-to-sink chain of a (sub)graph.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PathView {
+    /// Node names from root to sink.
+    pub nodes: Vec<String>,
+    /// Sum of `self_total_active_ms` along the path.
+    pub total_ms: f64,
+}
+
+/// A node on the program critical path, by its share of the path.
+#[derive(Debug, Clone, Serialize)]
+pub struct Bottleneck {
+    /// Node name.
+    pub node: String,
+    /// The node's `self_total_active_ms`.
+    pub self_ms: f64,
+    /// `self_ms` as a fraction of the path's `total_ms`.
+    pub share: f64,
+    /// Whether the node is also on its block's critical path.
+    pub on_block_path: bool,
+}
+
+/// Critical paths of the program and of each block, with the bottlenecks.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CriticalPathView {
+    /// Critical path over the whole DAG.
+    pub program: PathView,
+    /// Critical path within each block, over the DAG edges inside it.
+    pub blocks: BTreeMap<String, PathView>,
+    /// Nodes on the program path with self time, most expensive first.
+    pub bottlenecks: Vec<Bottleneck>,
+}
+
+/// Compute the critical paths and set `slack_ms`/`block_slack_ms` on `nodes`.
+///
+/// Nodes on or downstream of a cycle in `dag_parents` have no longest path;
+/// they are left without slack and reported as a warning.
+pub fn attach_critical_path(
+    nodes: &mut BTreeMap<String, NameNodeView>,
+) -> (CriticalPathView, Vec<Diagnostic>) {
+    let all: BTreeSet<&str> = nodes.keys().map(String::as_str).collect();
+    let program = longest(nodes, &all);
+
+    let mut by_block: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for node in nodes.values() {
+        by_block
+            .entry(node.block.as_str())
+            .or_default()
+            .insert(node.name.as_str());
+    }
+    let blocks: BTreeMap<&str, Longest> = by_block
+        .iter()
+        .map(|(block, members)| (*block, longest(nodes, members)))
+        .collect();
+
+    let on_block_path: BTreeSet<&str> = blocks
+        .values()
+        .flat_map(|l| l.path.iter().copied())
+        .collect();
+    let mut bottlenecks: Vec<Bottleneck> = program
+        .path
+        .iter()
+        .map(|&n| (n, nodes[n].self_total_active_ms))
+        .filter(|&(_, ms)| ms > 0.0)
+        .map(|(n, ms)| Bottleneck {
+            node: n.to_string(),
+            self_ms: ms,
+            share: ms / program.total_ms,
+            on_block_path: on_block_path.contains(n),
+        })
+        .collect();
+    bottlenecks.sort_by(|a, b| {
+        b.self_ms
+            .total_cmp(&a.self_ms)
+            .then_with(|| a.node.cmp(&b.node))
+    });
+
+    let mut findings = Vec::new();
+    if !program.cyclic.is_empty() {
+        findings.push(Diagnostic::warning(
+            "critical-path-cycle",
+            format!(
+                "{} node(s) are on or downstream of a cycle in the DAG and left out of the critical path, e.g. {}",
+                program.cyclic.len(),
+                program.cyclic[0]
+            ),
+        ));
+    }
+
+    let view = CriticalPathView {
+        program: program.view(),
+        blocks: blocks
+            .iter()
+            .map(|(block, l)| (block.to_string(), l.view()))
+            .collect(),
+        bottlenecks,
+    };
+    let slack: Vec<(String, Option<f64>, Option<f64>)> = nodes
+        .values()
+        .map(|n| {
+            let name = n.name.as_str();
+            let block_slack = blocks[n.block.as_str()].slack.get(name).copied();
+            (
+                n.name.clone(),
+                program.slack.get(name).copied(),
+                block_slack,
+            )
+        })
+        .collect();
+    for (name, slack_ms, block_slack_ms) in slack {
+        if let Some(node) = nodes.get_mut(&name) {
+            node.slack_ms = slack_ms;
+            node.block_slack_ms = block_slack_ms;
+        }
+    }
+    (view, findings)
+}
+
+/// Longest path over the DAG edges between `members`.
+struct Longest<'a> {
+    path: Vec<&'a str>,
+    total_ms: f64,
+    slack: HashMap<&'a str, f64>,
+    /// Members that never became ready in topological order.
+    cyclic: Vec<&'a str>,
+}
+
+impl Longest<'_> {
+    fn view(&self) -> PathView {
+        PathView {
+            nodes: self.path.iter().map(|n| n.to_string()).collect(),
+            total_ms: self.total_ms,
+        }
+    }
+}
+
+fn longest<'a>(
+    nodes: &'a BTreeMap<String, NameNodeView>,
+    members: &BTreeSet<&'a str>,
+) -> Longest<'a> {
+    let ms = |n: &str| nodes[n].self_total_active_ms;
+    let parents = |n: &str| -> Vec<&'a str> {
+        nodes[n]
+            .dag_parents
+            .iter()
+            .map(String::as_str)
+            .filter(|p| members.contains(p))
+            .collect()
+    };
+
+    // Kahn's algorithm; nodes left pending sit on or below a cycle.
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut pending: BTreeMap<&str, usize> = BTreeMap::new();
+    for &n in members {
+        let ps = parents(n);
+        pending.insert(n, ps.len());
+        for p in ps {
+            children.entry(p).or_default().push(n);
+        }
+    }
+    let mut ready: Vec<&str> = pending
+        .iter()
+        .filter(|&(_, &count)| count == 0)
+        .map(|(&n, _)| n)
+        .collect();
+    let mut order = Vec::with_capacity(members.len());
+    while let Some(n) = ready.pop() {
+        order.push(n);
+        for &child in children.get(n).into_iter().flatten() {
+            let count = pending.get_mut(child).expect("child is a member");
+            *count -= 1;
+            if *count == 0 {
+                ready.push(child);
+            }
+        }
+    }
+
+    // Heaviest path ending at each node (with its predecessor on it), then
+    // heaviest path starting at each node.
+    let mut to: HashMap<&str, (f64, Option<&str>)> = HashMap::new();
+    for &n in &order {
+        let best = parents(n)
+            .into_iter()
+            .map(|p| (to[p].0, p))
+            .max_by(|a, b| a.0.total_cmp(&b.0).then_with(|| b.1.cmp(a.1)));
+        to.insert(n, (ms(n) + best.map_or(0.0, |b| b.0), best.map(|b| b.1)));
+    }
+    let mut from: HashMap<&str, f64> = HashMap::new();
+    for &n in order.iter().rev() {
+        let best = children
+            .get(n)
+            .into_iter()
+            .flatten()
+            .filter_map(|c| from.get(c))
+            .fold(0.0, |m: f64, &v| m.max(v));
+        from.insert(n, ms(n) + best);
+    }
+
+    // End at a sink so that trailing 0 ms nodes are not cut off by ties; with
+    // non-negative times the heaviest sink is as heavy as any node.
+    let is_sink = |n: &str| {
+        children
+            .get(n)
+            .into_iter()
+            .flatten()
+            .all(|c| !to.contains_key(c))
+    };
+    let end = order
+        .iter()
+        .copied()
+        .filter(|&n| is_sink(n))
+        .max_by(|a, b| to[a].0.total_cmp(&to[b].0).then_with(|| b.cmp(a)));
+    let total_ms = end.map_or(0.0, |n| to[n].0);
+    let mut path = Vec::new();
+    let mut cur = end;
+    while let Some(n) = cur {
+        path.push(n);
+        cur = to[n].1;
+    }
+    path.reverse();
+
+    // Paths summed in different orders differ by rounding; snap that to zero.
+    let epsilon = total_ms * 1e-9;
+    let slack = order
+        .iter()
+        .map(|&n| {
+            let slack = total_ms - (to[n].0 + from[n] - ms(n));
+            (n, if slack <= epsilon { 0.0 } else { slack })
+        })
+        .collect();
+    let cyclic = pending
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .map(|(n, _)| n)
+        .collect();
+    Longest {
+        path,
+        total_ms,
+        slack,
+        cyclic,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use crate::log::parse_log;
+    use crate::ops::OpsSpec;
+    use crate::profile::Profile;
+    use pretty_assertions::assert_eq;
+
+    /// Nodes `0..` from (block, self ms, parents), one operator each.
+    fn graph(spec: &[(&str, f64, &[u32])]) -> BTreeMap<String, NameNodeView> {
+        let nodes: Vec<serde_json::Value> = spec
+            .iter()
+            .enumerate()
+            .map(|(id, (block, _, parents))| {
+                serde_json::json!({
+                    "id": id,
+                    "name": format!("n{}", id),
+                    "block": block,
+                    "operators": [[0, id]],
+                    "parents": parents,
+                })
+            })
+            .collect();
+        let ops: OpsSpec = serde_json::from_value(serde_json::json!({ "nodes": nodes })).unwrap();
+        let log: String = spec
+            .iter()
+            .enumerate()
+            .map(|(id, (_, ms, _))| format!("[0, {}] 1 {} op\n", id, ms))
+            .collect();
+        let index = parse_log(log.as_bytes(), "log").unwrap();
+        Profile::from_parts(&ops, index).unwrap().data.nodes
+    }
+
+    fn slack(nodes: &BTreeMap<String, NameNodeView>) -> Vec<(Option<f64>, Option<f64>)> {
+        nodes
+            .values()
+            .map(|n| (n.slack_ms, n.block_slack_ms))
+            .collect()
+    }
+
+    #[test]
+    fn side_branch_of_a_diamond_has_slack() {
+        let mut nodes = graph(&[
+            ("b", 1.0, &[]),
+            ("b", 10.0, &[0]),
+            ("b", 3.0, &[0]),
+            ("b", 1.0, &[1, 2]),
+        ]);
+        let (view, findings) = attach_critical_path(&mut nodes);
+
+        assert_eq!(view.program.nodes, ["0", "1", "3"]);
+        assert_eq!(view.program.total_ms, 12.0);
+        assert_eq!(view.blocks["b"].nodes, view.program.nodes);
+        assert_eq!(
+            slack(&nodes),
+            [
+                (Some(0.0), Some(0.0)),
+                (Some(0.0), Some(0.0)),
+                (Some(7.0), Some(7.0)),
+                (Some(0.0), Some(0.0)),
+            ]
+        );
+        let bottlenecks: Vec<(&str, f64)> = view
+            .bottlenecks
+            .iter()
+            .map(|b| (b.node.as_str(), b.share))
+            .collect();
+        assert_eq!(
+            bottlenecks,
+            [("1", 10.0 / 12.0), ("0", 1.0 / 12.0), ("3", 1.0 / 12.0)]
+        );
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn block_paths_only_follow_edges_inside_the_block() {
+        // Block `b` is entered through node 1; inside `b` alone, node 2 leads.
+        let mut nodes = graph(&[
+            ("a", 10.0, &[]),
+            ("b", 1.0, &[0]),
+            ("b", 5.0, &[]),
+            ("b", 1.0, &[1, 2]),
+        ]);
+        let (view, _) = attach_critical_path(&mut nodes);
+
+        assert_eq!(view.program.nodes, ["0", "1", "3"]);
+        assert_eq!(view.program.total_ms, 12.0);
+        assert_eq!(view.blocks["a"].nodes, ["0"]);
+        assert_eq!(view.blocks["b"].nodes, ["2", "3"]);
+        assert_eq!(view.blocks["b"].total_ms, 6.0);
+        assert_eq!(
+            slack(&nodes),
+            [
+                (Some(0.0), Some(0.0)),
+                (Some(0.0), Some(4.0)),
+                (Some(6.0), Some(0.0)),
+                (Some(0.0), Some(0.0)),
+            ]
+        );
+        let on_block_path: Vec<(&str, bool)> = view
+            .bottlenecks
+            .iter()
+            .map(|b| (b.node.as_str(), b.on_block_path))
+            .collect();
+        assert_eq!(on_block_path, [("0", true), ("1", false), ("3", true)]);
+    }
+
+    #[test]
+    fn paths_run_through_to_a_sink() {
+        // Ties with the 0 ms tail must not end the path early.
+        let mut nodes = graph(&[
+            ("b", 5.0, &[]),
+            ("b", 0.0, &[0]),
+            ("b", 0.0, &[1]),
+            ("b", 2.0, &[]),
+        ]);
+        let (view, _) = attach_critical_path(&mut nodes);
+
+        assert_eq!(view.program.nodes, ["0", "1", "2"]);
+        assert_eq!(view.program.total_ms, 5.0);
+        assert_eq!(view.blocks["b"].nodes, view.program.nodes);
+    }
+
+    #[test]
+    fn cycles_are_left_out() {
+        let mut nodes = graph(&[
+            ("b", 1.0, &[]),
+            ("b", 2.0, &[]),
+            ("b", 3.0, &[]),
+            ("b", 4.0, &[]),
+            ("c", 5.0, &[]),
+        ]);
+        // 0 <-> 1 with 2 downstream of the cycle, 3 on itself; 4 is fine.
+        let parents: [&[&str]; 5] = [&["1"], &["0"], &["1"], &["3"], &[]];
+        for (node, parents) in nodes.values_mut().zip(parents) {
+            node.dag_parents = parents.iter().map(|p| p.to_string()).collect();
+        }
+        let (view, findings) = attach_critical_path(&mut nodes);
+
+        assert_eq!(view.program.nodes, ["4"]);
+        assert_eq!(view.program.total_ms, 5.0);
+        assert!(view.blocks["b"].nodes.is_empty());
+        assert_eq!(
+            slack(&nodes),
+            [
+                (None, None),
+                (None, None),
+                (None, None),
+                (None, None),
+                (Some(0.0), Some(0.0)),
+            ]
+        );
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].code, "critical-path-cycle");
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert!(
+            findings[0].message.starts_with("4 node(s)"),
+            "{}",
+            findings[0].message
+        );
+    }
+
+    #[test]
+    fn rounding_does_not_create_slack() {
+        // Summed front to back and back to front, these differ in the last bit.
+        let ms = [0.1, 0.7, 0.3, 1.1, 0.2, 0.6];
+        let to: Vec<f64> = ms
+            .iter()
+            .scan(0.0, |sum, &m| {
+                *sum += m;
+                Some(*sum)
+            })
+            .collect();
+        let from: f64 = ms[1..].iter().rev().fold(0.0, |sum, &m| m + sum);
+        assert_ne!(to[5] - (to[1] + from - ms[1]), 0.0);
+
+        let spec: Vec<(&str, f64, &[u32])> = vec![
+            ("b", ms[0], &[]),
+            ("b", ms[1], &[0]),
+            ("b", ms[2], &[1]),
+            ("b", ms[3], &[2]),
+            ("b", ms[4], &[3]),
+            ("b", ms[5], &[4]),
+        ];
+        let mut nodes = graph(&spec);
+        attach_critical_path(&mut nodes);
+        assert!(
+            nodes.values().all(|n| n.slack_ms == Some(0.0)),
+            "{:?}",
+            slack(&nodes)
+        );
+    }
+}
@@ -0,0 +1,441 @@
+//! Tabular export (CSV/TSV) of the node, operator and relation views.
+//!
+//! Writes up to four files into an output directory:
+//! - nodes.csv:     name, label, block, fingerprint, tags, self_ms, activations,
+//!   parents, slack_ms, block_slack_ms (see [`crate::critical`])
+//! - operators.csv: addr, op_name, node, ms, activations, then one
+//!   `metric_<name>` column per extra log metric (empty where an operator has
+//!   no value; prefixed so log headers cannot clash with other columns), then
+//!   p50_ms, p90_ms, p99_ms, heavy_tail, histogram when durations are known
+//! - relations.csv: name, rules, ms, activations, depends_on, used_by, one row
+//!   per relation of the parsed rules (see [`crate::datalog`])
+//!
+//! When several runs were aggregated, both tables gain ms_stddev, ms_min,
+//! ms_max, ms_ci_low and ms_ci_high after the fixed/metric columns. With
+//! `--sizes`, nodes.csv also gets input_tuples, output_tuples, us_per_tuple
+//! and tuples_per_s; with `--arrangements`, both tables get records, batches
+//! and peak_records.
+//!
+//! When the log is an event capture with channel events, edges.csv lists
+//! source, target, messages and records for every DAG edge, heaviest first.
+//!
+//! List-valued columns (tags, parents, depends_on, used_by) are joined with ';'.
+
+use crate::Result;
+use crate::arrangements::ArrangementView;
+use crate::error::Error;
+use crate::stats::Spread;
+use crate::view::ReportData;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Write `nodes.<ext>`, `operators.<ext>` and `relations.<ext>` (and
+/// `edges.<ext>` when edges have volumes) into `dir`, returning the paths.
+///
+/// `delimiter` is ',' for CSV or '\t' for TSV; the file extension follows it.
+pub fn write_tables(data: &ReportData, dir: &Path, delimiter: char) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)
+        .map_err(|e| Error::io("create output directory", dir.display().to_string(), e))?;
+
+    let ext = if delimiter == '\t' { "tsv" } else { "csv" };
+    let nodes_path = dir.join(format!("nodes.{}", ext));
+    let ops_path = dir.join(format!("operators.{}", ext));
+
+    write_file(&nodes_path, &nodes_table(data, delimiter))?;
+    write_file(&ops_path, &operators_table(data, delimiter))?;
+    let relations_path = dir.join(format!("relations.{}", ext));
+    write_file(&relations_path, &relations_table(data, delimiter))?;
+    let mut paths = vec![nodes_path, ops_path, relations_path];
+
+    if !data.edges.is_empty() {
+        let edges_path = dir.join(format!("edges.{}", ext));
+        write_file(&edges_path, &edges_table(data, delimiter))?;
+        paths.push(edges_path);
+    }
+    Ok(paths)
+}
+
+fn write_file(path: &Path, text: &str) -> Result<()> {
+    fs::write(path, text).map_err(|e| Error::io("write", path.display().to_string(), e))?;
+    Ok(())
+}
+
+fn nodes_table(data: &ReportData, delimiter: char) -> String {
+    let runs = data.totals.runs > 1;
+    let mut out = String::new();
+    let mut header = vec![
+        "name",
+        "label",
+        "block",
+        "fingerprint",
+        "tags",
+        "self_ms",
+        "activations",
+        "parents",
+        "slack_ms",
+        "block_slack_ms",
+    ];
+    if runs {
+        header.extend(SPREAD_COLUMNS);
+    }
+    let sized = data.nodes.values().any(|n| n.sizes.is_some());
+    if sized {
+        header.extend([
+            "input_tuples",
+            "output_tuples",
+            "us_per_tuple",
+            "tuples_per_s",
+        ]);
+    }
+    let arranged = data.arrangements.is_some();
+    if arranged {
+        header.extend(ARRANGEMENT_COLUMNS);
+    }
+    push_row(&mut out, delimiter, &header);
+    for node in data.nodes.values() {
+        let mut fields = vec![
+            node.name.clone(),
+            node.label.clone(),
+            node.block.clone(),
+            node.fingerprint.clone().unwrap_or_default(),
+            node.tags.join(";"),
+            node.self_total_active_ms.to_string(),
+            node.self_activations.to_string(),
+            node.dag_parents.join(";"),
+            node.slack_ms.map(|v| v.to_string()).unwrap_or_default(),
+            node.block_slack_ms
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+        ];
+        if runs {
+            fields.extend(spread_fields(node.ms_spread));
+        }
+        if sized {
+            let opt = |v: Option<String>| v.unwrap_or_default();
+            let s = node.sizes.as_ref();
+            fields.extend([
+                opt(s.and_then(|s| s.input).map(|v| v.to_string())),
+                opt(s.and_then(|s| s.output).map(|v| v.to_string())),
+                opt(s.and_then(|s| s.us_per_tuple).map(|v| v.to_string())),
+                opt(s.and_then(|s| s.tuples_per_s).map(|v| v.to_string())),
+            ]);
+        }
+        if arranged {
+            fields.extend(arrangement_fields(node.arrangement));
+        }
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        push_row(&mut out, delimiter, &fields);
+    }
+    out
+}
+
+/// Columns describing `ms` over repeated runs.
+const SPREAD_COLUMNS: [&str; 5] = ["ms_stddev", "ms_min", "ms_max", "ms_ci_low", "ms_ci_high"];
+
+fn spread_fields(spread: Option<Spread>) -> Vec<String> {
+    match spread {
+        Some(s) => [s.stddev, s.min, s.max, s.ci_low, s.ci_high]
+            .iter()
+            .map(|v| v.to_string())
+            .collect(),
+        None => vec![String::new(); SPREAD_COLUMNS.len()],
+    }
+}
+
+/// Columns describing arrangement size from `--arrangements`.
+const ARRANGEMENT_COLUMNS: [&str; 3] = ["records", "batches", "peak_records"];
+
+fn arrangement_fields(arrangement: Option<ArrangementView>) -> Vec<String> {
+    match arrangement {
+        Some(a) => [a.records, a.batches, a.peak_records]
+            .iter()
+            .map(|v| v.to_string())
+            .collect(),
+        None => vec![String::new(); ARRANGEMENT_COLUMNS.len()],
+    }
+}
+
+fn operators_table(data: &ReportData, delimiter: char) -> String {
+    let metrics: BTreeSet<&str> = data
+        .nodes
+        .values()
+        .flat_map(|n| &n.operators)
+        .flat_map(|op| op.metrics.keys().map(String::as_str))
+        .collect();
+
+    let has_dist = data
+        .nodes
+        .values()
+        .flat_map(|n| &n.operators)
+        .any(|op| op.activation.is_some());
+
+    let mut out = String::new();
+    let runs = data.totals.runs > 1;
+    let metric_columns: Vec<String> = metrics.iter().map(|m| format!("metric_{}", m)).collect();
+    let mut header = vec!["addr", "op_name", "node", "ms", "activations"];
+    header.extend(metric_columns.iter().map(String::as_str));
+    if runs {
+        header.extend(SPREAD_COLUMNS);
+    }
+    if has_dist {
+        header.extend(["p50_ms", "p90_ms", "p99_ms", "heavy_tail", "histogram"]);
+    }
+    let arranged = data.arrangements.is_some();
+    if arranged {
+        header.extend(ARRANGEMENT_COLUMNS);
+    }
+    push_row(&mut out, delimiter, &header);
+    for node in data.nodes.values() {
+        for op in &node.operators {
+            let mut fields = vec![
+                format!("{:?}", op.addr),
+                op.op_name.clone(),
+                node.name.clone(),
+                op.total_active_ms.to_string(),
+                op.activations.to_string(),
+            ];
+            fields.extend(metrics.iter().map(|m| {
+                op.metrics
+                    .get(*m)
+                    .map(|v| v.to_string())
+                    .unwrap_or_default()
+            }));
+            if runs {
+                fields.extend(spread_fields(op.ms_spread));
+            }
+            if has_dist {
+                match &op.activation {
+                    Some(act) => fields.extend([
+                        act.p50_ms.to_string(),
+                        act.p90_ms.to_string(),
+                        act.p99_ms.to_string(),
+                        act.heavy_tail.to_string(),
+                        format!("{:?}", act.histogram),
+                    ]),
+                    None => fields.extend(std::iter::repeat_n(String::new(), 5)),
+                }
+            }
+            if arranged {
+                fields.extend(arrangement_fields(op.arrangement));
+            }
+            let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+            push_row(&mut out, delimiter, &fields);
+        }
+    }
+    out
+}
+
+fn relations_table(data: &ReportData, delimiter: char) -> String {
+    let mut out = String::new();
+    push_row(
+        &mut out,
+        delimiter,
+        &[
+            "name",
+            "rules",
+            "ms",
+            "activations",
+            "depends_on",
+            "used_by",
+        ],
+    );
+    for r in &data.relations {
+        let fields = [
+            r.name.clone(),
+            r.rules.len().to_string(),
+            r.total_active_ms.to_string(),
+            r.activations.to_string(),
+            r.depends_on.join(";"),
+            r.used_by.join(";"),
+        ];
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        push_row(&mut out, delimiter, &fields);
+    }
+    out
+}
+
+fn edges_table(data: &ReportData, delimiter: char) -> String {
+    let mut out = String::new();
+    push_row(
+        &mut out,
+        delimiter,
+        &["source", "target", "messages", "records"],
+    );
+    for edge in &data.edges {
+        let (messages, records) = (edge.messages.to_string(), edge.records.to_string());
+        push_row(
+            &mut out,
+            delimiter,
+            &[&edge.source, &edge.target, &messages, &records],
+        );
+    }
+    out
+}
+
+fn push_row(out: &mut String, delimiter: char, fields: &[&str]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(delimiter);
+        }
+        out.push_str(&quote(field, delimiter));
+    }
+    out.push('\n');
+}
+
+/// Quote a field per RFC 4180 when it contains the delimiter, quotes, or newlines.
+fn quote(field: &str, delimiter: char) -> String {
+    let needs_quotes = field.contains(delimiter)
+        || field.contains('"')
+        || field.contains('\n')
+        || field.contains('\r');
+    if needs_quotes {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::{self, LogIndex};
+    use crate::ops::OpsSpec;
+    use crate::profile::Profile;
+    use crate::view;
+    use pretty_assertions::assert_eq;
+
+    fn ops() -> OpsSpec {
+        serde_json::from_value(serde_json::json!({
+            "nodes": [
+                {"id": 0, "name": "e: input", "block": "in", "operators": [[0, 1]], "parents": []},
+                {"id": 1, "name": "r: join, \"x\"", "block": "s0", "fingerprint": "0xa",
+                 "operators": [[0, 2]], "parents": [0]},
+            ],
+            "rules": [{"text": "r(x) :- e(x), x != \"a,b\".", "plan_tree": [{"fingerprint": "0xa", "parents": []}]}],
+        }))
+        .unwrap()
+    }
+
+    /// Report data over one log per run, built the way `Profile::load_runs` does.
+    fn report(logs: &[&str]) -> ReportData {
+        let ops = ops();
+        let indexes: Vec<LogIndex> = logs
+            .iter()
+            .map(|text| log::parse_log(text.as_bytes(), "log").unwrap())
+            .collect();
+        if let [index] = indexes.as_slice() {
+            return Profile::from_parts(&ops, index.clone()).unwrap().data;
+        }
+        let runs: Vec<ReportData> = indexes
+            .iter()
+            .map(|index| Profile::from_parts(&ops, index.clone()).unwrap().data)
+            .collect();
+        let mut data = Profile::from_parts(&ops, log::mean_index(&indexes))
+            .unwrap()
+            .data;
+        view::attach_spreads(&mut data, &runs);
+        data
+    }
+
+    fn header(table: &str) -> Vec<&str> {
+        table.lines().next().unwrap().split(',').collect()
+    }
+
+    #[test]
+    fn fields_are_quoted_per_rfc_4180() {
+        assert_eq!(quote("plain", ','), "plain");
+        assert_eq!(quote("a,b", ','), "\"a,b\"");
+        assert_eq!(quote("a,b", '\t'), "a,b");
+        assert_eq!(quote("a\tb", '\t'), "\"a\tb\"");
+        assert_eq!(quote("say \"hi\"", ','), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote("two\nlines", ','), "\"two\nlines\"");
+        assert_eq!(quote("cr\r", ','), "\"cr\r\"");
+
+        let data = report(&["[0, 1] 1 1 Input\n[0, 2] 2 3 Join\n"]);
+        let table = nodes_table(&data, ',');
+        let rows: Vec<&str> = table.lines().skip(1).collect();
+        assert_eq!(
+            rows,
+            [
+                "0,e: input,in,,,1,1,,0,0",
+                "1,\"r: join, \"\"x\"\"\",s0,0xa,,3,2,0,0,0",
+            ]
+        );
+    }
+
+    #[test]
+    fn metric_columns_are_prefixed() {
+        // `records` and `ms` would otherwise clash with fixed columns.
+        let data = report(&[
+            "addr activations total_active_ms records ms name\n[0, 1] 1 1 5 7 Input\n[0, 2] 2 3 9 - Join\n",
+        ]);
+        let table = operators_table(&data, ',');
+        assert_eq!(
+            header(&table),
+            [
+                "addr",
+                "op_name",
+                "node",
+                "ms",
+                "activations",
+                "metric_ms",
+                "metric_records"
+            ]
+        );
+        let rows: Vec<&str> = table.lines().skip(1).collect();
+        assert_eq!(
+            rows,
+            ["\"[0, 1]\",Input,0,1,1,7,5", "\"[0, 2]\",Join,1,3,2,,9"]
+        );
+    }
+
+    #[test]
+    fn repeated_runs_add_spread_columns() {
+        let data = report(&[
+            "[0, 1] 1 1 Input\n[0, 2] 2 2 Join\n",
+            "[0, 1] 1 3 Input\n[0, 2] 2 4 Join\n",
+        ]);
+        let spread = ["ms_stddev", "ms_min", "ms_max", "ms_ci_low", "ms_ci_high"];
+        assert_eq!(header(&nodes_table(&data, ','))[10..], spread);
+        assert_eq!(header(&operators_table(&data, ','))[5..], spread);
+        let table = operators_table(&data, '\t');
+        let row: Vec<&str> = table.lines().nth(1).unwrap().split('\t').collect();
+        assert_eq!(
+            row[..8],
+            [
+                "[0, 1]",
+                "Input",
+                "0",
+                "2",
+                "1",
+                "1.4142135623730951",
+                "1",
+                "3"
+            ]
+        );
+    }
+
+    #[test]
+    fn tables_are_written_per_delimiter() {
+        let data = report(&["[0, 1] 1 1 Input\n[0, 2] 2 3 Join\n"]);
+        let dir = std::env::temp_dir().join(format!("csv-test-{}", std::process::id()));
+        let tsv = write_tables(&data, &dir, '\t');
+        let relations = fs::read_to_string(dir.join("relations.tsv"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<String> = tsv
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["nodes.tsv", "operators.tsv", "relations.tsv"]);
+        assert_eq!(
+            relations.unwrap(),
+            "name\trules\tms\tactivations\tdepends_on\tused_by\n\
+             r\t1\t3\t2\te\t\n\
+             e\t0\t0\t0\t\tr\n"
+        );
+    }
+}
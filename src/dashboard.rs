@@ -0,0 +1,467 @@
+//! Multi-run dashboard: one index over a directory of profiling runs.
+//!
+//! Every subdirectory holding a profile log and an ops.json is a run:
+//!
+//! ```text
+//! runs/
+//!   tc-small-4t/   profile.log  ops.json  meta.json
+//!   tc-large-8t/   profile.log.zst  ops.json.gz  meta.json
+//! ```
+//!
+//! `meta.json` is optional and must be a JSON object (dataset, threads, commit,
+//! ...); its fields become columns of the run table. The dashboard directory
+//! gets `index.html`, `index.json` and a full report per run under `runs/`.
+//! Nodes are matched across runs by their ops.json id, which is stable as
+//! long as the runs profile the same program.
+
+use crate::Result;
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::Error;
+use crate::input;
+use crate::log::ParseOptions;
+use crate::profile::Profile;
+use crate::render;
+use crate::sink::{HtmlFile, Sink};
+use crate::view::ReportData;
+
+use anyhow::bail;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File names tried, in order, for a run's profile log and ops spec.
+const LOG_NAMES: &[&str] = &["profile.log", "profile.log.gz", "profile.log.zst"];
+const OPS_NAMES: &[&str] = &["ops.json", "ops.json.gz", "ops.json.zst"];
+const META_NAME: &str = "meta.json";
+
+/// Everything the dashboard index shows.
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardData {
+    /// In display order: by `sort_key` when given, else by directory name.
+    pub runs: Vec<RunSummary>,
+    /// Busiest nodes, with one entry per run in `runs` order.
+    pub nodes: Vec<NodeTrend>,
+    /// Runs that could not be loaded, among others.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// One row of the run table.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    /// Directory name.
+    pub name: String,
+    /// Fields of the run's `meta.json`, if any.
+    pub meta: BTreeMap<String, serde_json::Value>,
+    /// Path of the full report, relative to the dashboard directory.
+    pub report: Option<String>,
+    /// Total mapped ms; 0 for a failed run.
+    pub total_mapped_ms: f64,
+    /// Total mapped activations; 0 for a failed run.
+    pub total_mapped_activations: u64,
+    /// Share of log operators mapped to a node; `None` for an empty log.
+    pub coverage: Option<f64>,
+    /// The rule with the most active time.
+    pub top_rule: Option<TopEntry>,
+    /// The node with the most self time.
+    pub top_node: Option<TopEntry>,
+    /// Number of warning diagnostics.
+    pub warnings: usize,
+    /// Why the run is missing from the trends, if it failed to load.
+    pub error: Option<String>,
+}
+
+/// A rule or node named in the run table, with its ms.
+#[derive(Debug, Clone, Serialize)]
+pub struct TopEntry {
+    /// Rule text or node label.
+    pub label: String,
+    /// Active time in ms.
+    pub ms: f64,
+}
+
+/// A node's self time across runs (`None` where a run lacks the node or failed).
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeTrend {
+    /// ops.json node id.
+    pub name: String,
+    /// Node label.
+    pub label: String,
+    /// Node block.
+    pub block: String,
+    /// Self time per run, in run order.
+    pub ms: Vec<Option<f64>>,
+}
+
+/// Build the dashboard for the runs under `dir` and write it into `out`.
+///
+/// Runs that fail to load are listed with their error rather than aborting
+/// the whole dashboard. `top` limits the node trends to the nodes with the
+/// highest self time in any run; `sort_key` orders runs by a `meta.json`
+/// field (numbers numerically, runs without the field last).
+pub fn write_dashboard(
+    dir: &Path,
+    out: &Path,
+    opts: &ParseOptions,
+    top: usize,
+    sort_key: Option<&str>,
+) -> Result<DashboardData> {
+    let runs = discover(dir)?;
+    if runs.is_empty() {
+        bail!(
+            "no runs found in {}: expected subdirectories with {} and {}",
+            dir.display(),
+            LOG_NAMES[0],
+            OPS_NAMES[0]
+        );
+    }
+
+    let reports_dir = out.join("runs");
+    fs::create_dir_all(&reports_dir).map_err(|e| {
+        Error::io(
+            "create output directory",
+            reports_dir.display().to_string(),
+            e,
+        )
+    })?;
+
+    let mut loaded: Vec<(RunSummary, Option<ReportData>)> = runs
+        .into_iter()
+        .map(|run| load_run(run, &reports_dir, opts))
+        .collect();
+    if let Some(key) = sort_key {
+        loaded.sort_by(|(a, _), (b, _)| compare_meta(a.meta.get(key), b.meta.get(key)));
+    }
+
+    let mut diagnostics = Vec::new();
+    for (summary, _) in &loaded {
+        if let Some(err) = &summary.error {
+            diagnostics.push(Diagnostic::warning(
+                "run-failed",
+                format!("run '{}' could not be loaded: {}", summary.name, err),
+            ));
+        }
+    }
+    let reports: Vec<Option<&ReportData>> = loaded.iter().map(|(_, d)| d.as_ref()).collect();
+    let nodes = node_trends(&reports, top);
+    let data = DashboardData {
+        runs: loaded.into_iter().map(|(summary, _)| summary).collect(),
+        nodes,
+        diagnostics,
+    };
+
+    let json = serde_json::to_string_pretty(&data)?;
+    write(&out.join("index.json"), &json)?;
+    write(
+        &out.join("index.html"),
+        &render::render_dashboard_html(&data)?,
+    )?;
+    Ok(data)
+}
+
+/// A run directory and the files found in it.
+struct RunDir {
+    name: String,
+    log: PathBuf,
+    ops: PathBuf,
+    meta: Option<PathBuf>,
+}
+
+/// Subdirectories of `dir` that hold a log and an ops spec, sorted by name.
+fn discover(dir: &Path) -> Result<Vec<RunDir>> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| Error::io("read runs directory", dir.display().to_string(), e))?;
+    let mut runs = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| Error::io("read runs directory", dir.display().to_string(), e))?
+            .path();
+        if !path.is_dir() {
+            continue;
+        }
+        let find = |names: &[&str]| names.iter().map(|n| path.join(n)).find(|p| p.is_file());
+        let (Some(log), Some(ops)) = (find(LOG_NAMES), find(OPS_NAMES)) else {
+            continue;
+        };
+        let meta = Some(path.join(META_NAME)).filter(|p| p.is_file());
+        runs.push(RunDir {
+            name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            log,
+            ops,
+            meta,
+        });
+    }
+    runs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(runs)
+}
+
+/// Load one run and write its report; failures end up in `error`.
+fn load_run(
+    run: RunDir,
+    reports_dir: &Path,
+    opts: &ParseOptions,
+) -> (RunSummary, Option<ReportData>) {
+    let mut summary = RunSummary {
+        name: run.name.clone(),
+        meta: BTreeMap::new(),
+        report: None,
+        total_mapped_ms: 0.0,
+        total_mapped_activations: 0,
+        coverage: None,
+        top_rule: None,
+        top_node: None,
+        warnings: 0,
+        error: None,
+    };
+
+    let loaded = (|| -> Result<ReportData> {
+        if let Some(meta) = &run.meta {
+            summary.meta = read_meta(meta)?;
+        }
+        let profile =
+            Profile::load_with(&run.log.to_string_lossy(), &run.ops.to_string_lossy(), opts)?;
+        let report = format!("runs/{}.html", run.name);
+        HtmlFile::new(reports_dir.join(format!("{}.html", run.name))).write(&profile.data)?;
+        summary.report = Some(report);
+        Ok(profile.data)
+    })();
+
+    match loaded {
+        Ok(data) => {
+            summarize(&mut summary, &data);
+            (summary, Some(data))
+        }
+        Err(err) => {
+            summary.error = Some(format!("{:#}", err));
+            (summary, None)
+        }
+    }
+}
+
+fn read_meta(path: &Path) -> Result<BTreeMap<String, serde_json::Value>> {
+    let name = path.display().to_string();
+    let text = input::read_to_string(&name, "read run metadata")?;
+    Ok(serde_json::from_str(&text).map_err(|e| Error::schema(&name, e))?)
+}
+
+fn summarize(summary: &mut RunSummary, data: &ReportData) {
+    let t = &data.totals;
+    summary.total_mapped_ms = t.total_mapped_ms;
+    summary.total_mapped_activations = t.total_mapped_activations;
+    summary.coverage =
+        (t.operators_in_log > 0).then(|| t.operators_mapped as f64 / t.operators_in_log as f64);
+    summary.top_rule = data
+        .rules
+        .iter()
+        .max_by(|a, b| a.total_active_ms.total_cmp(&b.total_active_ms))
+        .map(|r| TopEntry {
+            label: r.text.clone(),
+            ms: r.total_active_ms,
+        });
+    summary.top_node = data
+        .nodes
+        .values()
+        .max_by(|a, b| a.self_total_active_ms.total_cmp(&b.self_total_active_ms))
+        .map(|n| TopEntry {
+            label: n.label.clone(),
+            ms: n.self_total_active_ms,
+        });
+    summary.warnings = data
+        .diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Warning)
+        .count();
+}
+
+/// The `top` nodes by their highest self time in any run.
+fn node_trends(reports: &[Option<&ReportData>], top: usize) -> Vec<NodeTrend> {
+    let mut trends: BTreeMap<&str, NodeTrend> = BTreeMap::new();
+    for (i, data) in reports.iter().enumerate() {
+        let Some(data) = data else { continue };
+        for node in data.nodes.values() {
+            let trend = trends.entry(&node.name).or_insert_with(|| NodeTrend {
+                name: node.name.clone(),
+                label: node.label.clone(),
+                block: node.block.clone(),
+                ms: vec![None; reports.len()],
+            });
+            trend.ms[i] = Some(node.self_total_active_ms);
+        }
+    }
+    let peak = |t: &NodeTrend| t.ms.iter().flatten().copied().fold(0.0, f64::max);
+    let mut trends: Vec<NodeTrend> = trends.into_values().collect();
+    trends.sort_by(|a, b| {
+        peak(b)
+            .total_cmp(&peak(a))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    trends.truncate(top);
+    trends
+}
+
+/// Numbers before strings, both before missing values.
+fn compare_meta(
+    a: Option<&serde_json::Value>,
+    b: Option<&serde_json::Value>,
+) -> std::cmp::Ordering {
+    use serde_json::Value;
+    use std::cmp::Ordering;
+    match (a, b) {
+        (Some(Value::Number(x)), Some(Value::Number(y))) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            x.total_cmp(&y)
+        }
+        (Some(Value::Number(_)), Some(_)) => Ordering::Less,
+        (Some(_), Some(Value::Number(_))) => Ordering::Greater,
+        (Some(x), Some(y)) => meta_text(x).cmp(&meta_text(y)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn meta_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn write(path: &Path, text: &str) -> Result<()> {
+    fs::write(path, text).map_err(|e| Error::io("write", path.display().to_string(), e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    const LOG_HEADER: &str = "addr activations total_active_ms name\n";
+
+    /// A runs directory with one subdirectory per `(name, log rows, meta)`.
+    fn runs_dir(tag: &str, runs: &[(&str, &str, Option<serde_json::Value>)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dashboard-{}-{}", tag, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let ops = json!({
+            "nodes": [
+                {"id": 0, "name": "e: input", "block": "input", "operators": [[0, 1]], "parents": []},
+                {"id": 1, "name": "r: map", "block": "stratum 0", "fingerprint": "0xa",
+                 "operators": [[0, 2]], "parents": [0]},
+            ],
+            "rules": [{"text": "r(x) :- e(x).", "plan_tree": [{"fingerprint": "0xa", "parents": []}]}],
+        });
+        for (name, rows, meta) in runs {
+            let run = dir.join("runs").join(name);
+            fs::create_dir_all(&run).unwrap();
+            fs::write(run.join("ops.json"), ops.to_string()).unwrap();
+            fs::write(run.join("profile.log"), format!("{}{}", LOG_HEADER, rows)).unwrap();
+            if let Some(meta) = meta {
+                fs::write(run.join(META_NAME), meta.to_string()).unwrap();
+            }
+        }
+        dir
+    }
+
+    #[test]
+    fn index_summarizes_each_run() {
+        let dir = runs_dir(
+            "index",
+            &[
+                (
+                    "a-large",
+                    "[0, 1]  2  1.000  Input\n[0, 2]  4  9.000  Map\n",
+                    Some(json!({"threads": 8, "dataset": "large"})),
+                ),
+                (
+                    "b-small",
+                    "[0, 1]  1  0.500  Input\n[0, 2]  2  1.500  Map\n[0, 3]  1  2.000  Unmapped\n",
+                    Some(json!({"threads": 2, "dataset": "small"})),
+                ),
+            ],
+        );
+        let out = dir.join("out");
+        let data = write_dashboard(
+            &dir.join("runs"),
+            &out,
+            &ParseOptions::default(),
+            10,
+            Some("threads"),
+        );
+        let index = fs::read_to_string(out.join("index.json"));
+        let report = out.join("runs").join("a-large.html").is_file();
+        fs::remove_dir_all(&dir).unwrap();
+        let data = data.unwrap();
+
+        // Sorted by `threads`, numerically.
+        let runs: Vec<_> = data
+            .runs
+            .iter()
+            .map(|r| {
+                (
+                    r.name.as_str(),
+                    r.meta["dataset"].as_str().unwrap(),
+                    r.report.as_deref(),
+                    r.total_mapped_ms,
+                    r.total_mapped_activations,
+                    r.coverage,
+                    r.error.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                (
+                    "b-small",
+                    "small",
+                    Some("runs/b-small.html"),
+                    2.0,
+                    3,
+                    Some(2.0 / 3.0),
+                    None
+                ),
+                (
+                    "a-large",
+                    "large",
+                    Some("runs/a-large.html"),
+                    10.0,
+                    6,
+                    Some(1.0),
+                    None
+                ),
+            ]
+        );
+        let top_rule = data.runs[1].top_rule.as_ref().unwrap();
+        assert_eq!(
+            (top_rule.label.as_str(), top_rule.ms),
+            ("r(x) :- e(x).", 9.0)
+        );
+        let top_node = data.runs[0].top_node.as_ref().unwrap();
+        assert_eq!((top_node.label.as_str(), top_node.ms), ("r: map", 1.5));
+
+        let trends: Vec<_> = data
+            .nodes
+            .iter()
+            .map(|t| (t.label.as_str(), t.ms.clone()))
+            .collect();
+        assert_eq!(
+            trends,
+            [
+                ("r: map", vec![Some(1.5), Some(9.0)]),
+                ("e: input", vec![Some(0.5), Some(1.0)]),
+            ]
+        );
+        assert!(data.diagnostics.is_empty());
+
+        let index: serde_json::Value = serde_json::from_str(&index.unwrap()).unwrap();
+        assert_eq!(index["runs"][0]["name"], "b-small");
+        assert_eq!(index["nodes"][0]["ms"], json!([1.5, 9.0]));
+        assert!(report);
+    }
+}
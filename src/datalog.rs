@@ -0,0 +1,428 @@
+//! Datalog rule text (`RuleView::text`) parsed into head and body relations,
+//! and the relation-level view built from it.
+//!
+//! The grammar is the subset FlowLog prints for its rules:
+//!
+//! ```text
+//! sssp(y, min(d + w)) :- sssp(x, d), edge(x, y, w), !blocked(y), d < 100.
+//! ```
+//!
+//! A head atom whose arguments may be aggregates (`count`, `sum`, `min`,
+//! `max`, `avg`), then `:-` and a comma-separated body of atoms, negated atoms
+//! (`!rel(..)` or `not rel(..)`) and comparisons. Arguments are kept as text.
+//! Text that does not fit is left unparsed; the rule is still shown, just not
+//! in the relation view.
+
+use crate::stats::Spread;
+use crate::view::{NameNodeView, RuleView};
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Aggregate functions recognized in head arguments.
+const AGGREGATES: &[&str] = &["count", "sum", "min", "max", "avg"];
+
+/// Comparison operators, longest first so `<=` is not read as `<`.
+const COMPARISONS: &[&str] = &["==", "!=", "<=", ">=", "<", ">", "="];
+
+/// `relation(arg, ...)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Atom {
+    /// Relation name.
+    pub relation: String,
+    /// Arguments, verbatim.
+    pub args: Vec<String>,
+}
+
+/// An aggregate in a head argument, e.g. `min(d + w)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Aggregate {
+    /// Aggregate function name, e.g. `min`.
+    pub function: String,
+    /// The aggregated expression, e.g. `d + w`.
+    pub expr: String,
+    /// Position among the head arguments.
+    pub position: usize,
+}
+
+/// One rule, split into its parts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParsedRule {
+    /// The derived atom.
+    pub head: Atom,
+    /// Positive body atoms, in rule order.
+    pub body: Vec<Atom>,
+    /// Negated body atoms, in rule order.
+    pub negated: Vec<Atom>,
+    /// Aggregates among the head arguments.
+    pub aggregates: Vec<Aggregate>,
+    /// Comparison literals, verbatim (e.g. `d < 100`).
+    pub comparisons: Vec<String>,
+}
+
+impl ParsedRule {
+    /// Relations the head depends on, positive or negated, without duplicates.
+    pub fn dependencies(&self) -> BTreeSet<&str> {
+        self.body
+            .iter()
+            .chain(&self.negated)
+            .map(|a| a.relation.as_str())
+            .collect()
+    }
+}
+
+/// Parse one rule; the error says what did not fit.
+///
+/// ```
+/// use flowlog_profile_viz::datalog::parse_rule;
+///
+/// let rule = parse_rule("tc(x, y) :- tc(x, z), arc(z, y).").unwrap();
+/// assert_eq!(rule.head.relation, "tc");
+/// assert_eq!(rule.body[1].args, ["z", "y"]);
+/// assert!(parse_rule("tc(x, y) :- ???").is_err());
+/// ```
+pub fn parse_rule(text: &str) -> Result<ParsedRule, String> {
+    let text = text.trim();
+    let text = text.strip_suffix('.').unwrap_or(text).trim_end();
+    let (head, body) = match text.split_once(":-") {
+        Some((head, body)) => (head, Some(body)),
+        None => (text, None),
+    };
+
+    let head = parse_atom(head.trim()).ok_or_else(|| format!("bad rule head `{}`", head.trim()))?;
+    let aggregates = head
+        .args
+        .iter()
+        .enumerate()
+        .filter_map(|(position, arg)| {
+            let (function, expr) = call(arg)?;
+            AGGREGATES.contains(&function).then(|| Aggregate {
+                function: function.to_string(),
+                expr: expr.trim().to_string(),
+                position,
+            })
+        })
+        .collect();
+
+    let mut rule = ParsedRule {
+        head,
+        body: Vec::new(),
+        negated: Vec::new(),
+        aggregates,
+        comparisons: Vec::new(),
+    };
+    for literal in body.map(split_top_level).unwrap_or_default() {
+        let literal = literal.trim();
+        let negated = literal
+            .strip_prefix('!')
+            .or_else(|| literal.strip_prefix("not "))
+            .map(str::trim_start);
+        if let Some(atom) = negated.and_then(parse_atom) {
+            rule.negated.push(atom);
+        } else if let Some(atom) = parse_atom(literal) {
+            rule.body.push(atom);
+        } else if COMPARISONS.iter().any(|op| literal.contains(op)) {
+            rule.comparisons.push(literal.to_string());
+        } else {
+            return Err(format!("bad body literal `{}`", literal));
+        }
+    }
+    Ok(rule)
+}
+
+/// `name(inner)` spanning all of `s`, with balanced parentheses.
+fn call(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim();
+    let open = s.find('(')?;
+    let name = s[..open].trim();
+    let inner = s[open + 1..].strip_suffix(')')?;
+    let valid_name = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    // `a(x) < b(y)` ends in `)` too; the inner text must close nothing early.
+    (valid_name && balanced(inner)).then_some((name, inner))
+}
+
+fn parse_atom(s: &str) -> Option<Atom> {
+    let (relation, inner) = call(s)?;
+    let args = if inner.trim().is_empty() {
+        Vec::new()
+    } else {
+        split_top_level(inner)
+            .into_iter()
+            .map(|a| a.trim().to_string())
+            .collect()
+    };
+    Some(Atom {
+        relation: relation.to_string(),
+        args,
+    })
+}
+
+/// Whether every `(` in `s` is closed within `s`, ignoring quoted text.
+fn balanced(s: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => {
+                depth -= 1;
+                if depth < 0 {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    depth == 0 && !quoted
+}
+
+/// Split at commas outside parentheses and quotes.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0i32, false, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// A relation with the rules deriving it and their combined cost.
+#[derive(Debug, Clone, Serialize)]
+pub struct RelationView {
+    /// Relation name.
+    pub name: String,
+    /// Texts of the rules with this relation as head; empty for inputs.
+    pub rules: Vec<String>,
+    /// Relations the rules read (positive or negated).
+    pub depends_on: Vec<String>,
+    /// Of `depends_on`, those read under negation.
+    pub negates: Vec<String>,
+    /// Relations whose rules read this one.
+    pub used_by: Vec<String>,
+    /// Aggregated over the distinct plan nodes of all rules deriving it.
+    pub activations: u64,
+    /// Self time of those plan nodes, in ms.
+    pub total_active_ms: f64,
+    /// `total_active_ms` over repeated runs; `None` for a single run.
+    pub ms_spread: Option<Spread>,
+}
+
+/// The relation dependency graph of the parsed rules, most expensive first.
+///
+/// Relations that only appear in rule bodies (inputs) are included with no
+/// rules and no cost. A plan node shared by several rules of one relation is
+/// counted once.
+pub fn build_relations(
+    rules: &[RuleView],
+    nodes: &BTreeMap<String, NameNodeView>,
+) -> Vec<RelationView> {
+    #[derive(Default)]
+    struct Acc<'a> {
+        rules: Vec<String>,
+        depends_on: BTreeSet<&'a str>,
+        negates: BTreeSet<&'a str>,
+        used_by: BTreeSet<&'a str>,
+        nodes: BTreeSet<&'a str>,
+    }
+
+    let mut relations: BTreeMap<&str, Acc> = BTreeMap::new();
+    for rule in rules {
+        let Some(parsed) = &rule.parsed else {
+            continue;
+        };
+        let head = parsed.head.relation.as_str();
+        for dep in parsed.dependencies() {
+            relations.entry(dep).or_default().used_by.insert(head);
+        }
+        let acc = relations.entry(head).or_default();
+        acc.rules.push(rule.text.clone());
+        acc.depends_on.extend(parsed.dependencies());
+        acc.negates
+            .extend(parsed.negated.iter().map(|a| a.relation.as_str()));
+        acc.nodes
+            .extend(rule.nodes.values().filter_map(|pn| pn.node.as_deref()));
+    }
+
+    let mut views: Vec<RelationView> = relations
+        .into_iter()
+        .map(|(name, acc)| {
+            let owned = acc.nodes.iter().filter_map(|n| nodes.get(*n));
+            let (activations, total_active_ms) = owned.fold((0, 0.0), |(a, ms), n| {
+                (a + n.self_activations, ms + n.self_total_active_ms)
+            });
+            let names = |set: BTreeSet<&str>| set.into_iter().map(str::to_string).collect();
+            RelationView {
+                name: name.to_string(),
+                rules: acc.rules,
+                depends_on: names(acc.depends_on),
+                negates: names(acc.negates),
+                used_by: names(acc.used_by),
+                activations,
+                total_active_ms,
+                ms_spread: None,
+            }
+        })
+        .collect();
+    views.sort_by(|a, b| {
+        b.total_active_ms
+            .total_cmp(&a.total_active_ms)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    views
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn atom(relation: &str, args: &[&str]) -> Atom {
+        Atom {
+            relation: relation.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn negation_both_spellings() {
+        for text in [
+            "reach(y) :- node(y), !blocked(y).",
+            "reach(y) :- node(y), not blocked(y).",
+            "reach(y) :- node(y), ! blocked(y)",
+        ] {
+            let rule = parse_rule(text).unwrap();
+            assert_eq!(rule.body, [atom("node", &["y"])], "{}", text);
+            assert_eq!(rule.negated, [atom("blocked", &["y"])], "{}", text);
+            assert!(rule.comparisons.is_empty(), "{}", text);
+            assert_eq!(
+                rule.dependencies().into_iter().collect::<Vec<_>>(),
+                ["blocked", "node"]
+            );
+        }
+    }
+
+    #[test]
+    fn comparisons_are_not_negation_or_atoms() {
+        let rule =
+            parse_rule("p(x, y) :- q(x, y), x != y, x <= 3, f(x) < g(y), \"a,b\" = y.").unwrap();
+        assert_eq!(rule.body, [atom("q", &["x", "y"])]);
+        assert!(rule.negated.is_empty());
+        assert_eq!(
+            rule.comparisons,
+            ["x != y", "x <= 3", "f(x) < g(y)", "\"a,b\" = y"]
+        );
+    }
+
+    #[test]
+    fn head_aggregates() {
+        let rule = parse_rule("sssp(y, min(d + w)) :- sssp(x, d), edge(x, y, w).").unwrap();
+        assert_eq!(rule.head, atom("sssp", &["y", "min(d + w)"]));
+        assert_eq!(
+            rule.aggregates,
+            [Aggregate {
+                function: "min".to_string(),
+                expr: "d + w".to_string(),
+                position: 1,
+            }]
+        );
+        assert_eq!(rule.body[1], atom("edge", &["x", "y", "w"]));
+
+        let rule = parse_rule("stats(count(x), sum(f(x, y)), g(x)) :- r(x, y).").unwrap();
+        let functions: Vec<(&str, &str, usize)> = rule
+            .aggregates
+            .iter()
+            .map(|a| (a.function.as_str(), a.expr.as_str(), a.position))
+            .collect();
+        // `g` is not an aggregate.
+        assert_eq!(functions, [("count", "x", 0), ("sum", "f(x, y)", 1)]);
+    }
+
+    #[test]
+    fn facts_and_unparsable_rules() {
+        let fact = parse_rule("edge(1, 2).").unwrap();
+        assert_eq!(fact.head, atom("edge", &["1", "2"]));
+        assert!(fact.body.is_empty() && fact.dependencies().is_empty());
+        assert_eq!(parse_rule("start()").unwrap().head, atom("start", &[]));
+
+        for (text, error) in [
+            ("tc(x, y) :- ???", "bad body literal `???`"),
+            ("tc x y :- arc(x, y)", "bad rule head `tc x y`"),
+            ("f(x) < g(y) :- r(x, y)", "bad rule head `f(x) < g(y)`"),
+        ] {
+            assert_eq!(parse_rule(text).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn relations_count_shared_plan_nodes_once() {
+        use crate::log::parse_log;
+        use crate::ops::OpsSpec;
+        use crate::profile::Profile;
+
+        // Both `tc` rules scan `arc` through the same plan node.
+        let ops: OpsSpec = serde_json::from_str(
+            r#"{
+            "nodes": [
+                {"id": 0, "name": "arc", "block": "input", "operators": [[0, 1]], "parents": []},
+                {"id": 1, "name": "scan", "block": "s", "fingerprint": "0xa", "operators": [[0, 2]], "parents": [0]},
+                {"id": 2, "name": "join", "block": "s", "fingerprint": "0xb", "operators": [[0, 3]], "parents": [1]},
+                {"id": 3, "name": "count", "block": "s", "fingerprint": "0xc", "operators": [[0, 4]], "parents": [2]}
+            ],
+            "rules": [
+                {"text": "tc(x, y) :- arc(x, y).", "plan_tree": [{"fingerprint": "0xa", "parents": []}]},
+                {"text": "tc(x, y) :- tc(x, z), arc(z, y), !blocked(y).", "plan_tree": [
+                    {"fingerprint": "0xa", "parents": []},
+                    {"fingerprint": "0xb", "parents": ["0xa"]}
+                ]},
+                {"text": "reach(count(y)) :- tc(x, y).", "plan_tree": [{"fingerprint": "0xc", "parents": []}]}
+            ]}"#,
+        )
+        .unwrap();
+        let log = "[0, 1] 1 1 A\n[0, 2] 2 10 Scan\n[0, 3] 3 100 Join\n[0, 4] 4 1000 Count\n";
+        let data = Profile::from_parts(&ops, parse_log(log.as_bytes(), "log").unwrap())
+            .unwrap()
+            .data;
+
+        let summary: Vec<(&str, usize, u64, f64)> = data
+            .relations
+            .iter()
+            .map(|r| {
+                (
+                    r.name.as_str(),
+                    r.rules.len(),
+                    r.activations,
+                    r.total_active_ms,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("reach", 1, 4, 1000.0),
+                ("tc", 2, 5, 110.0),
+                ("arc", 0, 0, 0.0),
+                ("blocked", 0, 0, 0.0),
+            ]
+        );
+        let tc = &data.relations[1];
+        assert_eq!(tc.depends_on, ["arc", "blocked", "tc"]);
+        assert_eq!(tc.negates, ["blocked"]);
+        assert_eq!(tc.used_by, ["reach", "tc"]);
+        assert_eq!(data.relations[2].used_by, ["tc"]);
+    }
+}
@@ -1,13 +1,156 @@
-//! Shared diagnostics helpers for consistent, colored output.
+//! Shared diagnostics helpers for consistent output on stderr.
+//!
+//! This is the only place that decides how diagnostics look: colored text
+//! (subject to `set_color`) or one JSON object per line (`set_format`).
+//! Error and warning *messages* elsewhere are plain strings.
+//!
+//! Warnings found while loading a profile are also collected as [`Diagnostic`]s
+//! into ReportData, so they survive into the generated report.
+
+use crate::error;
 
 use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Output format for diagnostics printed to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Colored, human-readable lines.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
 
-/// Format a warning message with a colored prefix.
+/// Choose how diagnostics are printed from now on.
+pub fn set_format(format: Format) {
+    JSON.store(format == Format::Json, Ordering::Relaxed);
+}
+
+/// Force colored output on or off.
+pub fn set_color(enabled: bool) {
+    colored::control::set_override(enabled);
+}
+
+/// Whether color should be used by default: stderr is a terminal and
+/// `NO_COLOR` is unset or empty.
+pub fn color_auto() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    !no_color && std::io::stderr().is_terminal()
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing; the report is complete.
+    Info,
+    /// Part of the report may be missing or misleading.
+    Warning,
+}
+
+/// A non-fatal finding about the inputs, optionally tied to a node and/or addr.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// How serious the finding is.
+    pub severity: Severity,
+    /// Stable kebab-case identifier, e.g. `addr-missing-from-log`.
+    pub code: String,
+    /// Human-readable description.
+    pub message: String,
+    /// Node the finding is about, if any.
+    pub node: Option<String>,
+    /// Operator address the finding is about, if any.
+    pub addr: Option<Vec<u32>>,
+}
+
+impl Diagnostic {
+    /// A warning with stable `code`.
+    pub fn warning(code: &str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, code, message)
+    }
+
+    /// An informational finding with stable `code`.
+    pub fn info(code: &str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Info, code, message)
+    }
+
+    fn new(severity: Severity, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: code.to_string(),
+            message: message.into(),
+            node: None,
+            addr: None,
+        }
+    }
+
+    /// Tie the finding to a node.
+    pub fn with_node(mut self, node: impl Into<String>) -> Self {
+        self.node = Some(node.into());
+        self
+    }
+
+    /// Tie the finding to an operator address.
+    pub fn with_addr(mut self, addr: &[u32]) -> Self {
+        self.addr = Some(addr.to_vec());
+        self
+    }
+}
+
+/// Print a collected diagnostic; info-level ones are kept for the report only.
+pub fn emit(diag: &Diagnostic) {
+    if diag.severity < Severity::Warning {
+        return;
+    }
+    if JSON.load(Ordering::Relaxed) {
+        eprintln!("{}", serde_json::to_value(diag).unwrap_or_default());
+    } else {
+        eprintln!("{} {}", "WARN".yellow().bold(), diag.message);
+    }
+}
+
+/// Print a warning.
 pub fn warn(message: impl AsRef<str>) {
-    eprintln!("{} {}", "WARN".yellow().bold(), message.as_ref());
+    if JSON.load(Ordering::Relaxed) {
+        let line = json!({ "severity": "warning", "message": message.as_ref() });
+        eprintln!("{}", line);
+    } else {
+        eprintln!("{} {}", "WARN".yellow().bold(), message.as_ref());
+    }
 }
 
-/// Format an error message with a colored prefix.
-pub fn error_message(message: impl AsRef<str>) -> String {
-    format!("{} {}", "ERROR".red().bold(), message.as_ref())
+/// Print an error with its context chain.
+pub fn error(err: &anyhow::Error) {
+    if JSON.load(Ordering::Relaxed) {
+        eprintln!("{}", error_json(err));
+    } else {
+        eprintln!("{} {:#}", "ERROR".red().bold(), err);
+    }
+}
+
+/// Machine-readable form of an error. Kind and location come from the typed
+/// `error::Error` in the chain (if any); `context` lists the messages added
+/// around it and `causes` the underlying errors beneath it.
+pub fn error_json(err: &anyhow::Error) -> serde_json::Value {
+    let chain: Vec<&(dyn std::error::Error + 'static)> = err.chain().collect();
+    let typed_at = chain
+        .iter()
+        .position(|e| e.downcast_ref::<error::Error>().is_some())
+        .unwrap_or(chain.len().saturating_sub(1));
+    let typed = error::find(err);
+
+    let messages: Vec<String> = chain.iter().map(|e| e.to_string()).collect();
+    json!({
+        "severity": "error",
+        "kind": typed.map(|e| e.kind()).unwrap_or("other"),
+        "message": messages.get(typed_at),
+        "location": typed.and_then(|e| e.location()),
+        "context": &messages[..typed_at.min(messages.len())],
+        "causes": messages.get(typed_at + 1..).unwrap_or_default(),
+    })
 }
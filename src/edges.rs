@@ -0,0 +1,246 @@
+//! Data volume on DAG edges, from the `Channels` and `Messages` events of a
+//! raw Timely capture (see [`crate::events`]).
+//!
+//! Operator-to-operator volumes are mapped through ops.json to nodes. Volume
+//! between two nodes that `dag_parents` links becomes that edge's count;
+//! traffic between operators of the same node is internal and not shown.
+
+use crate::addr::Addr;
+use crate::diagnostics::Diagnostic;
+use crate::events::ChannelVolume;
+use crate::ops::ValidatedOps;
+use crate::view::ReportData;
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Records and messages sent along one DAG edge, parent to child.
+#[derive(Debug, Clone, Serialize)]
+pub struct EdgeView {
+    /// Parent node name (the producer).
+    pub source: String,
+    /// Child node name (the consumer).
+    pub target: String,
+    /// Messages sent along the edge, over all workers.
+    pub messages: u64,
+    /// Records in those messages.
+    pub records: u64,
+}
+
+/// Set `edges` on `data`: every DAG edge with its volume, heaviest first.
+///
+/// Leaves `edges` empty when `channels` is (the log had no channel events).
+pub fn attach_edges(
+    data: &mut ReportData,
+    ops: &ValidatedOps,
+    channels: &[ChannelVolume],
+) -> Vec<Diagnostic> {
+    if channels.is_empty() {
+        return Vec::new();
+    }
+    let owner: HashMap<&Addr, String> = ops
+        .nodes
+        .iter()
+        .flat_map(|(id, spec)| spec.operators.iter().map(move |a| (a, id.to_string())))
+        .collect();
+
+    let mut edges: BTreeMap<(&str, &str), (u64, u64)> = BTreeMap::new();
+    for node in data.nodes.values() {
+        for parent in &node.dag_parents {
+            edges.insert((parent, &node.name), (0, 0));
+        }
+    }
+    let (mut off_dag, mut off_dag_records) = (0, 0);
+    let (mut unmapped, mut unmapped_records) = (0, 0);
+    for c in channels {
+        let (Some(source), Some(target)) = (owner.get(&c.source), owner.get(&c.target)) else {
+            unmapped += 1;
+            unmapped_records += c.records;
+            continue;
+        };
+        if source == target {
+            continue;
+        }
+        match edges.get_mut(&(source.as_str(), target.as_str())) {
+            Some((messages, records)) => {
+                *messages += c.messages;
+                *records += c.records;
+            }
+            None => {
+                off_dag += 1;
+                off_dag_records += c.records;
+            }
+        }
+    }
+
+    let mut views: Vec<EdgeView> = edges
+        .into_iter()
+        .map(|((source, target), (messages, records))| EdgeView {
+            source: source.to_string(),
+            target: target.to_string(),
+            messages,
+            records,
+        })
+        .collect();
+    views.sort_by(|a, b| {
+        b.records
+            .cmp(&a.records)
+            .then(b.messages.cmp(&a.messages))
+            .then_with(|| (&a.source, &a.target).cmp(&(&b.source, &b.target)))
+    });
+    data.edges = views;
+
+    let mut findings = Vec::new();
+    if unmapped > 0 {
+        findings.push(Diagnostic::info(
+            "edges-unmapped-channels",
+            format!(
+                "{} operator pair(s) carrying {} record(s) involve operators not assigned to any node",
+                unmapped, unmapped_records
+            ),
+        ));
+    }
+    if off_dag > 0 {
+        findings.push(Diagnostic::info(
+            "edges-off-dag",
+            format!(
+                "{} node pair(s) carrying {} record(s) are not joined by a DAG edge (e.g. feedback)",
+                off_dag, off_dag_records
+            ),
+        ));
+    }
+    findings
+}
+
+/// Mean volume per operator pair over repeated runs, a pair missing from a
+/// run counting as zero there (like [`crate::log::mean_index`]).
+pub fn mean_channels(runs: &[Vec<ChannelVolume>]) -> Vec<ChannelVolume> {
+    let n = runs.len().max(1) as f64;
+    let mut sums: BTreeMap<(&Addr, &Addr), (u64, u64)> = BTreeMap::new();
+    for c in runs.iter().flatten() {
+        let sum = sums.entry((&c.source, &c.target)).or_default();
+        sum.0 += c.messages;
+        sum.1 += c.records;
+    }
+    sums.into_iter()
+        .map(|((source, target), (messages, records))| ChannelVolume {
+            source: source.clone(),
+            target: target.clone(),
+            messages: (messages as f64 / n).round() as u64,
+            records: (records as f64 / n).round() as u64,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::parse_log;
+    use crate::ops::OpsSpec;
+    use crate::profile::Profile;
+    use pretty_assertions::assert_eq;
+
+    fn volume(source: &[u32], target: &[u32], messages: u64, records: u64) -> ChannelVolume {
+        ChannelVolume {
+            source: Addr::new(source.to_vec()),
+            target: Addr::new(target.to_vec()),
+            messages,
+            records,
+        }
+    }
+
+    /// A chain `0 -> 1 -> 2`, node 0 owning two operators.
+    fn report() -> (ReportData, ValidatedOps) {
+        let ops: OpsSpec = serde_json::from_str(
+            r#"{"nodes": [
+                {"id": 0, "name": "scan", "block": "b", "operators": [[0, 1], [0, 2]], "parents": []},
+                {"id": 1, "name": "join", "block": "b", "operators": [[0, 3]], "parents": [0]},
+                {"id": 2, "name": "sink", "block": "b", "operators": [[0, 4]], "parents": [1]}
+            ]}"#,
+        )
+        .unwrap();
+        let log = "[0, 1] 1 1 A\n[0, 2] 1 1 B\n[0, 3] 1 1 C\n[0, 4] 1 1 D\n";
+        let data = Profile::from_parts(&ops, parse_log(log.as_bytes(), "log").unwrap())
+            .unwrap()
+            .data;
+        (data, ops.validate_and_build().unwrap())
+    }
+
+    fn edges(data: &ReportData) -> Vec<(&str, &str, u64, u64)> {
+        data.edges
+            .iter()
+            .map(|e| (e.source.as_str(), e.target.as_str(), e.messages, e.records))
+            .collect()
+    }
+
+    #[test]
+    fn channels_map_onto_dag_edges() {
+        let (mut data, ops) = report();
+        let findings = attach_edges(
+            &mut data,
+            &ops,
+            &[
+                // Inside node 0: not an edge.
+                volume(&[0, 1], &[0, 2], 9, 90),
+                volume(&[0, 2], &[0, 3], 2, 10),
+                volume(&[0, 1], &[0, 3], 1, 1),
+                volume(&[0, 3], &[0, 4], 1, 5),
+                // Feedback from sink to join runs against the DAG.
+                volume(&[0, 4], &[0, 3], 1, 2),
+                // No node owns [0, 9].
+                volume(&[0, 9], &[0, 3], 1, 7),
+            ],
+        );
+
+        assert_eq!(edges(&data), [("0", "1", 3, 11), ("1", "2", 1, 5)]);
+        let findings: Vec<(&str, &str)> = findings
+            .iter()
+            .map(|d| (d.code.as_str(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (
+                    "edges-unmapped-channels",
+                    "1 operator pair(s) carrying 7 record(s) involve operators not assigned to any node"
+                ),
+                (
+                    "edges-off-dag",
+                    "1 node pair(s) carrying 2 record(s) are not joined by a DAG edge (e.g. feedback)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn edges_without_traffic_are_listed_with_zero() {
+        let (mut data, ops) = report();
+        let findings = attach_edges(&mut data, &ops, &[volume(&[0, 3], &[0, 4], 1, 5)]);
+        assert_eq!(edges(&data), [("1", "2", 1, 5), ("0", "1", 0, 0)]);
+        assert!(findings.is_empty());
+
+        // No channel events at all: no edge view.
+        let (mut data, ops) = report();
+        assert!(attach_edges(&mut data, &ops, &[]).is_empty());
+        assert!(data.edges.is_empty());
+    }
+
+    #[test]
+    fn mean_counts_missing_pairs_as_zero() {
+        let mean = mean_channels(&[
+            vec![
+                volume(&[0, 1], &[0, 3], 2, 10),
+                volume(&[0, 3], &[0, 4], 1, 3),
+            ],
+            vec![volume(&[0, 1], &[0, 3], 4, 20)],
+        ]);
+        assert_eq!(
+            mean,
+            [
+                volume(&[0, 1], &[0, 3], 3, 15),
+                volume(&[0, 3], &[0, 4], 1, 2)
+            ]
+        );
+        assert!(mean_channels(&[]).is_empty());
+    }
+}
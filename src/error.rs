@@ -0,0 +1,247 @@
+//! Typed errors for every stage of the pipeline.
+//!
+//! Messages are plain text; underlying causes (I/O, JSON) are exposed through
+//! `source()` rather than repeated in the message. Coloring and machine-readable
+//! output are decided only when the CLI finally prints an error (see `diagnostics`).
+//!
+//! Functions still return `anyhow::Result`; an [`Error`] sits at the root of the
+//! chain and can be recovered with `err.downcast_ref::<Error>()` or [`find`].
+
+use serde::Serialize;
+use std::fmt;
+use std::io;
+
+/// Where in a file an error was found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Location {
+    /// File name, or `<stdin>`.
+    pub path: String,
+    /// 1-based line, when known.
+    pub line: Option<usize>,
+    /// 1-based column, when known.
+    pub column: Option<usize>,
+}
+
+impl Location {
+    /// A whole file.
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            line: None,
+            column: None,
+        }
+    }
+
+    /// A line in a file.
+    pub fn line(path: impl Into<String>, line: usize) -> Self {
+        Self {
+            path: path.into(),
+            line: Some(line),
+            column: None,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(col) = self.column {
+                write!(f, ":{}", col)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A fatal input or output error, with a stable [`Error::kind`] for tooling.
+#[derive(Debug)]
+pub enum Error {
+    /// A line of a profile log (or another line-based input) could not be parsed.
+    Parse {
+        /// File and line.
+        location: Location,
+        /// What is wrong with the line.
+        message: String,
+    },
+    /// A JSON input (ops.json, run metadata, ...) is not valid JSON or does not
+    /// match the expected shape.
+    Schema {
+        /// File, with the line and column serde reported.
+        location: Location,
+        /// The serde error.
+        source: serde_json::Error,
+    },
+    /// Inputs are well-formed but inconsistent: ops.json (duplicate ids, missing
+    /// parents, ...) or the combination of inputs given (two of them on stdin, ...).
+    Validation {
+        /// The file, once known (see [`Error::in_file`]).
+        location: Option<Location>,
+        /// What is inconsistent.
+        message: String,
+    },
+    /// ops.json and the log disagree about operator ownership.
+    Mapping {
+        /// What disagrees.
+        message: String,
+    },
+    /// Reading or writing a file failed.
+    Io {
+        /// File name, or `<stdin>`/`<stdout>`.
+        location: Location,
+        /// What was attempted, e.g. "read log".
+        action: &'static str,
+        /// The underlying I/O error.
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// A malformed line `line` of `path`.
+    pub fn parse(path: &str, line: usize, message: impl Into<String>) -> Self {
+        Error::Parse {
+            location: Location::line(path, line),
+            message: message.into(),
+        }
+    }
+
+    /// `path` is not valid JSON of the expected shape.
+    pub fn schema(path: &str, source: serde_json::Error) -> Self {
+        Error::Schema {
+            location: Location {
+                path: path.to_string(),
+                line: Some(source.line()).filter(|l| *l > 0),
+                column: Some(source.column()).filter(|c| *c > 0),
+            },
+            source,
+        }
+    }
+
+    /// Inputs that are well-formed but inconsistent.
+    pub fn validation(message: impl Into<String>) -> Self {
+        Error::Validation {
+            location: None,
+            message: message.into(),
+        }
+    }
+
+    /// Inputs that disagree about operator ownership.
+    pub fn mapping(message: impl Into<String>) -> Self {
+        Error::Mapping {
+            message: message.into(),
+        }
+    }
+
+    /// Failed to `action` (e.g. "read log") on `path`.
+    pub fn io(action: &'static str, path: impl Into<String>, source: io::Error) -> Self {
+        Error::Io {
+            location: Location::file(path),
+            action,
+            source,
+        }
+    }
+
+    /// Attach a file to a validation error that was raised without one.
+    pub fn in_file(self, path: &str) -> Self {
+        match self {
+            Error::Validation {
+                location: None,
+                message,
+            } => Error::Validation {
+                location: Some(Location::file(path)),
+                message,
+            },
+            other => other,
+        }
+    }
+
+    /// Stable, lowercase category name for tooling.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Parse { .. } => "parse",
+            Error::Schema { .. } => "schema",
+            Error::Validation { .. } => "validation",
+            Error::Mapping { .. } => "mapping",
+            Error::Io { .. } => "io",
+        }
+    }
+
+    /// Where the error was found, when it is tied to a file.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Error::Parse { location, .. }
+            | Error::Schema { location, .. }
+            | Error::Io { location, .. } => Some(location),
+            Error::Validation { location, .. } => location.as_ref(),
+            Error::Mapping { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse { location, message } => {
+                write!(f, "parse error at {}: {}", location, message)
+            }
+            Error::Schema { location, .. } => write!(f, "invalid JSON file {}", location.path),
+            Error::Validation {
+                location: Some(location),
+                message,
+            } => write!(f, "invalid input {}: {}", location, message),
+            Error::Validation {
+                location: None,
+                message,
+            } => write!(f, "invalid input: {}", message),
+            Error::Mapping { message } => write!(f, "{}", message),
+            Error::Io {
+                location, action, ..
+            } => write!(f, "{} {}", action, location.path),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Schema { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Find the typed error in an anyhow chain, if there is one.
+pub fn find(err: &anyhow::Error) -> Option<&Error> {
+    err.chain().find_map(|e| e.downcast_ref::<Error>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn located_errors_name_their_file() {
+        let err = Error::validation("duplicate node id 3").in_file("sizes.json");
+        assert_eq!(
+            err.to_string(),
+            "invalid input sizes.json: duplicate node id 3"
+        );
+        assert_eq!(err.location(), Some(&Location::file("sizes.json")));
+
+        let err = Error::validation("no runs");
+        assert_eq!(err.to_string(), "invalid input: no runs");
+        assert_eq!(err.location(), None);
+
+        let err = Error::io("read log file", "<stdin>", io::ErrorKind::NotFound.into());
+        assert_eq!(err.to_string(), "read log file <stdin>");
+        assert_eq!(err.location(), Some(&Location::file("<stdin>")));
+        assert_eq!(err.kind(), "io");
+
+        let err = Error::parse("log", 7, "bad row");
+        assert_eq!(err.to_string(), "parse error at log:7: bad row");
+        assert_eq!(err.location(), Some(&Location::line("log", 7)));
+    }
+}
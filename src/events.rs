@@ -0,0 +1,752 @@
+//! Raw Timely logging events as a log source.
+//!
+//! Instead of the pre-aggregated profile table, read the events Timely's
+//! logging emits and derive the per-operator numbers ourselves. A capture is a
+//! sequence of `(Duration, worker, TimelyEvent)` records, either
+//!
+//! - JSON lines, one record per line as serde writes it, e.g.
+//!   `[{"secs":0,"nanos":1500},0,{"Schedule":{"id":3,"start_stop":"Start"}}]`, or
+//! - bincode (1.x default options), records back to back.
+//!
+//! `Operates` supplies operator addresses and names; `Schedule` start/stop pairs
+//! give activations, total and maximum activation time; `Shutdown` closes an
+//! operator. `Channels` and sent `Messages` give the data volume between
+//! operators (see [`ChannelVolume`]). Everything else is read (bincode is not
+//! self-describing) and ignored.
+
+use crate::Result;
+use crate::addr::Addr;
+use crate::error::Error;
+use crate::histogram::Histogram;
+use crate::log::{LogIndex, LogRow, ParseStats, ParsedLog};
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::BufRead;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How a raw event capture is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventEncoding {
+    /// One JSON `[time, worker, event]` array per line.
+    JsonLines,
+    /// Timely's bincode capture format.
+    Bincode,
+}
+
+/// Whole-capture statistics, beyond what fits in per-operator rows.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EventStats {
+    /// Records read, including event kinds we do not use.
+    pub events: usize,
+    /// Distinct workers seen.
+    pub workers: usize,
+    /// From the first to the last event timestamp, over all workers.
+    pub span_ms: f64,
+    /// `Schedule` stops without a start, and starts never stopped.
+    pub unmatched_schedules: usize,
+    /// `Schedule` events for operator ids no `Operates` event described.
+    pub unknown_operators: usize,
+}
+
+// Mirrors of timely::logging's event types (timely 0.12 layout). Bincode is not
+// self-describing, so every variant must be spelled out to be skipped, even the
+// ones we never look at.
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+enum TimelyEvent {
+    Operates(OperatesEvent),
+    Channels(ChannelsEvent),
+    PushProgress(PushProgressEvent),
+    Messages(MessagesEvent),
+    Schedule(ScheduleEvent),
+    Shutdown(ShutdownEvent),
+    Application(ApplicationEvent),
+    GuardedMessage(GuardedMessageEvent),
+    GuardedProgress(GuardedProgressEvent),
+    CommChannels(CommChannelsEvent),
+    Input(InputEvent),
+    Park(ParkEvent),
+    Text(String),
+}
+
+/// Variants this module consumes; other JSON events are skipped unparsed, so
+/// newer Timely versions with extra variants still load.
+const CONSUMED: &[&str] = &["Operates", "Channels", "Messages", "Schedule", "Shutdown"];
+
+/// Data sent from one operator to another, over every channel between them.
+///
+/// Channels into or out of a nested scope are followed through the scope
+/// boundary, so the endpoints are always the producing and consuming
+/// operators, never the scope itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelVolume {
+    /// Sending operator.
+    pub source: Addr,
+    /// Receiving operator.
+    pub target: Addr,
+    /// Messages sent, over all workers.
+    pub messages: u64,
+    /// Records in those messages.
+    pub records: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct OperatesEvent {
+    id: usize,
+    addr: Vec<usize>,
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct ChannelsEvent {
+    id: usize,
+    scope_addr: Vec<usize>,
+    source: (usize, usize),
+    target: (usize, usize),
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+struct PushProgressEvent {
+    op_id: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+struct MessagesEvent {
+    is_send: bool,
+    channel: usize,
+    source: usize,
+    target: usize,
+    seq_no: usize,
+    length: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct ScheduleEvent {
+    id: usize,
+    start_stop: StartStop,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct ShutdownEvent {
+    id: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+struct ApplicationEvent {
+    id: usize,
+    is_start: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+struct GuardedMessageEvent {
+    is_start: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+struct GuardedProgressEvent {
+    is_start: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+struct CommChannelsEvent {
+    identifier: usize,
+    kind: CommChannelKind,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+enum CommChannelKind {
+    Progress,
+    Data,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+struct InputEvent {
+    start_stop: StartStop,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+enum ParkEvent {
+    Park(Option<Duration>),
+    Unpark,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[cfg_attr(test, derive(Serialize))]
+enum StartStop {
+    Start,
+    Stop,
+}
+
+type Record<E> = (Duration, usize, E);
+
+/// Parse a capture into the same index the profile table produces.
+///
+/// Each row carries a `max_activation_ms` metric, an activation duration
+/// histogram and the workers it was seen on. With `lenient`, undecodable JSON
+/// lines are skipped and a truncated bincode tail ends the capture; both are
+/// counted in the returned stats.
+pub fn parse_events(
+    mut reader: impl BufRead,
+    path: &str,
+    encoding: EventEncoding,
+    lenient: bool,
+) -> Result<ParsedLog> {
+    let mut acc = Accumulator::default();
+    let mut stats = ParseStats::default();
+
+    match encoding {
+        EventEncoding::JsonLines => {
+            let mut line = String::new();
+            let mut lno = 0;
+            loop {
+                line.clear();
+                let n = reader
+                    .read_line(&mut line)
+                    .map_err(|e| Error::io("read log file", path, e))?;
+                if n == 0 {
+                    break;
+                }
+                lno += 1;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match decode_json(&line) {
+                    Ok((time, worker, Some(event))) => acc.record(time, worker, event),
+                    Ok((time, worker, None)) => acc.seen(time, worker),
+                    Err(message) if lenient => stats.skip(lno, message),
+                    Err(message) => bail!(Error::parse(path, lno, message)),
+                }
+            }
+        }
+        EventEncoding::Bincode => {
+            // "Line" numbers are 1-based record indices for this encoding.
+            let mut record = 0;
+            while !reader
+                .fill_buf()
+                .map_err(|e| Error::io("read log file", path, e))?
+                .is_empty()
+            {
+                record += 1;
+                match bincode::deserialize_from::<_, Record<TimelyEvent>>(&mut reader) {
+                    Ok((time, worker, event)) => acc.record(time, worker, event),
+                    // Nothing after a bad record can be trusted: there is no framing to resync on.
+                    Err(err) if lenient => {
+                        stats.skip(record, format!("bad bincode record: {}", err));
+                        break;
+                    }
+                    Err(err) => bail!(Error::parse(
+                        path,
+                        record,
+                        format!("bad bincode record: {}", err)
+                    )),
+                }
+            }
+        }
+    }
+
+    let (index, channels, events) = acc.finish();
+    stats.events = Some(events);
+    Ok(ParsedLog {
+        index,
+        stats,
+        channels,
+    })
+}
+
+/// Decode one JSON record; the event is `None` for well-formed events we do
+/// not use.
+fn decode_json(line: &str) -> std::result::Result<Record<Option<TimelyEvent>>, String> {
+    let (time, worker, event): Record<serde_json::Value> =
+        serde_json::from_str(line).map_err(|e| format!("bad event record: {}", e))?;
+    let tag = match &event {
+        serde_json::Value::Object(map) if map.len() == 1 => map.keys().next().unwrap().clone(),
+        _ => return Err(format!("bad event record: unexpected event {}", event)),
+    };
+    if !CONSUMED.contains(&tag.as_str()) {
+        return Ok((time, worker, None));
+    }
+    let event = TimelyEvent::deserialize(event).map_err(|e| format!("bad {} event: {}", tag, e))?;
+    Ok((time, worker, Some(event)))
+}
+
+#[derive(Default)]
+struct OperatorStats {
+    addr: Vec<usize>,
+    name: String,
+    activations: u64,
+    total: Duration,
+    max: Duration,
+    histogram: Histogram,
+    /// Start of the activation in progress, if any.
+    started: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Accumulator {
+    /// Keyed by (worker, operator id); ids are only unique per worker.
+    operators: HashMap<(usize, usize), OperatorStats>,
+    /// Channel layout by id. Every worker builds the same dataflow, so the
+    /// first worker's description stands for all of them.
+    channels: HashMap<usize, ChannelsEvent>,
+    /// (messages, records) sent per channel id, over all workers.
+    sent: HashMap<usize, (u64, u64)>,
+    events: usize,
+    workers: BTreeSet<usize>,
+    first: Option<Duration>,
+    last: Duration,
+    unmatched: usize,
+    unknown: usize,
+}
+
+impl Accumulator {
+    /// Count a record towards the event, worker and time span totals, also
+    /// when its event is one we do not use: it still shows the worker was
+    /// running at `time`.
+    fn seen(&mut self, time: Duration, worker: usize) {
+        self.events += 1;
+        self.workers.insert(worker);
+        self.first = Some(self.first.map_or(time, |f| f.min(time)));
+        self.last = self.last.max(time);
+    }
+
+    fn record(&mut self, time: Duration, worker: usize, event: TimelyEvent) {
+        self.seen(time, worker);
+        match event {
+            TimelyEvent::Operates(op) => {
+                self.operators
+                    .entry((worker, op.id))
+                    .or_insert_with(|| OperatorStats {
+                        addr: op.addr,
+                        name: op.name,
+                        ..OperatorStats::default()
+                    });
+            }
+            TimelyEvent::Schedule(ScheduleEvent { id, start_stop }) => {
+                let Some(op) = self.operators.get_mut(&(worker, id)) else {
+                    self.unknown += 1;
+                    return;
+                };
+                match (start_stop, op.started.take()) {
+                    (StartStop::Start, previous) => {
+                        self.unmatched += usize::from(previous.is_some());
+                        op.started = Some(time);
+                    }
+                    (StartStop::Stop, Some(start)) => {
+                        let elapsed = time.saturating_sub(start);
+                        op.activations += 1;
+                        op.total += elapsed;
+                        op.max = op.max.max(elapsed);
+                        op.histogram.record(elapsed);
+                    }
+                    (StartStop::Stop, None) => self.unmatched += 1,
+                }
+            }
+            TimelyEvent::Shutdown(ShutdownEvent { id }) => {
+                if let Some(op) = self.operators.get_mut(&(worker, id)) {
+                    self.unmatched += usize::from(op.started.take().is_some());
+                }
+            }
+            TimelyEvent::Channels(channel) => {
+                self.channels.entry(channel.id).or_insert(channel);
+            }
+            // Receives would count every message a second time.
+            TimelyEvent::Messages(MessagesEvent {
+                is_send: true,
+                channel,
+                length,
+                ..
+            }) => {
+                let (messages, records) = self.sent.entry(channel).or_default();
+                *messages += 1;
+                *records += length as u64;
+            }
+            _ => {}
+        }
+    }
+
+    /// Fold per-worker operators into one row per address, and channel
+    /// traffic into per-operator-pair volumes.
+    fn finish(self) -> (LogIndex, Vec<ChannelVolume>, EventStats) {
+        let channels = channel_volumes(&self.channels, &self.sent);
+        let max_metric: Arc<str> = Arc::from("max_activation_ms");
+        let mut unmatched = self.unmatched;
+        let mut index = LogIndex::new();
+        for ((worker, _), op) in self.operators {
+            unmatched += usize::from(op.started.is_some());
+            let addr = to_addr(&op.addr);
+            let max_ms = ms(op.max);
+            let row = index.entry(addr.clone()).or_insert_with(|| LogRow {
+                addr,
+                activations: 0,
+                total_active_ms: 0.0,
+                op_name: op.name,
+                metrics: BTreeMap::from([(Arc::clone(&max_metric), 0.0)]),
+                workers: BTreeSet::new(),
+                histogram: Some(Histogram::default()),
+            });
+            row.activations += op.activations;
+            row.total_active_ms += ms(op.total);
+            let max = row.metrics.entry(Arc::clone(&max_metric)).or_insert(0.0);
+            *max = max.max(max_ms);
+            row.workers.insert(worker as u32);
+            if let Some(histogram) = &mut row.histogram {
+                histogram.merge(&op.histogram);
+            }
+        }
+
+        let stats = EventStats {
+            events: self.events,
+            workers: self.workers.len(),
+            span_ms: self.first.map_or(0.0, |first| ms(self.last - first)),
+            unmatched_schedules: unmatched,
+            unknown_operators: self.unknown,
+        };
+        (index, channels, stats)
+    }
+}
+
+/// Channels keyed by scope address and source (operator index, output port).
+type BySource<'a> = HashMap<(&'a [usize], (usize, usize)), Vec<&'a ChannelsEvent>>;
+
+/// Resolve each channel with traffic to the operators it connects.
+///
+/// Inside a scope, operator index 0 stands for the scope's own boundary. A
+/// channel's volume is counted once, on the channel leaving the producing
+/// operator; channels leaving a boundary (or a nested scope) only carry data
+/// already counted upstream and are skipped.
+fn channel_volumes(
+    channels: &HashMap<usize, ChannelsEvent>,
+    sent: &HashMap<usize, (u64, u64)>,
+) -> Vec<ChannelVolume> {
+    let scopes: HashSet<&[usize]> = channels.values().map(|c| &c.scope_addr[..]).collect();
+    let mut by_source: BySource = HashMap::new();
+    for channel in channels.values() {
+        by_source
+            .entry((&channel.scope_addr, channel.source))
+            .or_default()
+            .push(channel);
+    }
+
+    let mut volumes: BTreeMap<(Addr, Addr), (u64, u64)> = BTreeMap::new();
+    for (id, &(messages, records)) in sent {
+        let Some(channel) = channels.get(id) else {
+            continue;
+        };
+        let source = operator_addr(&channel.scope_addr, channel.source.0);
+        if channel.source.0 == 0 || scopes.contains(&source[..]) {
+            continue;
+        }
+        let mut targets = Vec::new();
+        let mut seen = HashSet::new();
+        consumers(
+            &channel.scope_addr,
+            channel.target,
+            &scopes,
+            &by_source,
+            &mut seen,
+            &mut targets,
+        );
+        for target in targets {
+            let key = (to_addr(&source), to_addr(&target));
+            let volume = volumes.entry(key).or_default();
+            volume.0 += messages;
+            volume.1 += records;
+        }
+    }
+    volumes
+        .into_iter()
+        .map(|((source, target), (messages, records))| ChannelVolume {
+            source,
+            target,
+            messages,
+            records,
+        })
+        .collect()
+}
+
+/// The operators consuming what arrives at `target` (operator index, input
+/// port) in `scope`, entering and leaving nested scopes as needed.
+fn consumers(
+    scope: &[usize],
+    target: (usize, usize),
+    scopes: &HashSet<&[usize]>,
+    by_source: &BySource,
+    seen: &mut HashSet<(Vec<usize>, (usize, usize))>,
+    out: &mut Vec<Vec<usize>>,
+) {
+    if !seen.insert((scope.to_vec(), target)) {
+        return;
+    }
+    let (next_scope, next_source) = if target.0 == 0 {
+        // Leaving `scope` through its output port: continue in the parent.
+        let Some((&index, parent)) = scope.split_last() else {
+            return;
+        };
+        (parent.to_vec(), (index, target.1))
+    } else {
+        let addr = operator_addr(scope, target.0);
+        if !scopes.contains(&addr[..]) {
+            out.push(addr);
+            return;
+        }
+        // Entering a nested scope through its input port.
+        (addr, (0, target.1))
+    };
+    for channel in by_source
+        .get(&(&next_scope[..], next_source))
+        .into_iter()
+        .flatten()
+    {
+        consumers(&next_scope, channel.target, scopes, by_source, seen, out);
+    }
+}
+
+fn operator_addr(scope: &[usize], index: usize) -> Vec<usize> {
+    let mut addr = scope.to_vec();
+    addr.push(index);
+    addr
+}
+
+fn to_addr(addr: &[usize]) -> Addr {
+    Addr::new(addr.iter().map(|&a| a as u32).collect())
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn at(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn operates(id: usize, addr: &[usize], name: &str) -> TimelyEvent {
+        TimelyEvent::Operates(OperatesEvent {
+            id,
+            addr: addr.to_vec(),
+            name: name.to_string(),
+        })
+    }
+
+    fn schedule(id: usize, start_stop: StartStop) -> TimelyEvent {
+        TimelyEvent::Schedule(ScheduleEvent { id, start_stop })
+    }
+
+    fn channel(id: usize, scope: &[usize], source: usize, target: usize) -> TimelyEvent {
+        TimelyEvent::Channels(ChannelsEvent {
+            id,
+            scope_addr: scope.to_vec(),
+            source: (source, 0),
+            target: (target, 0),
+        })
+    }
+
+    fn send(channel: usize, length: usize, is_send: bool) -> TimelyEvent {
+        TimelyEvent::Messages(MessagesEvent {
+            is_send,
+            channel,
+            source: 0,
+            target: 0,
+            seq_no: 0,
+            length,
+        })
+    }
+
+    /// Two workers running `Input -> Region { Map } -> Sink`.
+    fn capture() -> Vec<Record<TimelyEvent>> {
+        use StartStop::{Start, Stop};
+        let mut records = Vec::new();
+        for worker in 0..2 {
+            for (id, addr, name) in [
+                (1, &[0, 1][..], "Input"),
+                (2, &[0, 2], "Region"),
+                (3, &[0, 2, 1], "Map"),
+                (4, &[0, 3], "Sink"),
+            ] {
+                records.push((at(0), worker, operates(id, addr, name)));
+            }
+            for event in [
+                // Into the region, through it to Map, and back out to Sink.
+                channel(10, &[0], 1, 2),
+                channel(11, &[0, 2], 0, 1),
+                channel(12, &[0, 2], 1, 0),
+                channel(13, &[0], 2, 3),
+            ] {
+                records.push((at(0), worker, event));
+            }
+        }
+        records.extend([
+            (at(1), 0, schedule(1, Start)),
+            (at(2), 0, schedule(1, Stop)),
+            (at(10), 0, schedule(3, Start)),
+            (at(13), 0, schedule(3, Stop)),
+            (at(20), 0, schedule(3, Start)),
+            (at(21), 0, schedule(3, Stop)),
+            (at(5), 1, schedule(3, Start)),
+            (at(9), 1, schedule(3, Stop)),
+            // A second start before the stop: one unmatched.
+            (at(30), 1, schedule(1, Start)),
+            (at(31), 1, schedule(1, Start)),
+            (at(33), 1, schedule(1, Stop)),
+            // A stop without a start, and a start never stopped.
+            (at(35), 0, schedule(4, Stop)),
+            (at(40), 1, schedule(4, Start)),
+            (at(41), 0, schedule(99, Start)),
+            (at(2), 0, send(10, 5, true)),
+            (at(3), 0, send(10, 7, true)),
+            (at(3), 1, send(10, 3, true)),
+            (at(4), 1, send(10, 3, false)),
+            (at(11), 0, send(11, 100, true)),
+            (at(12), 0, send(12, 4, true)),
+            (at(12), 0, send(13, 4, true)),
+            // Unused by the profile, but a third worker still ran until then.
+            (at(100), 2, TimelyEvent::Text("done".to_string())),
+        ]);
+        records
+    }
+
+    fn json_lines(records: &[Record<TimelyEvent>]) -> Vec<u8> {
+        let mut out = String::new();
+        for record in records {
+            out.push_str(&serde_json::to_string(record).unwrap());
+            out.push('\n');
+        }
+        out.into_bytes()
+    }
+
+    fn bincode(records: &[Record<TimelyEvent>]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for record in records {
+            bincode::serialize_into(&mut out, record).unwrap();
+        }
+        out
+    }
+
+    fn parse(bytes: &[u8], encoding: EventEncoding, lenient: bool) -> Result<ParsedLog> {
+        parse_events(bytes, "capture", encoding, lenient)
+    }
+
+    #[test]
+    fn both_encodings_decode_the_same_capture() {
+        let records = capture();
+        for (encoding, bytes) in [
+            (EventEncoding::JsonLines, json_lines(&records)),
+            (EventEncoding::Bincode, bincode(&records)),
+        ] {
+            let parsed = parse(&bytes, encoding, false).unwrap();
+            let rows: Vec<(String, u64, f64, f64, Vec<u32>, u64)> = parsed
+                .index
+                .values()
+                .map(|r| {
+                    (
+                        format!("{:?} {}", r.addr.0, r.op_name),
+                        r.activations,
+                        (r.total_active_ms * 1e6).round() / 1e6,
+                        r.metrics["max_activation_ms"],
+                        r.workers.iter().copied().collect(),
+                        r.histogram.as_ref().unwrap().count(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                rows,
+                [
+                    ("[0, 1] Input".to_string(), 2, 3.0, 2.0, vec![0, 1], 2),
+                    ("[0, 2] Region".to_string(), 0, 0.0, 0.0, vec![0, 1], 0),
+                    ("[0, 2, 1] Map".to_string(), 3, 8.0, 4.0, vec![0, 1], 3),
+                    ("[0, 3] Sink".to_string(), 0, 0.0, 0.0, vec![0, 1], 0),
+                ],
+                "{:?}",
+                encoding
+            );
+
+            let stats = parsed.stats.events.unwrap();
+            assert_eq!(stats.events, records.len());
+            assert_eq!(stats.workers, 3);
+            assert_eq!(stats.span_ms, 100.0);
+            assert_eq!(stats.unmatched_schedules, 3);
+            assert_eq!(stats.unknown_operators, 1);
+
+            // Receives and channels touching the region boundary add nothing.
+            let volumes: Vec<(String, String, u64, u64)> = parsed
+                .channels
+                .iter()
+                .map(|c| {
+                    (
+                        format!("{:?}", c.source.0),
+                        format!("{:?}", c.target.0),
+                        c.messages,
+                        c.records,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                volumes,
+                [
+                    ("[0, 1]".to_string(), "[0, 2, 1]".to_string(), 3, 15),
+                    ("[0, 2, 1]".to_string(), "[0, 3]".to_string(), 1, 4),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn lenient_mode_skips_bad_records() {
+        let records = capture();
+        let mut json = json_lines(&records[..3]);
+        json.extend_from_slice(
+            b"[{\"secs\":0,\"nanos\":0},0,{\"Schedule\":{\"id\":1}}]\nnot json\n",
+        );
+        let parsed = parse(&json, EventEncoding::JsonLines, true).unwrap();
+        assert_eq!(parsed.stats.lines_skipped, 2);
+        assert_eq!(parsed.stats.skipped[0].line, 4);
+        assert!(
+            parsed.stats.skipped[0]
+                .message
+                .starts_with("bad Schedule event")
+        );
+        assert_eq!(parsed.index.len(), 3);
+        let err = parse(&json, EventEncoding::JsonLines, false).unwrap_err();
+        let err = crate::error::find(&err).unwrap();
+        assert_eq!(err.location().and_then(|l| l.line), Some(4));
+
+        // A truncated bincode tail ends the capture.
+        let mut bytes = bincode(&records[..3]);
+        bytes.truncate(bytes.len() - 3);
+        let parsed = parse(&bytes, EventEncoding::Bincode, true).unwrap();
+        assert_eq!(parsed.stats.lines_skipped, 1);
+        assert_eq!(parsed.stats.skipped[0].line, 3);
+        assert_eq!(parsed.index.len(), 2);
+        assert!(parse(&bytes, EventEncoding::Bincode, false).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Log-bucketed activation duration histograms.
+//!
+//! Bucket 0 counts activations shorter than 1µs; bucket `k` (k ≥ 1) counts
+//! those in `[2^(k-1), 2^k)` µs. The same layout is used for histograms built
+//! from raw events and for the `histogram` column of a profile table, so both
+//! can be merged across workers.
+
+use std::time::Duration;
+
+/// Durations of 2^(BUCKETS-2) µs (about 6 days) and longer share the last bucket.
+const BUCKETS: usize = 40;
+
+/// Activation counts per duration bucket; trailing empty buckets are trimmed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    /// Build from per-bucket counts, in bucket order.
+    pub fn from_counts(mut counts: Vec<u64>) -> Self {
+        if counts.len() > BUCKETS {
+            let overflow: u64 = counts.drain(BUCKETS..).sum();
+            counts[BUCKETS - 1] += overflow;
+        }
+        while counts.last() == Some(&0) {
+            counts.pop();
+        }
+        Self { counts }
+    }
+
+    /// Counts per bucket, trailing empty buckets trimmed.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Number of activations recorded.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Count one activation of `duration`.
+    pub fn record(&mut self, duration: Duration) {
+        let us = duration.as_micros();
+        let bucket = if us == 0 {
+            0
+        } else {
+            (u128::BITS - us.leading_zeros()) as usize
+        }
+        .min(BUCKETS - 1);
+        if self.counts.len() <= bucket {
+            self.counts.resize(bucket + 1, 0);
+        }
+        self.counts[bucket] += 1;
+    }
+
+    /// Add `other`'s counts to this histogram.
+    pub fn merge(&mut self, other: &Histogram) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (mine, theirs) in self.counts.iter_mut().zip(&other.counts) {
+            *mine += theirs;
+        }
+    }
+
+    /// Estimate the `q` quantile (0..=1) in milliseconds, interpolating
+    /// linearly within the bucket that holds it. `None` when empty.
+    pub fn quantile_ms(&self, q: f64) -> Option<f64> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+        let rank = (q.clamp(0.0, 1.0) * total as f64).ceil().max(1.0);
+        let mut below = 0u64;
+        for (k, &count) in self.counts.iter().enumerate() {
+            if count > 0 && (below + count) as f64 >= rank {
+                let (lo, hi) = bucket_bounds_ms(k);
+                let frac = (rank - below as f64) / count as f64;
+                return Some(lo + (hi - lo) * frac);
+            }
+            below += count;
+        }
+        None
+    }
+
+    /// Upper bound of the highest non-empty bucket, in milliseconds.
+    pub fn upper_bound_ms(&self) -> Option<f64> {
+        let k = self.counts.iter().rposition(|&c| c > 0)?;
+        Some(bucket_bounds_ms(k).1)
+    }
+}
+
+/// `[lo, hi)` of bucket `k`, in milliseconds.
+fn bucket_bounds_ms(k: usize) -> (f64, f64) {
+    let us = |e: usize| (1u64 << e) as f64;
+    if k == 0 {
+        (0.0, 0.001)
+    } else {
+        (us(k - 1) / 1000.0, us(k) / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn of(durations: &[Duration]) -> Histogram {
+        let mut h = Histogram::default();
+        for &d in durations {
+            h.record(d);
+        }
+        h
+    }
+
+    fn close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("non-empty histogram");
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn buckets_are_log2_microseconds() {
+        let us = Duration::from_micros;
+        let bucket = |d: Duration| of(&[d]).counts().len() - 1;
+        assert_eq!(bucket(Duration::ZERO), 0);
+        assert_eq!(bucket(Duration::from_nanos(999)), 0);
+        assert_eq!(bucket(us(1)), 1);
+        assert_eq!(bucket(us(2)), 2);
+        assert_eq!(bucket(us(3)), 2);
+        assert_eq!(bucket(us(4)), 3);
+        assert_eq!(bucket(us(1023)), 10);
+        assert_eq!(bucket(us(1024)), 11);
+        assert_eq!(bucket(Duration::from_secs(86400 * 365)), BUCKETS - 1);
+
+        let h = of(&[us(0), us(1), us(2), us(3)]);
+        assert_eq!(h.counts(), &[1, 1, 2]);
+        assert_eq!(h.count(), 4);
+        assert_eq!(
+            Histogram::from_counts(vec![1, 0, 2, 0, 0]).counts(),
+            &[1, 0, 2]
+        );
+        let mut wide = vec![0; BUCKETS + 2];
+        wide[BUCKETS - 1] = 1;
+        wide[BUCKETS + 1] = 2;
+        assert_eq!(Histogram::from_counts(wide).counts()[BUCKETS - 1], 3);
+    }
+
+    #[test]
+    fn quantiles_interpolate_within_buckets() {
+        // 50 × 1µs, 49 × 8µs and one 1.5ms activation.
+        let mut durations = vec![Duration::from_micros(1); 50];
+        durations.extend(vec![Duration::from_micros(8); 49]);
+        durations.push(Duration::from_micros(1500));
+        let h = of(&durations);
+        assert_eq!(h.count(), 100);
+
+        close(h.quantile_ms(0.25), 0.0015); // halfway through [1, 2)µs
+        close(h.quantile_ms(0.5), 0.002);
+        close(h.quantile_ms(0.99), 0.016);
+        close(h.quantile_ms(1.0), 2.048);
+        close(h.quantile_ms(0.0), 0.001 + 0.001 / 50.0);
+        close(h.upper_bound_ms(), 2.048);
+
+        let mut merged = of(&durations[..50]);
+        merged.merge(&of(&durations[50..]));
+        assert_eq!(merged, h);
+    }
+
+    #[test]
+    fn empty_histogram_has_no_quantiles() {
+        let h = Histogram::default();
+        assert_eq!(h.count(), 0);
+        assert_eq!(h.quantile_ms(0.5), None);
+        assert_eq!(h.upper_bound_ms(), None);
+        assert_eq!(Histogram::from_counts(vec![0, 0]), h);
+    }
+}
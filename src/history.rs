@@ -0,0 +1,442 @@
+//! Local run history: `record` appends a summary of each run to a JSON-lines
+//! file, `history` turns the file into per-rule and per-node time trends.
+//!
+//! Each line is one [`HistoryEntry`], keyed by user labels (commit, dataset,
+//! workers, ...). Only summaries are kept, so the file stays small across
+//! weeks of runs. Rules are matched across entries by their text, nodes by
+//! their fingerprint (or block and label when they have none), since ops.json
+//! node ids shift as the program changes.
+
+use crate::Result;
+use crate::error::Error;
+use crate::view::ReportData;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+/// File used when the history path is a directory.
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// One recorded run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+    /// Labels given to `record`, e.g. `commit` or `dataset`.
+    pub labels: BTreeMap<String, String>,
+    /// Profile logs aggregated into this entry (see [`crate::Profile::load_runs`]).
+    pub runs: usize,
+    /// Total mapped ms of the run.
+    pub total_mapped_ms: f64,
+    /// Total mapped activations of the run.
+    pub total_mapped_activations: u64,
+    /// Per-node samples.
+    pub nodes: Vec<NodeSample>,
+    /// Per-rule samples.
+    pub rules: Vec<RuleSample>,
+}
+
+/// A node's cost in one recorded run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeSample {
+    /// Matching key across entries: fingerprint, else `block/label`.
+    pub key: String,
+    /// Node label.
+    pub label: String,
+    /// Node block.
+    pub block: String,
+    /// Self time in ms.
+    pub ms: f64,
+    /// Self activations.
+    pub activations: u64,
+}
+
+/// A rule's cost in one recorded run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleSample {
+    /// Rule text; the matching key across entries.
+    pub text: String,
+    /// Active time of the rule's plan nodes, in ms.
+    pub ms: f64,
+    /// Activations of the rule's plan nodes.
+    pub activations: u64,
+}
+
+impl HistoryEntry {
+    /// Summarize `data` under `labels`.
+    pub fn new(data: &ReportData, labels: BTreeMap<String, String>, recorded_at: u64) -> Self {
+        Self {
+            recorded_at,
+            labels,
+            runs: data.totals.runs,
+            total_mapped_ms: data.totals.total_mapped_ms,
+            total_mapped_activations: data.totals.total_mapped_activations,
+            nodes: data
+                .nodes
+                .values()
+                .map(|n| NodeSample {
+                    key: n
+                        .fingerprint
+                        .clone()
+                        .unwrap_or_else(|| format!("{}/{}", n.block, n.label)),
+                    label: n.label.clone(),
+                    block: n.block.clone(),
+                    ms: n.self_total_active_ms,
+                    activations: n.self_activations,
+                })
+                .collect(),
+            rules: data
+                .rules
+                .iter()
+                .map(|r| RuleSample {
+                    text: r.text.clone(),
+                    ms: r.total_active_ms,
+                    activations: r.activations,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The history file for `path`: `path` itself, or [`HISTORY_FILE`] inside it
+/// when it is an existing directory or ends with a path separator.
+pub fn history_file(path: &Path) -> PathBuf {
+    if path.is_dir() || names_dir(path) {
+        path.join(HISTORY_FILE)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Whether `path` is written as a directory, e.g. `runs/`.
+fn names_dir(path: &Path) -> bool {
+    path.as_os_str()
+        .to_string_lossy()
+        .ends_with(std::path::is_separator)
+}
+
+/// Append `entry` to the history at `path` (created if missing, along with
+/// its directory when `path` ends with a separator).
+pub fn append(path: &Path, entry: &HistoryEntry) -> Result<PathBuf> {
+    let file = history_file(path);
+    if names_dir(path) {
+        fs::create_dir_all(path)
+            .map_err(|e| Error::io("create history directory", path.display().to_string(), e))?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file)
+        .and_then(|mut f| f.write_all(line.as_bytes()))
+        .map_err(|e| Error::io("append to history", file.display().to_string(), e))?;
+    Ok(file)
+}
+
+/// Read every entry of the history at `path`, in recording order.
+pub fn read(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let file = history_file(path);
+    let name = file.display().to_string();
+    let text = fs::read_to_string(&file).map_err(|e| Error::io("read history", &name, e))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                Error::parse(&name, i + 1, format!("invalid history entry: {}", e)).into()
+            })
+        })
+        .collect()
+}
+
+/// Everything the `history` outputs show.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryData {
+    /// Entries matching the filter, in recording order.
+    pub entries: Vec<EntrySummary>,
+    /// The `top` rules and nodes by their highest ms in any entry.
+    pub rules: Vec<Trend>,
+    /// Like `rules`, for nodes.
+    pub nodes: Vec<Trend>,
+}
+
+/// The totals of one entry, without its samples.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntrySummary {
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+    /// Labels given to `record`.
+    pub labels: BTreeMap<String, String>,
+    /// Profile logs aggregated into the entry.
+    pub runs: usize,
+    /// Total mapped ms.
+    pub total_mapped_ms: f64,
+    /// Total mapped activations.
+    pub total_mapped_activations: u64,
+}
+
+/// A rule's or node's ms per entry (`None` where an entry lacks it).
+#[derive(Debug, Clone, Serialize)]
+pub struct Trend {
+    /// Rule text, or the node's matching key.
+    pub key: String,
+    /// Rule text or node label, for display.
+    pub label: String,
+    /// Empty for rules.
+    pub block: String,
+    /// ms per entry, in recording order.
+    pub ms: Vec<Option<f64>>,
+}
+
+impl Trend {
+    /// Relative change from the first to the last entry that has a value.
+    pub fn change(&self) -> Option<f64> {
+        let mut present = self.ms.iter().flatten();
+        let first = *present.next()?;
+        let last = *present.next_back()?;
+        (first > 0.0).then(|| last / first - 1.0)
+    }
+}
+
+/// Trends over the entries whose labels match every `KEY=VALUE` in `filter`.
+pub fn build(entries: &[HistoryEntry], filter: &[(String, String)], top: usize) -> HistoryData {
+    let entries: Vec<&HistoryEntry> = entries
+        .iter()
+        .filter(|e| filter.iter().all(|(k, v)| e.labels.get(k) == Some(v)))
+        .collect();
+
+    let mut rules: BTreeMap<&str, Trend> = BTreeMap::new();
+    let mut nodes: BTreeMap<&str, Trend> = BTreeMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        for r in &entry.rules {
+            let trend = rules.entry(&r.text).or_insert_with(|| Trend {
+                key: r.text.clone(),
+                label: r.text.clone(),
+                block: String::new(),
+                ms: vec![None; entries.len()],
+            });
+            // Rules sharing a text add up, like nodes sharing a key.
+            *trend.ms[i].get_or_insert(0.0) += r.ms;
+        }
+        for n in &entry.nodes {
+            let trend = nodes.entry(&n.key).or_insert_with(|| Trend {
+                key: n.key.clone(),
+                label: n.label.clone(),
+                block: n.block.clone(),
+                ms: vec![None; entries.len()],
+            });
+            // Nodes sharing a key (e.g. unfingerprinted duplicates) add up.
+            *trend.ms[i].get_or_insert(0.0) += n.ms;
+        }
+    }
+
+    HistoryData {
+        entries: entries
+            .iter()
+            .map(|e| EntrySummary {
+                recorded_at: e.recorded_at,
+                labels: e.labels.clone(),
+                runs: e.runs,
+                total_mapped_ms: e.total_mapped_ms,
+                total_mapped_activations: e.total_mapped_activations,
+            })
+            .collect(),
+        rules: busiest(rules.into_values().collect(), top),
+        nodes: busiest(nodes.into_values().collect(), top),
+    }
+}
+
+fn busiest(mut trends: Vec<Trend>, top: usize) -> Vec<Trend> {
+    let peak = |t: &Trend| t.ms.iter().flatten().copied().fold(0.0, f64::max);
+    trends.sort_by(|a, b| peak(b).total_cmp(&peak(a)).then_with(|| a.key.cmp(&b.key)));
+    trends.truncate(top);
+    trends
+}
+
+/// Plain-text trends: the entry table, then rules and nodes with sparklines.
+pub fn render_text(data: &HistoryData) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{} entries", data.entries.len());
+    for (i, e) in data.entries.iter().enumerate() {
+        let labels: Vec<String> = e
+            .labels
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        let _ = writeln!(
+            out,
+            "  {:>3}  {}  {:>12.3} ms  {}",
+            i + 1,
+            format_timestamp(e.recorded_at),
+            e.total_mapped_ms,
+            labels.join(" ")
+        );
+    }
+    for (title, trends) in [("Rules", &data.rules), ("Nodes", &data.nodes)] {
+        if trends.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "\n{} (by peak ms):", title);
+        for t in trends {
+            let last = t.ms.iter().rev().flatten().next().copied().unwrap_or(0.0);
+            let change = t
+                .change()
+                .map(|c| format!("{:+.1}%", c * 100.0))
+                .unwrap_or_default();
+            let label = if t.block.is_empty() {
+                t.label.clone()
+            } else {
+                format!("{} [{}]", t.label, t.block)
+            };
+            let _ = writeln!(
+                out,
+                "  {}  {:>12.3} ms {:>8}  {}",
+                sparkline(&t.ms),
+                last,
+                change,
+                label
+            );
+        }
+    }
+    out
+}
+
+/// One character per entry, scaled to the trend's peak; gaps for missing entries.
+fn sparkline(values: &[Option<f64>]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let peak = values.iter().flatten().copied().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|v| match v {
+            None => ' ',
+            Some(_) if peak <= 0.0 => BARS[0],
+            Some(v) => BARS[((v / peak) * 7.0).round() as usize],
+        })
+        .collect()
+}
+
+/// `YYYY-MM-DD HH:MM` in UTC.
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let (hour, minute) = ((secs % 86_400) / 3600, (secs % 3600) / 60);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year, month, day, hour, minute
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn entry(recorded_at: u64, commit: &str, rules: &[(&str, f64)]) -> HistoryEntry {
+        HistoryEntry {
+            recorded_at,
+            labels: BTreeMap::from([("commit".to_string(), commit.to_string())]),
+            runs: 1,
+            total_mapped_ms: rules.iter().map(|(_, ms)| ms).sum(),
+            total_mapped_activations: 0,
+            nodes: rules
+                .iter()
+                .map(|&(text, ms)| NodeSample {
+                    key: format!("fp-{}", text),
+                    label: text.to_string(),
+                    block: "main".to_string(),
+                    ms,
+                    activations: 1,
+                })
+                .collect(),
+            rules: rules
+                .iter()
+                .map(|&(text, ms)| RuleSample {
+                    text: text.to_string(),
+                    ms,
+                    activations: 1,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn timestamps_are_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(1_709_251_140), "2024-02-29 23:59");
+        assert_eq!(format_timestamp(1_709_251_200), "2024-03-01 00:00");
+        assert_eq!(format_timestamp(1_792_326_840), "2026-10-18 12:34");
+    }
+
+    #[test]
+    fn sparklines_scale_to_the_peak_and_leave_gaps() {
+        assert_eq!(sparkline(&[Some(0.0), Some(4.0), None, Some(8.0)]), "▁▅ █");
+        assert_eq!(sparkline(&[Some(0.0), None, Some(0.0)]), "▁ ▁");
+        assert_eq!(sparkline(&[None, None]), "  ");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn build_matches_rules_across_entries() {
+        let dir = std::env::temp_dir().join(format!("history-test-{}/", std::process::id()));
+        let path = Path::new(&dir);
+        let _ = fs::remove_dir_all(path);
+        let first = entry(0, "a", &[("A(x) :- B(x).", 10.0), ("C(x) :- A(x).", 2.0)]);
+        let second = entry(86_400, "b", &[("A(x) :- B(x).", 5.0)]);
+        assert_eq!(append(path, &first).unwrap(), path.join(HISTORY_FILE));
+        append(path, &second).unwrap();
+        let entries = read(path).unwrap();
+        fs::remove_dir_all(path).unwrap();
+
+        let data = build(&entries, &[], 10);
+        let labels: Vec<&str> = data
+            .entries
+            .iter()
+            .map(|e| e.labels["commit"].as_str())
+            .collect();
+        assert_eq!(labels, ["a", "b"]);
+        let rules: Vec<(&str, &[Option<f64>])> = data
+            .rules
+            .iter()
+            .map(|t| (t.key.as_str(), t.ms.as_slice()))
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                ("A(x) :- B(x).", &[Some(10.0), Some(5.0)][..]),
+                ("C(x) :- A(x).", &[Some(2.0), None][..]),
+            ]
+        );
+        assert_eq!(data.rules[0].change(), Some(-0.5));
+        assert_eq!(data.rules[1].change(), None);
+        assert_eq!(data.nodes[1].key, "fp-C(x) :- A(x).");
+        assert_eq!(data.nodes[1].ms, [Some(2.0), None]);
+
+        let filtered = build(&entries, &[("commit".into(), "b".into())], 1);
+        assert_eq!(filtered.entries.len(), 1);
+        assert_eq!(filtered.rules.len(), 1);
+        assert_eq!(filtered.rules[0].ms, [Some(5.0)]);
+    }
+
+    #[test]
+    fn duplicate_rule_texts_add_up_like_nodes() {
+        let dup = entry(0, "a", &[("A(x) :- B(x).", 1.0), ("A(x) :- B(x).", 2.0)]);
+        let data = build(&[dup], &[], 10);
+        assert_eq!(data.rules.len(), 1);
+        assert_eq!(data.rules[0].ms, [Some(3.0)]);
+        assert_eq!(data.nodes[0].ms, [Some(3.0)]);
+    }
+}
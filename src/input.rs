@@ -0,0 +1,259 @@
+//! Opening input files: `-` for stdin, transparent gzip/zstd decompression.
+//!
+//! Compression is detected from the first bytes of the stream, not the file
+//! extension, so piped and renamed archives work too.
+
+use crate::error::Error;
+
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Path that stands for stdin (and stdout on the output side).
+pub const STDIO: &str = "-";
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Read buffer size; large enough that syscalls do not dominate.
+pub(crate) const BUF_SIZE: usize = 1 << 16;
+
+/// How an input is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Uncompressed.
+    Plain,
+    /// gzip, detected by magic bytes.
+    Gzip,
+    /// zstd, detected by magic bytes.
+    Zstd,
+}
+
+/// An opened input, decompressed if needed.
+pub struct Input {
+    /// Decompressed contents.
+    pub reader: Box<dyn BufRead + Send>,
+    /// How the input was stored.
+    pub encoding: Encoding,
+    /// Whether this is a plain regular file that can be reopened and seeked.
+    pub seekable: bool,
+}
+
+/// Name used for `path` in messages: `<stdin>` for `-`.
+pub fn display_name(path: &str) -> &str {
+    if path == STDIO { "<stdin>" } else { path }
+}
+
+/// Open `path` (or stdin for `-`), sniffing gzip/zstd magic bytes.
+///
+/// `action` describes the read in I/O errors, e.g. "read log file".
+pub fn open(path: &str, action: &'static str) -> Result<Input, Error> {
+    let err = |e| Error::io(action, display_name(path), e);
+
+    let (raw, is_file): (Box<dyn BufRead + Send>, bool) = if path == STDIO {
+        (
+            Box::new(BufReader::with_capacity(BUF_SIZE, io::stdin())),
+            false,
+        )
+    } else {
+        let file = File::open(path).map_err(err)?;
+        // FIFOs, process substitution and devices open fine but cannot seek.
+        let is_file = file.metadata().map_err(err)?.is_file();
+        (Box::new(BufReader::with_capacity(BUF_SIZE, file)), is_file)
+    };
+
+    let (reader, encoding) = decode(raw).map_err(err)?;
+    Ok(Input {
+        reader,
+        encoding,
+        seekable: is_file && encoding == Encoding::Plain,
+    })
+}
+
+/// Read all of `path` (or stdin) into a string, decompressing if needed.
+pub fn read_to_string(path: &str, action: &'static str) -> Result<String, Error> {
+    let mut input = open(path, action)?;
+    let mut text = String::new();
+    input
+        .reader
+        .read_to_string(&mut text)
+        .map_err(|e| Error::io(action, display_name(path), e))?;
+    Ok(text)
+}
+
+/// Sniff `raw` and wrap it in the matching decompressor.
+fn decode(raw: Box<dyn BufRead + Send>) -> io::Result<(Box<dyn BufRead + Send>, Encoding)> {
+    let (raw, encoding) = sniff(raw)?;
+    let reader: Box<dyn BufRead + Send> = match encoding {
+        Encoding::Plain => raw,
+        Encoding::Gzip => Box::new(BufReader::with_capacity(BUF_SIZE, MultiGzDecoder::new(raw))),
+        Encoding::Zstd => Box::new(BufReader::with_capacity(
+            BUF_SIZE,
+            zstd::Decoder::with_buffer(raw)?,
+        )),
+    };
+    Ok((reader, encoding))
+}
+
+/// Detect the encoding from the first bytes, without losing them.
+fn sniff(mut reader: Box<dyn BufRead + Send>) -> io::Result<(Box<dyn BufRead + Send>, Encoding)> {
+    let head = reader.fill_buf()?;
+    if head.len() >= ZSTD_MAGIC.len() {
+        let encoding = encoding_of(head);
+        return Ok((reader, encoding));
+    }
+    // A pipe may deliver fewer bytes than the longest magic at first; read
+    // up to it (or EOF) and put the bytes back in front.
+    let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+    reader
+        .by_ref()
+        .take(ZSTD_MAGIC.len() as u64)
+        .read_to_end(&mut head)?;
+    let encoding = encoding_of(&head);
+    Ok((Box::new(io::Cursor::new(head).chain(reader)), encoding))
+}
+
+fn encoding_of(head: &[u8]) -> Encoding {
+    if head.starts_with(GZIP_MAGIC) {
+        Encoding::Gzip
+    } else if head.starts_with(ZSTD_MAGIC) {
+        Encoding::Zstd
+    } else {
+        Encoding::Plain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    const TEXT: &str = "addr activations total_active_ms name\n[0, 1]  2  0.5  Input\n";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zstd(data: &[u8]) -> Vec<u8> {
+        zstd::encode_all(data, 0).unwrap()
+    }
+
+    /// A reader that hands out one byte per call, like a slow pipe.
+    struct Trickle(io::Cursor<Vec<u8>>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(1);
+            self.0.read(&mut buf[..n])
+        }
+    }
+
+    fn decode_all(data: Vec<u8>, trickle: bool) -> (Encoding, String) {
+        let raw: Box<dyn BufRead + Send> = if trickle {
+            Box::new(BufReader::new(Trickle(io::Cursor::new(data))))
+        } else {
+            Box::new(io::Cursor::new(data))
+        };
+        let (mut reader, encoding) = decode(raw).unwrap();
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        (encoding, text)
+    }
+
+    #[test]
+    fn magic_bytes_select_the_decoder() {
+        for trickle in [false, true] {
+            let plain = TEXT.as_bytes().to_vec();
+            assert_eq!(
+                decode_all(plain, trickle),
+                (Encoding::Plain, TEXT.to_string())
+            );
+            assert_eq!(
+                decode_all(gzip(TEXT.as_bytes()), trickle),
+                (Encoding::Gzip, TEXT.to_string())
+            );
+            assert_eq!(
+                decode_all(zstd(TEXT.as_bytes()), trickle),
+                (Encoding::Zstd, TEXT.to_string())
+            );
+            // Shorter than either magic, or only a prefix of one.
+            for short in ["", "a", "\u{1f}", "(\u{b5}/"] {
+                assert_eq!(
+                    decode_all(short.as_bytes().to_vec(), trickle),
+                    (Encoding::Plain, short.to_string())
+                );
+            }
+        }
+    }
+
+    /// A file under the temp directory, removed on drop.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, data: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("input-{}-{}", std::process::id(), name));
+            std::fs::write(&path, data).unwrap();
+            Self(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn compressed_files_round_trip() {
+        // Extensions are irrelevant; `renamed` is gzip without `.gz`.
+        let files = [
+            (TempFile::new("log", TEXT.as_bytes()), Encoding::Plain, true),
+            (
+                TempFile::new("log.gz", &gzip(TEXT.as_bytes())),
+                Encoding::Gzip,
+                false,
+            ),
+            (
+                TempFile::new("log.zst", &zstd(TEXT.as_bytes())),
+                Encoding::Zstd,
+                false,
+            ),
+            (
+                TempFile::new("renamed", &gzip(TEXT.as_bytes())),
+                Encoding::Gzip,
+                false,
+            ),
+        ];
+        for (file, encoding, seekable) in &files {
+            let input = open(file.path(), "read log file").unwrap();
+            assert_eq!((input.encoding, input.seekable), (*encoding, *seekable));
+            assert_eq!(read_to_string(file.path(), "read log file").unwrap(), TEXT);
+        }
+
+        // Concatenated gzip members decode as one stream.
+        let mut twice = gzip(TEXT.as_bytes());
+        twice.extend(gzip(TEXT.as_bytes()));
+        let file = TempFile::new("twice.gz", &twice);
+        assert_eq!(read_to_string(file.path(), "read").unwrap(), TEXT.repeat(2));
+    }
+
+    #[test]
+    fn truncated_archives_are_io_errors() {
+        let mut data = gzip(TEXT.as_bytes());
+        data.truncate(data.len() / 2);
+        let file = TempFile::new("truncated.gz", &data);
+        let err = read_to_string(file.path(), "read log file").unwrap_err();
+        assert_eq!(err.kind(), "io");
+        assert!(err.to_string().starts_with("read log file "), "{err}");
+    }
+}
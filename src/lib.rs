@@ -0,0 +1,52 @@
+//! FlowLog profile visualizer.
+//!
+//! Combines FlowLog's operator mapping (`ops.json`) with a Timely profile log
+//! into [`ReportData`], and renders it as HTML, Markdown, CSV/TSV or SQLite.
+//! [`Profile::load`] runs the whole parse → validate → aggregate pipeline;
+//! [`Sink`] implementations write the result.
+//!
+//! The terminal UI, the live-reload server and the file watcher behind the
+//! CLI are only built with the `cli` feature (on by default); library users
+//! can turn it off with `default-features = false` to drop their dependencies.
+
+#![warn(missing_docs)]
+
+pub mod addr;
+pub mod arrangements;
+pub mod compare;
+pub mod critical;
+pub mod csv;
+pub mod dashboard;
+pub mod datalog;
+pub mod diagnostics;
+pub mod edges;
+pub mod error;
+pub mod events;
+pub mod histogram;
+pub mod history;
+pub mod input;
+pub mod log;
+pub mod markdown;
+pub mod ops;
+pub mod profile;
+pub mod render;
+#[cfg(feature = "cli")]
+pub mod serve;
+pub mod sink;
+pub mod sizes;
+pub mod source;
+pub mod sqlite;
+pub mod stats;
+#[cfg(feature = "cli")]
+pub mod tui;
+pub mod view;
+#[cfg(feature = "cli")]
+pub mod watch;
+
+pub use error::Error;
+pub use profile::{Overlays, Profile};
+pub use sink::{HtmlFile, MarkdownFile, Sink, TableDir};
+pub use view::ReportData;
+
+/// Result type of the library; errors carry an [`Error`] when typed.
+pub type Result<T> = anyhow::Result<T>;
@@ -1,147 +1,845 @@
 //! Log parsing for the Timely operator profile table.
+//!
+//! The table is self-describing: a header row names the columns, so FlowLog can
+//! add columns without breaking older readers. `addr`, `activations`,
+//! `total_active_ms` and `name` are required; `worker` splits an operator's row
+//! per worker (rows are combined per addr); any other column is an extra metric
+//! whose numeric values are kept in [`LogRow::metrics`]. Logs without a header
+//! use the original `addr activations total_active_ms name` layout.
+//!
+//! Logs from long runs can be several GB (often archived compressed, see
+//! [`crate::input`]), so the file is streamed line by line through a reused
+//! buffer instead of being read into memory. With `jobs > 1` the file is split
+//! into newline-aligned byte ranges that are parsed on separate threads.
+//!
+//! Raw Timely event captures are handled by [`crate::events`]; the entry points
+//! here pick the reader from [`LogFormat`], sniffing the input by default.
 
 use crate::Result;
 use crate::addr::Addr;
-use crate::diagnostics;
+use crate::error::Error;
+use crate::events::{self, ChannelVolume, EventEncoding, EventStats};
+use crate::histogram::Histogram;
+use crate::input;
 
-use anyhow::{Context, anyhow, bail};
-use regex::Regex;
-use std::collections::BTreeMap;
-use std::fs;
+use anyhow::bail;
+use serde::Serialize;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::thread;
 
 /// A single operator row from the Timely profile table.
 #[derive(Debug, Clone)]
 pub struct LogRow {
+    /// Operator address.
     pub addr: Addr,
+    /// Times the operator was scheduled.
     pub activations: u64,
+    /// Time spent in those activations, in ms.
     pub total_active_ms: f64,
+    /// Timely operator name.
     pub op_name: String,
+    /// Extra numeric columns (e.g. `records_in`, `peak_bytes`), by header name.
+    pub metrics: BTreeMap<Arc<str>, f64>,
+    /// Workers whose rows were combined into this one; empty without a `worker` column.
+    pub workers: BTreeSet<u32>,
+    /// Activation durations, when the log has them (raw events or a `histogram` column).
+    pub histogram: Option<Histogram>,
+}
+
+impl LogRow {
+    /// Fold another worker's row for the same operator into this one.
+    ///
+    /// Counts and times add up; `max_*` and `peak_*` metrics take the maximum,
+    /// other metrics add up, and histograms merge. Fails (leaving `self`
+    /// untouched) unless both rows come from a `worker` column and from
+    /// different workers.
+    fn absorb(&mut self, other: LogRow) -> std::result::Result<(), Box<LogRow>> {
+        if self.workers.is_empty()
+            || other.workers.is_empty()
+            || !self.workers.is_disjoint(&other.workers)
+        {
+            return Err(Box::new(other));
+        }
+        self.activations += other.activations;
+        self.total_active_ms += other.total_active_ms;
+        for (name, value) in other.metrics {
+            let combine = if name.starts_with("max_") || name.starts_with("peak_") {
+                f64::max
+            } else {
+                |a: f64, b: f64| a + b
+            };
+            self.metrics
+                .entry(name)
+                .and_modify(|v| *v = combine(*v, value))
+                .or_insert(value);
+        }
+        match (&mut self.histogram, other.histogram) {
+            (Some(mine), Some(theirs)) => mine.merge(&theirs),
+            (mine @ None, theirs) => *mine = theirs,
+            (Some(_), None) => {}
+        }
+        self.workers.extend(other.workers);
+        Ok(())
+    }
 }
 
 /// Index by address for fast lookup during aggregation.
 pub type LogIndex = BTreeMap<Addr, LogRow>;
 
+/// Average repeated runs of the same program into one index.
+///
+/// Counts and times are means over all runs, an operator missing from a run
+/// counting as zero there, so node and rule sums stay means too. `max_*` and
+/// `peak_*` metrics take the maximum, other metrics the mean over the runs
+/// that have them; workers and histograms are combined.
+pub fn mean_index(runs: &[LogIndex]) -> LogIndex {
+    let n = runs.len().max(1) as f64;
+    let mut out = LogIndex::new();
+    let mut activations: BTreeMap<&Addr, u64> = BTreeMap::new();
+    // (sum, runs) of metrics that are averaged.
+    let mut sums: BTreeMap<(&Addr, &Arc<str>), (f64, usize)> = BTreeMap::new();
+    for index in runs {
+        for (addr, row) in index {
+            *activations.entry(addr).or_default() += row.activations;
+            let mean = out.entry(addr.clone()).or_insert_with(|| LogRow {
+                activations: 0,
+                total_active_ms: 0.0,
+                metrics: BTreeMap::new(),
+                workers: BTreeSet::new(),
+                histogram: None,
+                ..row.clone()
+            });
+            mean.total_active_ms += row.total_active_ms / n;
+            for (name, &value) in &row.metrics {
+                if name.starts_with("max_") || name.starts_with("peak_") {
+                    let slot = mean.metrics.entry(Arc::clone(name)).or_insert(value);
+                    *slot = slot.max(value);
+                } else {
+                    let (sum, count) = sums.entry((addr, name)).or_default();
+                    *sum += value;
+                    *count += 1;
+                }
+            }
+            mean.workers.extend(&row.workers);
+            if let Some(h) = &row.histogram {
+                mean.histogram.get_or_insert_default().merge(h);
+            }
+        }
+    }
+    for ((addr, name), (sum, count)) in sums {
+        if let Some(row) = out.get_mut(addr) {
+            row.metrics.insert(Arc::clone(name), sum / count as f64);
+        }
+    }
+    for (addr, total) in activations {
+        if let Some(row) = out.get_mut(addr) {
+            row.activations = (total as f64 / n).round() as u64;
+        }
+    }
+    out
+}
+
+/// Knobs for reading a profile log.
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    /// Number of threads used to parse a file; 1 parses sequentially.
+    pub jobs: usize,
+    /// Skip malformed lines (truncated writes, interleaved stdout noise)
+    /// instead of failing; they are counted in [`ParseStats`].
+    pub lenient: bool,
+    /// What kind of log the file holds.
+    pub format: LogFormat,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            jobs: 1,
+            lenient: false,
+            format: LogFormat::Auto,
+        }
+    }
+}
+
+/// Kinds of log file that can feed a [`LogIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Decide from the first bytes of the (decompressed) input.
+    Auto,
+    /// The pre-aggregated profile table.
+    Table,
+    /// Raw Timely logging events, see [`crate::events`].
+    Events(EventEncoding),
+}
+
+/// A parsed log together with what the parser had to skip or adjust.
+#[derive(Debug, Clone)]
+pub struct ParsedLog {
+    /// Rows by operator address.
+    pub index: LogIndex,
+    /// What was skipped or adjusted.
+    pub stats: ParseStats,
+    /// Data volume between operators; only event captures have it.
+    pub channels: Vec<ChannelVolume>,
+}
+
+/// Lines skipped in lenient mode and values adjusted on the way in.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ParseStats {
+    /// Total number of skipped lines.
+    pub lines_skipped: usize,
+    /// The first [`MAX_SKIPPED_EXAMPLES`] skipped lines, in file order.
+    pub skipped: Vec<SkippedLine>,
+    /// Negative activation counts or times that were clamped to zero.
+    pub negatives_clamped: usize,
+    /// Capture-wide numbers when the log was a raw event capture.
+    pub events: Option<EventStats>,
+}
+
+/// One line dropped by lenient parsing.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedLine {
+    /// 1-based line number in the log.
+    pub line: usize,
+    /// Why the line was skipped.
+    pub message: String,
+}
+
+/// How many skipped lines are kept verbatim; the rest are only counted.
+pub const MAX_SKIPPED_EXAMPLES: usize = 10;
+
+impl ParseStats {
+    pub(crate) fn skip(&mut self, line: usize, message: String) {
+        self.lines_skipped += 1;
+        if self.skipped.len() < MAX_SKIPPED_EXAMPLES {
+            self.skipped.push(SkippedLine { line, message });
+        }
+    }
+
+    /// Add another run's stats (see [`crate::Profile::load_runs`]). Counts add
+    /// up; skipped examples are prefixed with `log_name`, since their line
+    /// numbers are relative to that log; event stats keep the largest worker
+    /// count and span.
+    pub(crate) fn merge_run(&mut self, other: ParseStats, log_name: &str) {
+        self.lines_skipped += other.lines_skipped;
+        self.negatives_clamped += other.negatives_clamped;
+        let room = MAX_SKIPPED_EXAMPLES - self.skipped.len();
+        self.skipped
+            .extend(other.skipped.into_iter().take(room).map(|s| SkippedLine {
+                message: format!("{}: {}", log_name, s.message),
+                ..s
+            }));
+        self.events = match (self.events.take(), other.events) {
+            (Some(a), Some(b)) => Some(EventStats {
+                events: a.events + b.events,
+                workers: a.workers.max(b.workers),
+                span_ms: a.span_ms.max(b.span_ms),
+                unmatched_schedules: a.unmatched_schedules + b.unmatched_schedules,
+                unknown_operators: a.unknown_operators + b.unknown_operators,
+            }),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// Append a later chunk's stats, shifting its line numbers by `offset`.
+    fn merge(&mut self, other: ParseStats, offset: usize) {
+        self.lines_skipped += other.lines_skipped;
+        self.negatives_clamped += other.negatives_clamped;
+        let room = MAX_SKIPPED_EXAMPLES - self.skipped.len();
+        self.skipped
+            .extend(other.skipped.into_iter().take(room).map(|s| SkippedLine {
+                line: s.line + offset,
+                ..s
+            }));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Column {
+    Addr,
+    Activations,
+    TotalActiveMs,
+    Name,
+    Worker,
+    Histogram,
+    Metric(Arc<str>),
+}
+
+impl Column {
+    fn from_header(name: &str) -> Self {
+        match name {
+            "addr" => Column::Addr,
+            "activations" => Column::Activations,
+            "total_active_ms" => Column::TotalActiveMs,
+            "name" => Column::Name,
+            "worker" => Column::Worker,
+            "histogram" => Column::Histogram,
+            other => Column::Metric(other.into()),
+        }
+    }
+
+    fn header(&self) -> &str {
+        match self {
+            Column::Addr => "addr",
+            Column::Activations => "activations",
+            Column::TotalActiveMs => "total_active_ms",
+            Column::Name => "name",
+            Column::Worker => "worker",
+            Column::Histogram => "histogram",
+            Column::Metric(name) => name,
+        }
+    }
+}
+
+/// Column layout declared by the most recent header row.
+#[derive(Debug, Clone, PartialEq)]
+struct Layout {
+    columns: Vec<Column>,
+    /// Index of the `name` column, which may span several tokens.
+    name: usize,
+}
+
+impl Default for Layout {
+    /// The layout of logs written before the header was authoritative.
+    fn default() -> Self {
+        Self {
+            columns: vec![
+                Column::Addr,
+                Column::Activations,
+                Column::TotalActiveMs,
+                Column::Name,
+            ],
+            name: 3,
+        }
+    }
+}
+
+impl Layout {
+    /// Interpret `tokens` as a header row; `None` if they do not look like one
+    /// (a header is all identifiers, one of which is `addr`).
+    fn from_header(tokens: &[&str]) -> Option<std::result::Result<Self, String>> {
+        let is_ident = |t: &&str| {
+            t.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && t.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+        if !tokens.contains(&"addr") || !tokens.iter().all(is_ident) {
+            return None;
+        }
+
+        let columns: Vec<Column> = tokens.iter().map(|t| Column::from_header(t)).collect();
+        for (i, t) in tokens.iter().enumerate() {
+            if tokens[..i].contains(t) {
+                return Some(Err(format!("duplicate column {:?} in header", t)));
+            }
+        }
+        let required = Layout::default();
+        let missing: Vec<&str> = required
+            .columns
+            .iter()
+            .filter(|c| !columns.contains(c))
+            .map(Column::header)
+            .collect();
+        if !missing.is_empty() {
+            return Some(Err(format!(
+                "header is missing required column(s): {}",
+                missing.join(", ")
+            )));
+        }
+
+        let name = columns.iter().position(|c| *c == Column::Name)?;
+        Some(Ok(Self { columns, name }))
+    }
+
+    fn describe(&self) -> String {
+        let names: Vec<&str> = self.columns.iter().map(Column::header).collect();
+        names.join(" ")
+    }
+
+    /// Parse a data row split into token `spans` of `line`.
+    ///
+    /// Every column is one token except `name`, which takes whatever lies
+    /// between the columns before and after it, spaces included.
+    ///
+    /// Negative counts and times are clamped to zero and tallied in `clamped`.
+    fn parse_row(
+        &self,
+        line: &str,
+        spans: &[(usize, usize)],
+        clamped: &mut usize,
+    ) -> std::result::Result<LogRow, String> {
+        let n = self.columns.len();
+        if spans.len() < n {
+            return Err(format!(
+                "expected {} columns ({}), found {}: {:?}",
+                n,
+                self.describe(),
+                spans.len(),
+                line.trim_end()
+            ));
+        }
+        // Columns after `name` are taken from the end of the line.
+        let shift = spans.len() - n;
+
+        let mut row = LogRow {
+            addr: Addr::new(Vec::new()),
+            activations: 0,
+            total_active_ms: 0.0,
+            op_name: String::new(),
+            metrics: BTreeMap::new(),
+            workers: BTreeSet::new(),
+            histogram: None,
+        };
+        for (i, column) in self.columns.iter().enumerate() {
+            let (start, end) = match i.cmp(&self.name) {
+                std::cmp::Ordering::Less => spans[i],
+                std::cmp::Ordering::Equal => (spans[i].0, spans[i + shift].1),
+                std::cmp::Ordering::Greater => spans[i + shift],
+            };
+            let token = &line[start..end];
+            match column {
+                // Interleaved stdout noise usually fails here first.
+                Column::Addr if !token.starts_with('[') => {
+                    return Err(format!("cannot parse line: {:?}", line.trim_end()));
+                }
+                Column::Addr => {
+                    row.addr =
+                        parse_addr(token).map_err(|e| format!("bad addr {}: {}", token, e))?;
+                }
+                Column::Activations => {
+                    // Exact integers take the fast path; anything else goes
+                    // through f64 so "1.2e6" and "-1" are accepted.
+                    row.activations = match token.parse::<u64>() {
+                        Ok(n) => n,
+                        Err(_) => {
+                            let n = parse_number(token)
+                                .ok_or_else(|| format!("bad activations: {:?}", token))?;
+                            clamp_negative(n, clamped).round() as u64
+                        }
+                    };
+                }
+                Column::TotalActiveMs => {
+                    let ms = parse_number(token)
+                        .ok_or_else(|| format!("bad total_active_ms: {:?}", token))?;
+                    row.total_active_ms = clamp_negative(ms, clamped);
+                }
+                Column::Name => row.op_name = token.to_string(),
+                Column::Worker => {
+                    row.workers
+                        .insert(token.parse().map_err(|e| format!("bad worker: {}", e))?);
+                }
+                Column::Histogram if token.starts_with('[') => {
+                    row.histogram = Some(
+                        parse_histogram(token)
+                            .map_err(|e| format!("bad histogram {}: {}", token, e))?,
+                    );
+                }
+                // Non-numeric values (e.g. "-" for n/a) are simply not recorded.
+                Column::Histogram => {}
+                Column::Metric(name) => {
+                    if let Some(value) = parse_number(token) {
+                        row.metrics.insert(Arc::clone(name), value);
+                    }
+                }
+            }
+        }
+        Ok(row)
+    }
+}
+
+/// Per-bucket counts written like an addr, e.g. `[0, 12, 40, 3]`.
+fn parse_histogram(token: &str) -> std::result::Result<Histogram, std::num::ParseIntError> {
+    let inner = token.trim_start_matches('[').trim_end_matches(']').trim();
+    let counts = if inner.is_empty() {
+        Vec::new()
+    } else {
+        inner
+            .split(',')
+            .map(|c| c.trim().parse())
+            .collect::<std::result::Result<_, _>>()?
+    };
+    Ok(Histogram::from_counts(counts))
+}
+
+/// A finite decimal or scientific-notation number.
+fn parse_number(token: &str) -> Option<f64> {
+    token.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+fn clamp_negative(value: f64, clamped: &mut usize) -> f64 {
+    if value < 0.0 {
+        *clamped += 1;
+        0.0
+    } else {
+        value
+    }
+}
+
 /// Parse a Timely profile table log file into an address-to-row index.
 ///
-/// Expected columns (whitespace-separated):
+/// Expected columns (whitespace-separated), unless a header says otherwise:
 /// addr  activations  total_active_ms  name...
 ///
 /// Example:
 /// [0, 8, 10]   33   853.886   ThresholdTotal
 pub fn parse_log_file(path: &str) -> Result<LogIndex> {
-    let text = fs::read_to_string(path)
-        .with_context(|| diagnostics::error_message(format!("read log file {}", path)))?;
-
-    // We allow variable spacing; name may contain spaces and ':'.
-    // Capture:
-    // 1) addr: \[ ... \]
-    // 2) activations: integer
-    // 3) total_active_ms: float/integer
-    // 4) name: rest of line
-    const LOG_LINE_RE: &str = r#"^\s*(\[[^\]]*\])\s+(\d+)\s+([0-9]+(?:\.[0-9]+)?)\s+(.*?)\s*$"#;
-    let re = Regex::new(LOG_LINE_RE)?;
-
-    let mut out: LogIndex = LogIndex::new();
-    for (lineno, line) in text.lines().enumerate() {
-        let lno = lineno + 1;
-        let line = line.trim_end();
-
-        if line.trim().is_empty() {
-            continue;
+    Ok(parse_log_file_with(path, &ParseOptions::default())?.index)
+}
+
+/// [`parse_log_file`] with explicit options (lenient mode, parallel parsing).
+///
+/// `path` may be `-` for stdin; gzip and zstd input is decompressed on the fly.
+/// Parallel parsing needs random access, so compressed files and stdin are
+/// always parsed sequentially.
+pub fn parse_log_file_with(path: &str, opts: &ParseOptions) -> Result<ParsedLog> {
+    let mut input = input::open(path, "read log file")?;
+    let format = match opts.format {
+        LogFormat::Auto => sniff_format(&mut input.reader)
+            .map_err(|e| Error::io("read log file", input::display_name(path), e))?,
+        format => format,
+    };
+    if let LogFormat::Events(encoding) = format {
+        return events::parse_events(
+            input.reader,
+            input::display_name(path),
+            encoding,
+            opts.lenient,
+        );
+    }
+
+    if opts.jobs > 1 && input.seekable {
+        let file = File::open(path).map_err(|e| Error::io("read log file", path, e))?;
+        return parse_parallel(file, input.reader, path, opts);
+    }
+    let table = parse_table(
+        input.reader,
+        input::display_name(path),
+        Layout::default(),
+        false,
+        opts.lenient,
+    )?;
+    Ok(table.parsed)
+}
+
+/// Tell a profile table from an event capture by its first bytes.
+///
+/// Table rows start with `addr` or `[0, ...`; JSON event records start with
+/// `[{` (or `[[` for a sequence-encoded timestamp); bincode starts with a
+/// little-endian u64 and so contains control bytes almost immediately.
+fn sniff_format(reader: &mut impl BufRead) -> std::io::Result<LogFormat> {
+    let head = reader.fill_buf()?;
+    let head = &head[..head.len().min(64)];
+    if head.iter().any(|&b| b < 0x20 && !b.is_ascii_whitespace()) {
+        return Ok(LogFormat::Events(EventEncoding::Bincode));
+    }
+    let text = head.trim_ascii_start();
+    if text.starts_with(b"[{") || text.starts_with(b"[[") {
+        return Ok(LogFormat::Events(EventEncoding::JsonLines));
+    }
+    Ok(LogFormat::Table)
+}
+
+/// Parse a profile table from any buffered reader; `path` is only used in errors.
+pub fn parse_log(reader: impl BufRead, path: &str) -> Result<LogIndex> {
+    Ok(parse_table(reader, path, Layout::default(), false, false)?
+        .parsed
+        .index)
+}
+
+struct Table {
+    parsed: ParsedLog,
+    /// Number of lines read, to place a chunk's line numbers in the whole file.
+    lines: usize,
+}
+
+/// Parse rows starting out with `layout`.
+///
+/// A header row replaces the layout, unless `fixed` is set, in which case a
+/// header that disagrees with `layout` is an error even when `lenient`.
+fn parse_table(
+    mut reader: impl BufRead,
+    path: &str,
+    layout: Layout,
+    fixed: bool,
+    lenient: bool,
+) -> Result<Table> {
+    let mut parser = TableParser {
+        layout,
+        fixed,
+        index: LogIndex::new(),
+        spans: Vec::new(),
+        negatives_clamped: 0,
+    };
+    let mut stats = ParseStats::default();
+    // One buffer for the whole file: memory stays flat regardless of log size.
+    let mut buf = Vec::new();
+    let mut lno = 0;
+    loop {
+        buf.clear();
+        let n = reader
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| Error::io("read log file", path, e))?;
+        if n == 0 {
+            break;
         }
+        lno += 1;
 
-        // Skip header line if present.
-        if line.contains("addr") && line.contains("activations") && line.contains("total_active_ms")
-        {
-            continue;
+        let message = match parser.line(&buf) {
+            Ok(()) => continue,
+            Err(LineError::LayoutChanged) => {
+                bail!(Error::parse(path, lno, "header changes the column layout"))
+            }
+            Err(LineError::Bad(message)) => message,
+        };
+        if !lenient {
+            bail!(Error::parse(path, lno, message));
         }
+        stats.skip(lno, message);
+    }
 
-        let caps = match re.captures(line) {
-            Some(c) => c,
-            None => {
-                bail!(
-                    "{}",
-                    diagnostics::error_message(format!(
-                        "log parse error at {}:{}: cannot parse line: {:?}",
-                        path, lno, line
-                    ))
-                );
+    stats.negatives_clamped = parser.negatives_clamped;
+    Ok(Table {
+        parsed: ParsedLog {
+            index: parser.index,
+            stats,
+            channels: Vec::new(),
+        },
+        lines: lno,
+    })
+}
+
+enum LineError {
+    /// The line is malformed; lenient mode skips it.
+    Bad(String),
+    /// A header disagreed with a fixed layout.
+    LayoutChanged,
+}
+
+/// Per-table parsing state, fed one line at a time.
+struct TableParser {
+    layout: Layout,
+    fixed: bool,
+    index: LogIndex,
+    spans: Vec<(usize, usize)>,
+    negatives_clamped: usize,
+}
+
+impl TableParser {
+    fn line(&mut self, raw: &[u8]) -> std::result::Result<(), LineError> {
+        let line = std::str::from_utf8(raw)
+            .map_err(|e| LineError::Bad(format!("invalid UTF-8: {}", e)))?;
+
+        tokenize(line, &mut self.spans);
+        if self.spans.is_empty() {
+            return Ok(());
+        }
+
+        match header(line, &self.spans) {
+            Some(Ok(header)) if self.fixed && header != self.layout => {
+                return Err(LineError::LayoutChanged);
+            }
+            Some(Ok(header)) => {
+                self.layout = header;
+                return Ok(());
+            }
+            Some(Err(message)) => return Err(LineError::Bad(message)),
+            None => {}
+        }
+
+        let row = self
+            .layout
+            .parse_row(line, &self.spans, &mut self.negatives_clamped)
+            .map_err(LineError::Bad)?;
+        match self.index.entry(row.addr.clone()) {
+            Entry::Vacant(slot) => {
+                slot.insert(row);
             }
+            Entry::Occupied(mut slot) => {
+                if let Err(row) = slot.get_mut().absorb(row) {
+                    return Err(LineError::Bad(format!(
+                        "duplicate addr entry: {}",
+                        describe_row(&row)
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The layout declared by `line` if it is a header row.
+fn header(line: &str, spans: &[(usize, usize)]) -> Option<std::result::Result<Layout, String>> {
+    // Cheap rejection for data rows, which usually start with `[` or a digit.
+    let &(start, _) = spans.first()?;
+    if !line[start..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return None;
+    }
+    let tokens: Vec<&str> = spans.iter().map(|&(s, e)| &line[s..e]).collect();
+    Layout::from_header(&tokens)
+}
+
+/// Split `line` into whitespace-separated token spans, keeping `[...]` whole.
+fn tokenize(line: &str, spans: &mut Vec<(usize, usize)>) {
+    spans.clear();
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        if bytes[i] == b'[' {
+            while i < bytes.len() && bytes[i] != b']' {
+                i += 1;
+            }
+        }
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        spans.push((start, i));
+    }
+}
+
+/// Parse newline-aligned byte ranges of `file` on `opts.jobs` threads.
+///
+/// Each range builds its own index; they are merged in file order. On any
+/// failure the file is re-parsed sequentially, which reports the first problem
+/// with its real line number (chunk-local line numbers are meaningless).
+fn parse_parallel(
+    file: File,
+    head: impl BufRead,
+    path: &str,
+    opts: &ParseOptions,
+) -> Result<ParsedLog> {
+    let sequential = || {
+        let opts = ParseOptions { jobs: 1, ..*opts };
+        parse_log_file_with(path, &opts)
+    };
+    // Ranges after the first cannot see the header, so read it up front; a
+    // header that changes mid-file sends us down the sequential path.
+    let Some(layout) = leading_layout(head) else {
+        return sequential();
+    };
+    let bounds = chunk_bounds(&file, opts.jobs).map_err(|e| Error::io("read log file", path, e))?;
+
+    let chunks: Vec<Result<Table>> = thread::scope(|s| {
+        let handles: Vec<_> = bounds
+            .windows(2)
+            .map(|w| {
+                let (start, end, layout) = (w[0], w[1], layout.clone());
+                s.spawn(move || parse_chunk(path, start, end, layout, opts.lenient))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("log parser thread panicked"))
+            .collect()
+    });
+
+    let mut out = ParsedLog {
+        index: LogIndex::new(),
+        stats: ParseStats::default(),
+        channels: Vec::new(),
+    };
+    let mut offset = 0;
+    for chunk in chunks {
+        let Ok(Table { parsed, lines }) = chunk else {
+            return sequential();
         };
+        let ParsedLog {
+            index: mut chunk,
+            stats,
+            ..
+        } = parsed;
+        out.stats.merge(stats, offset);
+        offset += lines;
 
-        let addr_str = caps
-            .get(1)
-            .ok_or_else(|| {
-                anyhow!(diagnostics::error_message(format!(
-                    "log parse error at {}:{}: missing addr",
-                    path, lno
-                )))
-            })?
-            .as_str();
-        let activations: u64 = caps
-            .get(2)
-            .ok_or_else(|| {
-                anyhow!(diagnostics::error_message(format!(
-                    "log parse error at {}:{}: missing activations",
-                    path, lno
-                )))
-            })?
-            .as_str()
-            .parse()?;
-        let total_active_ms: f64 = caps
-            .get(3)
-            .ok_or_else(|| {
-                anyhow!(diagnostics::error_message(format!(
-                    "log parse error at {}:{}: missing total_active_ms",
-                    path, lno
-                )))
-            })?
-            .as_str()
-            .parse()?;
-        let op_name = caps
-            .get(4)
-            .ok_or_else(|| {
-                anyhow!(diagnostics::error_message(format!(
-                    "log parse error at {}:{}: missing name",
-                    path, lno
-                )))
-            })?
-            .as_str()
-            .to_string();
-
-        let addr = parse_addr(addr_str).with_context(|| {
-            diagnostics::error_message(format!("bad addr at {}:{}: {}", path, lno, addr_str))
-        })?;
-
-        let row = LogRow {
-            addr: addr.clone(),
-            activations,
-            total_active_ms,
-            op_name,
+        // Logs are usually sorted by address, making this a cheap bulk append.
+        let index = &mut out.index;
+        if index.last_key_value().map(|(k, _)| k) < chunk.first_key_value().map(|(k, _)| k) {
+            index.append(&mut chunk);
+            continue;
+        }
+        for (addr, row) in chunk {
+            match index.entry(addr) {
+                Entry::Vacant(slot) => {
+                    slot.insert(row);
+                }
+                Entry::Occupied(mut slot) => {
+                    if slot.get_mut().absorb(row).is_err() {
+                        return sequential();
+                    }
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The layout in effect for the first data row, or `None` if the header is bad.
+fn leading_layout(mut reader: impl BufRead) -> Option<Layout> {
+    let mut line = String::new();
+    let mut spans = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return Some(Layout::default());
+        }
+        tokenize(&line, &mut spans);
+        if spans.is_empty() {
+            continue;
+        }
+        return match header(&line, &spans) {
+            Some(header) => header.ok(),
+            None => Some(Layout::default()),
         };
+    }
+}
 
-        if out.insert(addr.clone(), row).is_some() {
-            bail!(
-                "{}",
-                diagnostics::error_message(format!(
-                    "duplicate addr entry in log at {}:{}: {}",
-                    path, lno, addr_str
-                ))
-            );
+fn parse_chunk(path: &str, start: u64, end: u64, layout: Layout, lenient: bool) -> Result<Table> {
+    let mut file = File::open(path).map_err(|e| Error::io("read log file", path, e))?;
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| Error::io("read log file", path, e))?;
+    let reader = BufReader::with_capacity(input::BUF_SIZE, file.take(end - start));
+    parse_table(reader, path, layout, true, lenient)
+}
+
+/// Split the file into `jobs` ranges whose boundaries fall just after a newline.
+fn chunk_bounds(file: &File, jobs: usize) -> std::io::Result<Vec<u64>> {
+    let len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut bounds = vec![0];
+    let mut skipped = Vec::new();
+    for i in 1..jobs as u64 {
+        let target = (len * i / jobs as u64).max(*bounds.last().unwrap());
+        reader.seek(SeekFrom::Start(target))?;
+        skipped.clear();
+        let boundary = target + reader.read_until(b'\n', &mut skipped)? as u64;
+        if boundary > *bounds.last().unwrap() && boundary < len {
+            bounds.push(boundary);
         }
     }
+    bounds.push(len);
+    Ok(bounds)
+}
 
-    Ok(out)
+fn describe_row(row: &LogRow) -> String {
+    let parts: Vec<String> = row.addr.0.iter().map(|p| p.to_string()).collect();
+    match row.workers.first() {
+        Some(worker) => format!("[{}] (worker {})", parts.join(", "), worker),
+        None => format!("[{}]", parts.join(", ")),
+    }
 }
 
 /// Parse "[0, 8, 10]" into Addr(vec![0, 8, 10]).
-fn parse_addr(s: &str) -> Result<Addr> {
+pub(crate) fn parse_addr(s: &str) -> std::result::Result<Addr, String> {
     let s = s.trim();
     if !s.starts_with('[') || !s.ends_with(']') {
-        bail!(
-            "{}",
-            diagnostics::error_message(format!("addr must be bracketed: {}", s))
-        );
+        return Err("addr must be bracketed".to_string());
     }
     let inner = &s[1..s.len() - 1].trim();
     if inner.is_empty() {
@@ -155,8 +853,459 @@ fn parse_addr(s: &str) -> Result<Addr> {
         }
         v.push(
             p.parse::<u32>()
-                .with_context(|| diagnostics::error_message(format!("bad addr element {}", p)))?,
+                .map_err(|e| format!("bad addr element {}: {}", p, e))?,
         );
     }
     Ok(Addr::new(v))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    /// A log file under the temp dir, removed on drop; the parallel path
+    /// needs a real, seekable file.
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new(name: &str, text: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "flowlog-log-test-{}-{}.log",
+                std::process::id(),
+                name
+            ));
+            std::fs::write(&path, text).unwrap();
+            Self(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn parse(log: &TempLog, jobs: usize, lenient: bool) -> Result<ParsedLog> {
+        let opts = ParseOptions {
+            jobs,
+            lenient,
+            format: LogFormat::Table,
+        };
+        parse_log_file_with(log.path(), &opts)
+    }
+
+    /// Everything the index holds, in a comparable form.
+    fn dump(parsed: &ParsedLog) -> String {
+        format!("{:#?}", parsed.index)
+    }
+
+    /// Rows for `ops` operators, each split over two workers whose rows are
+    /// far apart in the file, with names containing spaces.
+    fn worker_log(ops: u32) -> String {
+        let mut text = String::from("addr activations total_active_ms worker records_in name\n");
+        for worker in 0..2 {
+            for op in 0..ops {
+                text.push_str(&format!(
+                    "[0, {}]  {}  {}.25  {}  {}  Join  op {}\n",
+                    op,
+                    op + worker,
+                    op,
+                    worker,
+                    10 * op,
+                    op
+                ));
+                if op % 7 == 0 {
+                    text.push('\n');
+                }
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn parallel_parse_matches_sequential() {
+        let log = TempLog::new("parallel", &worker_log(50));
+        let expected = parse(&log, 1, false).unwrap();
+        assert_eq!(expected.index.len(), 50);
+        assert_eq!(expected.index[&Addr::new(vec![0, 3])].workers.len(), 2);
+        for jobs in [2, 3, 4, 7, 16, 64] {
+            let parsed = parse(&log, jobs, false).unwrap();
+            assert_eq!(dump(&parsed), dump(&expected), "jobs = {}", jobs);
+        }
+    }
+
+    #[test]
+    fn parallel_parse_of_tiny_logs() {
+        for (name, text) in [
+            ("empty", ""),
+            ("header-only", "addr activations total_active_ms name\n"),
+            ("one-row", "[0, 1] 3 1.5 Map\n"),
+            ("no-newline", "[0, 1] 3 1.5 Map\n[0, 2] 4 2.5 Filter"),
+        ] {
+            let log = TempLog::new(name, text);
+            let expected = parse(&log, 1, false).unwrap();
+            for jobs in [2, 8, 64] {
+                let parsed = parse(&log, jobs, false).unwrap();
+                assert_eq!(
+                    dump(&parsed),
+                    dump(&expected),
+                    "{} with jobs = {}",
+                    name,
+                    jobs
+                );
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parallel_parse_of_a_fifo_reads_sequentially() {
+        let text = worker_log(20);
+        let fifo = TempLog(
+            std::env::temp_dir().join(format!("flowlog-log-test-{}-fifo.log", std::process::id())),
+        );
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo.0)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let writer = {
+            let (path, text) = (fifo.0.clone(), text.clone());
+            thread::spawn(move || std::fs::write(path, text).unwrap())
+        };
+        let parsed = parse(&fifo, 4, false).unwrap();
+        writer.join().unwrap();
+
+        let expected = parse_log(text.as_bytes(), "log").unwrap();
+        assert_eq!(format!("{:#?}", parsed.index), format!("{:#?}", expected));
+    }
+
+    #[test]
+    fn chunk_bounds_fall_after_newlines() {
+        let text = worker_log(20);
+        let log = TempLog::new("bounds", &text);
+        let file = File::open(log.path()).unwrap();
+        for jobs in [1, 2, 3, 5, 8, 200] {
+            let bounds = chunk_bounds(&file, jobs).unwrap();
+            assert_eq!(bounds.first(), Some(&0));
+            assert_eq!(bounds.last(), Some(&(text.len() as u64)));
+            assert!(bounds.len() <= jobs + 1, "{:?}", bounds);
+            assert!(bounds.windows(2).all(|w| w[0] < w[1]), "{:?}", bounds);
+            for &b in &bounds[1..bounds.len() - 1] {
+                assert_eq!(text.as_bytes()[b as usize - 1], b'\n', "jobs = {}", jobs);
+            }
+        }
+
+        // Three short lines cannot make more than three chunks.
+        let log = TempLog::new("bounds-short", "[0] 1 1 A\n[1] 1 1 B\n[2] 1 1 C\n");
+        let file = File::open(log.path()).unwrap();
+        assert!(chunk_bounds(&file, 64).unwrap().len() <= 4);
+    }
+
+    #[test]
+    fn leading_layout_skips_blank_lines() {
+        let layout =
+            leading_layout("\n  \naddr name activations total_active_ms\n".as_bytes()).unwrap();
+        assert_eq!(layout.name, 1);
+        assert_eq!(
+            leading_layout("[0] 1 1 A\n".as_bytes()),
+            Some(Layout::default())
+        );
+        assert_eq!(leading_layout("".as_bytes()), Some(Layout::default()));
+        assert_eq!(leading_layout("addr name\n".as_bytes()), None);
+    }
+
+    fn tokens(line: &str) -> Vec<&str> {
+        let mut spans = Vec::new();
+        tokenize(line, &mut spans);
+        spans.iter().map(|&(s, e)| &line[s..e]).collect()
+    }
+
+    #[test]
+    fn tokenize_keeps_brackets_whole() {
+        let cases: &[(&str, &[&str])] = &[
+            ("", &[]),
+            ("  \t\n", &[]),
+            (
+                "[0, 8, 10]   33   853.886   ThresholdTotal\n",
+                &["[0, 8, 10]", "33", "853.886", "ThresholdTotal"],
+            ),
+            (
+                "[0,1] 2 3 Join  left side",
+                &["[0,1]", "2", "3", "Join", "left", "side"],
+            ),
+            (
+                "[0, 1] 2 3 A [0, 4, 1]",
+                &["[0, 1]", "2", "3", "A", "[0, 4, 1]"],
+            ),
+            ("[0, 1]x 2", &["[0, 1]x", "2"]),
+            ("[0, 1", &["[0, 1"]),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(tokens(line), *expected, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn header_layouts() {
+        use Column::*;
+        let metric = |name: &str| Metric(name.into());
+        type Expected = Option<std::result::Result<(Vec<Column>, usize), &'static str>>;
+        let cases: &[(&str, Expected)] = &[
+            (
+                "addr activations total_active_ms name",
+                Some(Ok((vec![Addr, Activations, TotalActiveMs, Name], 3))),
+            ),
+            (
+                "name addr total_active_ms activations",
+                Some(Ok((vec![Name, Addr, TotalActiveMs, Activations], 0))),
+            ),
+            (
+                "addr activations total_active_ms records_in name peak_bytes",
+                Some(Ok((
+                    vec![
+                        Addr,
+                        Activations,
+                        TotalActiveMs,
+                        metric("records_in"),
+                        Name,
+                        metric("peak_bytes"),
+                    ],
+                    4,
+                ))),
+            ),
+            (
+                "addr worker activations total_active_ms histogram name",
+                Some(Ok((
+                    vec![Addr, Worker, Activations, TotalActiveMs, Histogram, Name],
+                    5,
+                ))),
+            ),
+            (
+                "addr activations name",
+                Some(Err("header is missing required column(s): total_active_ms")),
+            ),
+            (
+                "addr name name activations total_active_ms",
+                Some(Err("duplicate column \"name\" in header")),
+            ),
+            // Not headers: no `addr`, or not all identifiers.
+            ("activations total_active_ms name", None),
+            ("addr activations total_active_ms Join-2", None),
+            ("[0] 1 2 addr", None),
+        ];
+        for (line, expected) in cases {
+            let got = Layout::from_header(&tokens(line)).map(|r| r.map(|l| (l.columns, l.name)));
+            let expected = expected.clone().map(|r| r.map_err(str::to_string));
+            assert_eq!(got, expected, "{:?}", line);
+        }
+    }
+
+    fn row(layout: &str, line: &str) -> std::result::Result<LogRow, String> {
+        let layout = match layout {
+            "" => Layout::default(),
+            header => Layout::from_header(&tokens(header)).unwrap().unwrap(),
+        };
+        let mut spans = Vec::new();
+        tokenize(line, &mut spans);
+        layout.parse_row(line, &spans, &mut 0)
+    }
+
+    /// A parsed row as `addr activations ms name metrics`.
+    fn summary(row: &LogRow) -> String {
+        format!(
+            "{:?} {} {} {:?} {:?}",
+            row.addr.0, row.activations, row.total_active_ms, row.op_name, row.metrics
+        )
+    }
+
+    #[test]
+    fn rows_follow_the_layout() {
+        let cases = [
+            (
+                "",
+                "[0, 8, 10]   33   853.886   ThresholdTotal",
+                r#"[0, 8, 10] 33 853.886 "ThresholdTotal" {}"#,
+            ),
+            (
+                "",
+                "[0, 1] 2 3 Join  left  side\n",
+                r#"[0, 1] 2 3 "Join  left  side" {}"#,
+            ),
+            ("", "[0, 1] 1.2e3 0.5 Map", r#"[0, 1] 1200 0.5 "Map" {}"#),
+            (
+                "name addr total_active_ms activations",
+                "Reduce by key [0, 2] 4.5 7",
+                r#"[0, 2] 7 4.5 "Reduce by key" {}"#,
+            ),
+            (
+                "addr activations total_active_ms records_in name peak_bytes",
+                "[0, 3] 1 2 100 Arrange  x 4096",
+                r#"[0, 3] 1 2 "Arrange  x" {"peak_bytes": 4096.0, "records_in": 100.0}"#,
+            ),
+            (
+                "addr activations total_active_ms records_in name",
+                "[0, 3] 1 2 - Arrange",
+                r#"[0, 3] 1 2 "Arrange" {}"#,
+            ),
+        ];
+        for (header, line, expected) in cases {
+            assert_eq!(summary(&row(header, line).unwrap()), expected, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn bad_rows_are_rejected() {
+        let cases: &[(&str, &str, &str)] = &[
+            (
+                "",
+                "[0, 1] 2 3",
+                "expected 4 columns (addr activations total_active_ms name), found 3",
+            ),
+            ("", "INFO starting worker 0", "cannot parse line"),
+            ("", "[0, x] 2 3 Map", "bad addr [0, x]"),
+            ("", "[0, 1] many 3 Map", "bad activations: \"many\""),
+            ("", "[0, 1] 2 NaN Map", "bad total_active_ms: \"NaN\""),
+            (
+                "addr worker activations total_active_ms name",
+                "[0, 1] w0 2 3 Map",
+                "bad worker",
+            ),
+        ];
+        for (header, line, expected) in cases {
+            let err = row(header, line).unwrap_err();
+            assert!(err.starts_with(expected), "{:?}: {}", line, err);
+        }
+    }
+
+    #[test]
+    fn negative_values_are_clamped() {
+        let mut clamped = 0;
+        let line = "[0, 1] -3 -0.5 Map";
+        let mut spans = Vec::new();
+        tokenize(line, &mut spans);
+        let row = Layout::default()
+            .parse_row(line, &spans, &mut clamped)
+            .unwrap();
+        assert_eq!((row.activations, row.total_active_ms, clamped), (0, 0.0, 2));
+    }
+
+    #[test]
+    fn header_can_change_mid_file() {
+        let text = "addr activations total_active_ms name\n\
+                    [0, 1] 2 3 Map\n\
+                    name total_active_ms activations addr\n\
+                    Filter 5 4 [0, 2]\n";
+        let log = TempLog::new("relayout", text);
+        let expected = parse(&log, 1, false).unwrap();
+        let filter = &expected.index[&Addr::new(vec![0, 2])];
+        assert_eq!((filter.activations, filter.total_active_ms), (4, 5.0));
+        assert_eq!(filter.op_name, "Filter");
+        // Chunks are parsed with the leading layout fixed; the change sends
+        // the parallel parser back to the sequential path.
+        for jobs in [2, 4] {
+            assert_eq!(dump(&parse(&log, jobs, false).unwrap()), dump(&expected));
+        }
+
+        let err = parse_table(text.as_bytes(), "t.log", Layout::default(), true, true)
+            .err()
+            .unwrap();
+        let err = crate::error::find(&err).unwrap();
+        assert_eq!(err.kind(), "parse");
+        assert_eq!(err.location().and_then(|l| l.line), Some(3));
+    }
+
+    #[test]
+    fn lenient_mode_counts_and_samples_skipped_lines() {
+        // Good rows on odd lines, 13 bad lines on even ones.
+        let mut text = String::new();
+        for i in 0..13 {
+            text.push_str(&format!("[0, {}] 1 -0.5 Map\n", i));
+            text.push_str(if i % 2 == 0 {
+                "worker 0: progress 42%\n"
+            } else {
+                "[0, 99] 1\n"
+            });
+        }
+        let log = TempLog::new("lenient", &text);
+
+        let parsed = parse(&log, 1, true).unwrap();
+        assert_eq!(parsed.index.len(), 13);
+        let stats = &parsed.stats;
+        assert_eq!(stats.lines_skipped, 13);
+        assert_eq!(stats.negatives_clamped, 13);
+        assert_eq!(stats.skipped.len(), MAX_SKIPPED_EXAMPLES);
+        let lines: Vec<usize> = stats.skipped.iter().map(|s| s.line).collect();
+        assert_eq!(lines, [2, 4, 6, 8, 10, 12, 14, 16, 18, 20]);
+        assert_eq!(
+            stats.skipped[0].message,
+            r#"cannot parse line: "worker 0: progress 42%""#
+        );
+        assert!(stats.skipped[1].message.starts_with("expected 4 columns"));
+
+        // Chunks merge their stats with file-wide line numbers.
+        for jobs in [2, 5] {
+            let parallel = parse(&log, jobs, true).unwrap();
+            assert_eq!(
+                format!("{:?}", parallel.stats),
+                format!("{:?}", parsed.stats),
+                "jobs = {}",
+                jobs
+            );
+        }
+
+        for jobs in [1, 4] {
+            let err = parse(&log, jobs, false).unwrap_err();
+            let err = crate::error::find(&err).unwrap();
+            assert_eq!(err.kind(), "parse");
+            assert_eq!(err.location().and_then(|l| l.line), Some(2));
+        }
+    }
+
+    #[test]
+    fn runs_merge_their_stats() {
+        let first = TempLog::new("run-a", "[0, 1] 1 -1 Map\nnoise\n");
+        let rows: String = (0..10)
+            .map(|i| format!("[0, {}] 1 1 Map\nnoise\n", i))
+            .collect();
+        let second = TempLog::new("run-b", &rows);
+        let events = |events, workers, span_ms| EventStats {
+            events,
+            workers,
+            span_ms,
+            unmatched_schedules: 1,
+            unknown_operators: 0,
+        };
+
+        let mut stats = ParseStats::default();
+        let mut a = parse(&first, 1, true).unwrap().stats;
+        a.events = Some(events(10, 2, 5.0));
+        stats.merge_run(a, "a.log");
+        let mut b = parse(&second, 1, true).unwrap().stats;
+        b.events = Some(events(30, 4, 3.0));
+        stats.merge_run(b, "b.log");
+
+        assert_eq!(stats.lines_skipped, 11);
+        assert_eq!(stats.negatives_clamped, 1);
+        let examples: Vec<(usize, &str)> = stats
+            .skipped
+            .iter()
+            .map(|s| (s.line, &s.message[..5]))
+            .collect();
+        let mut expected = vec![(2, "a.log")];
+        expected.extend((1..MAX_SKIPPED_EXAMPLES).map(|i| (2 * i, "b.log")));
+        assert_eq!(examples, expected);
+        let merged = EventStats {
+            unmatched_schedules: 2,
+            ..events(40, 4, 5.0)
+        };
+        assert_eq!(stats.events, Some(merged));
+    }
+}
@@ -1,81 +1,594 @@
-use anyhow::Context;
-use clap::Parser;
+use anyhow::bail;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use flowlog_profile_viz::events::EventEncoding;
+use flowlog_profile_viz::log::{LogFormat, ParseOptions};
+use flowlog_profile_viz::render::Embed;
+use flowlog_profile_viz::{
+    Error, HtmlFile, MarkdownFile, Overlays, Profile, Result, Sink, TableDir, compare, dashboard,
+    diagnostics, history, input, markdown, render, serve, source, sqlite, tui, watch,
+};
 use std::collections::BTreeMap;
-use std::fs;
-
-mod addr;
-mod diagnostics;
-mod log;
-mod ops;
-mod render;
-mod view;
-
-pub type Result<T> = anyhow::Result<T>;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "flowlog-profile-viz")]
 #[command(about = "FlowLog profile visualizer", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    /// Path to the Timely profile log.
-    #[arg(short = 'l', long)]
-    log: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// When to color diagnostics (honors NO_COLOR in auto mode).
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
+
+    /// Diagnostics format on stderr.
+    #[arg(long, value_enum, default_value_t = DiagnosticsFormat::Text, global = true)]
+    diagnostics_format: DiagnosticsFormat,
+
+    /// Threads used to parse the profile log (0 = one per CPU).
+    #[arg(short = 'j', long, default_value_t = 1, global = true)]
+    jobs: usize,
+
+    /// Skip malformed log lines instead of failing; they are reported as warnings.
+    #[arg(long, global = true)]
+    lenient: bool,
+
+    /// Profile log format; auto sniffs the table or a raw Timely event capture.
+    #[arg(long, value_enum, default_value_t = LogFormatArg::Auto, global = true)]
+    log_format: LogFormatArg,
+
+    #[command(flatten)]
+    report: Option<ReportArgs>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the --source program annotated with per-rule ms, % and activations.
+    Annotate(InputArgs),
+    /// Compare two sets of runs and report changes beyond run-to-run noise.
+    Compare(CompareArgs),
+    /// Build an index report over a directory of runs.
+    Dashboard(DashboardArgs),
+    /// Append a run to a SQLite database (created if missing).
+    ExportSqlite(ExportSqliteArgs),
+    /// Show per-rule and per-node time trends over recorded runs.
+    History(HistoryArgs),
+    /// Append a summary of a run to a local history file.
+    Record(RecordArgs),
+    /// Serve a live-reloading report on localhost.
+    Serve(ServeArgs),
+    /// Browse the profile interactively in the terminal.
+    Tui(InputArgs),
+}
+
+/// Profiling artifacts shared by subcommands.
+#[derive(Args, Clone)]
+struct InputArgs {
+    /// Path to the Timely profile log (`-` for stdin, may be .gz/.zst); repeat
+    /// for runs of the same program to aggregate them with their spread.
+    #[arg(short = 'l', long, required = true)]
+    log: Vec<String>,
+
+    /// Path to the ops.json spec (`-` for stdin, may be .gz/.zst).
+    #[arg(short = 'p', long)]
+    ops: String,
+
+    /// Relation/fingerprint tuple counts (JSON or a `KEY [INPUT] OUTPUT` table)
+    /// to show µs per tuple and tuples/s.
+    #[arg(long)]
+    sizes: Option<String>,
 
-    /// Path to the ops.json spec.
+    /// Arrangement sizes: an `[ADDR] RECORDS [BATCHES]` table or a JSON-lines
+    /// Differential (+ Timely Operates) event capture.
+    #[arg(long)]
+    arrangements: Option<String>,
+
+    /// FlowLog program (.dl) to annotate with the cost of each rule.
+    #[arg(long)]
+    source: Option<String>,
+}
+
+impl InputArgs {
+    fn overlays(&self) -> Overlays {
+        Overlays {
+            sizes: self.sizes.clone(),
+            arrangements: self.arrangements.clone(),
+            source: self.source.clone(),
+        }
+    }
+
+    /// Every input path, for modes that watch them.
+    fn paths(&self) -> Vec<&str> {
+        let mut paths = as_strs(&self.log);
+        paths.push(&self.ops);
+        paths.extend(
+            self.sizes
+                .iter()
+                .chain(&self.arrangements)
+                .chain(&self.source)
+                .map(String::as_str),
+        );
+        paths
+    }
+
+    fn load(&self, opts: &ParseOptions) -> Result<Profile> {
+        let mut profile = Profile::load_runs(&as_strs(&self.log), &self.ops, opts)?;
+        profile.apply_overlays(&self.overlays())?;
+        Ok(profile)
+    }
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    /// Path to the Timely profile log (`-` for stdin, may be .gz/.zst); repeat
+    /// for runs of the same program to aggregate them with their spread.
+    #[arg(short = 'l', long, required = true)]
+    log: Vec<String>,
+
+    /// Path to the ops.json spec (`-` for stdin, may be .gz/.zst).
     #[arg(short = 'p', long)]
     ops: String,
 
-    /// Output HTML file.
+    /// Relation/fingerprint tuple counts (JSON or a `KEY [INPUT] OUTPUT` table)
+    /// to show µs per tuple and tuples/s.
+    #[arg(long)]
+    sizes: Option<String>,
+
+    /// Arrangement sizes: an `[ADDR] RECORDS [BATCHES]` table or a JSON-lines
+    /// Differential (+ Timely Operates) event capture.
+    #[arg(long)]
+    arrangements: Option<String>,
+
+    /// FlowLog program (.dl) to annotate with the cost of each rule.
+    #[arg(long)]
+    source: Option<String>,
+
+    /// Output file, `-` for stdout (a directory for csv/tsv).
+    #[arg(short = 'o', long)]
+    out: String,
+
+    /// Output format.
+    #[arg(short = 'f', long, value_enum, default_value_t = Format::Html)]
+    format: Format,
+
+    /// How html output embeds the report data (auto compresses large reports).
+    #[arg(long, value_enum, default_value_t = EmbedArg::Auto)]
+    embed: EmbedArg,
+
+    /// Number of nodes and rules listed in summary formats.
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// Keep running and regenerate the output whenever --log or --ops change.
+    #[arg(short = 'w', long)]
+    watch: bool,
+
+    /// Polling interval for --watch, in milliseconds.
+    #[arg(long, default_value_t = 500)]
+    poll_ms: u64,
+}
+
+#[derive(Args)]
+struct ExportSqliteArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// SQLite database to write or append to.
+    #[arg(short = 'd', long)]
+    db: String,
+
+    /// Run metadata as KEY=VALUE (repeatable), e.g. --meta commit=abc123.
+    #[arg(short = 'm', long = "meta", value_parser = parse_key_value)]
+    meta: Vec<(String, String)>,
+}
+
+#[derive(Args)]
+struct RecordArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// History file to append to (created if missing), or a directory holding history.jsonl.
+    #[arg(short = 'H', long, default_value = history::HISTORY_FILE)]
+    history: String,
+
+    /// Run label as KEY=VALUE (repeatable), e.g. --label commit=abc123 --label workers=8.
+    #[arg(short = 'L', long = "label", value_parser = parse_key_value)]
+    labels: Vec<(String, String)>,
+}
+
+#[derive(Args)]
+struct HistoryArgs {
+    /// History file written by `record`, or a directory holding history.jsonl.
+    #[arg(short = 'H', long, default_value = history::HISTORY_FILE)]
+    history: String,
+
+    /// Only entries with this label, as KEY=VALUE (repeatable; all must match).
+    #[arg(short = 'W', long = "where", value_parser = parse_key_value)]
+    filter: Vec<(String, String)>,
+
+    /// Number of rules and nodes with trends (those with the highest ms in any entry).
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// Output format.
+    #[arg(short = 'f', long, value_enum, default_value_t = HistoryFormat::Text)]
+    format: HistoryFormat,
+
+    /// Output file, `-` for stdout.
+    #[arg(short = 'o', long, default_value = input::STDIO)]
+    out: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum HistoryFormat {
+    /// Entry table and sparklines.
+    Text,
+    /// Entries and trends as JSON.
+    Json,
+    /// Self-contained page with trend charts.
+    Html,
+}
+
+#[derive(Args)]
+struct CompareArgs {
+    /// Path to the ops.json spec shared by both sides (may be .gz/.zst).
+    #[arg(short = 'p', long)]
+    ops: String,
+
+    /// Profile log of a baseline run (repeat for repeated runs).
+    #[arg(short = 'b', long, required = true)]
+    base: Vec<String>,
+
+    /// Profile log of a candidate run (repeat for repeated runs).
+    #[arg(short = 'c', long, required = true)]
+    head: Vec<String>,
+
+    /// Significance level; changes with a higher p-value count as noise.
+    #[arg(long, default_value_t = 0.05)]
+    alpha: f64,
+
+    /// Minimum change of the mean, in percent, to report.
+    #[arg(long, default_value_t = 5.0)]
+    min_change: f64,
+
+    /// Minimum change of the mean, in ms, to report.
+    #[arg(long, default_value_t = 1.0)]
+    min_ms: f64,
+
+    /// Output format.
+    #[arg(short = 'f', long, value_enum, default_value_t = CompareFormat::Text)]
+    format: CompareFormat,
+
+    /// Exit with an error if any node or rule got slower.
+    #[arg(long)]
+    fail_on_regression: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CompareFormat {
+    /// Human-readable summary.
+    Text,
+    /// The full comparison as JSON.
+    Json,
+    /// A Markdown table of deltas with ▲/▼ arrows, for PR comments.
+    Markdown,
+}
+
+#[derive(Args)]
+struct DashboardArgs {
+    /// Directory whose subdirectories each hold profile.log, ops.json and optionally meta.json.
+    #[arg(short = 'r', long)]
+    runs: String,
+
+    /// Output directory for index.html, index.json and runs/<name>.html.
     #[arg(short = 'o', long)]
     out: String,
+
+    /// Number of nodes with trend lines (those with the highest self time in any run).
+    #[arg(long, default_value_t = 25)]
+    top: usize,
+
+    /// Order runs by this meta.json field instead of by directory name.
+    #[arg(long)]
+    sort_by: Option<String>,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Address to listen on.
+    #[arg(short = 'a', long, default_value = "127.0.0.1:8000")]
+    addr: String,
+
+    /// Polling interval for input changes, in milliseconds.
+    #[arg(long, default_value_t = 500)]
+    poll_ms: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DiagnosticsFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormatArg {
+    /// Detect from the first bytes of the input.
+    Auto,
+    /// Aggregated profile table (whitespace-separated columns).
+    Table,
+    /// Raw Timely logging events, one JSON record per line.
+    EventsJson,
+    /// Raw Timely logging events, bincode-encoded.
+    EventsBincode,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum EmbedArg {
+    /// Plain JSON, compressed above 1 MiB.
+    Auto,
+    /// Always plain JSON.
+    Json,
+    /// Always deflate + base64 (needs a browser with DecompressionStream).
+    Deflate,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Self-contained interactive HTML report.
+    Html,
+    /// Compact GitHub-flavored Markdown summary (for ▲/▼ deltas against a
+    /// baseline, use `compare --format markdown`).
+    Markdown,
+    /// nodes.csv + operators.csv + relations.csv (+ edges.csv) tables.
+    Csv,
+    /// nodes.tsv + operators.tsv + relations.tsv (+ edges.tsv) tables.
+    Tsv,
 }
 
-fn main() -> Result<()> {
-    let Cli { log, ops, out } = Cli::parse();
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    diagnostics::set_color(match cli.color {
+        ColorChoice::Auto => diagnostics::color_auto(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    });
+    diagnostics::set_format(match cli.diagnostics_format {
+        DiagnosticsFormat::Text => diagnostics::Format::Text,
+        DiagnosticsFormat::Json => diagnostics::Format::Json,
+    });
 
-    // 1) Parse + validate ops.json (contains both topology + operator mapping).
-    let ops_text = fs::read_to_string(&ops)
-        .with_context(|| diagnostics::error_message(format!("read ops file {}", ops)))?;
-    let ops_spec: ops::OpsSpec = serde_json::from_str(&ops_text)
-        .with_context(|| diagnostics::error_message(format!("parse ops file {}", ops)))?;
-    let validated = ops_spec.validate_and_build()?;
+    // Errors are printed here, and only here, so coloring stays out of messages.
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            diagnostics::error(&err);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    let ops::ValidatedOps {
-        nodes,
-        roots: root_ids,
-        rules,
-        fingerprint_to_node,
-    } = validated;
+fn run(cli: Cli) -> Result<()> {
+    let opts = ParseOptions {
+        jobs: match cli.jobs {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        },
+        lenient: cli.lenient,
+        format: match cli.log_format {
+            LogFormatArg::Auto => LogFormat::Auto,
+            LogFormatArg::Table => LogFormat::Table,
+            LogFormatArg::EventsJson => LogFormat::Events(EventEncoding::JsonLines),
+            LogFormatArg::EventsBincode => LogFormat::Events(EventEncoding::Bincode),
+        },
+    };
+    match (cli.command, cli.report) {
+        (Some(Command::Annotate(input)), _) => annotate(input, &opts),
+        (Some(Command::Compare(args)), _) => compare(args, &opts),
+        (Some(Command::Dashboard(args)), _) => dashboard(args, &opts),
+        (Some(Command::ExportSqlite(args)), _) => export_sqlite(args, &opts),
+        (Some(Command::History(args)), _) => show_history(args),
+        (Some(Command::Record(args)), _) => record(args, &opts),
+        (Some(Command::Serve(args)), _) => {
+            reject_stdin("serve", &args.input.paths())?;
+            serve::serve(
+                &as_strs(&args.input.log),
+                &args.input.ops,
+                &args.input.overlays(),
+                &args.addr,
+                Duration::from_millis(args.poll_ms),
+                &opts,
+            )
+        }
+        (Some(Command::Tui(input)), _) => tui::run(input.load(&opts)?.data),
+        (None, Some(args)) => report(args, &opts),
+        (None, None) => Cli::command().print_help().map_err(Into::into),
+    }
+}
 
-    // Prepare node map keyed by stringified id for downstream rendering.
-    let mut nodes_by_name = BTreeMap::new();
-    for (id, node) in nodes {
-        nodes_by_name.insert(id.to_string(), node);
+impl ReportArgs {
+    // Not flattened: clap cannot tell whether a flattened group inside the
+    // optional top-level report args was given.
+    fn input(&self) -> InputArgs {
+        InputArgs {
+            log: self.log.clone(),
+            ops: self.ops.clone(),
+            sizes: self.sizes.clone(),
+            arrangements: self.arrangements.clone(),
+            source: self.source.clone(),
+        }
     }
+}
 
-    let roots: Vec<String> = root_ids.iter().map(|id| id.to_string()).collect();
+fn report(args: ReportArgs, opts: &ParseOptions) -> Result<()> {
+    if args.watch {
+        let input = args.input();
+        let paths = input.paths();
+        reject_stdin("--watch", &paths)?;
+        return watch::watch(&paths, Duration::from_millis(args.poll_ms), || {
+            write_report(&args, opts)
+        });
+    }
+    write_report(&args, opts)
+}
 
-    let fingerprint_to_node: BTreeMap<String, String> = fingerprint_to_node
-        .into_iter()
-        .map(|(fp, id)| (fp, id.to_string()))
-        .collect();
+/// Run the full pipeline and write the output; nothing is written on failure.
+fn write_report(args: &ReportArgs, opts: &ParseOptions) -> Result<()> {
+    let profile = args.input().load(opts)?;
 
-    // 2) Parse log.
-    let log_index = log::parse_log_file(&log)?;
+    let out = args.out.as_str();
+    let sink: Box<dyn Sink> = match args.format {
+        Format::Html => Box::new(HtmlFile::new(out).with_embed(match args.embed {
+            EmbedArg::Auto => Embed::Auto,
+            EmbedArg::Json => Embed::Json,
+            EmbedArg::Deflate => Embed::Deflate,
+        })),
+        Format::Markdown => Box::new(MarkdownFile::new(out, args.top)),
+        Format::Csv => Box::new(TableDir::csv(out)),
+        Format::Tsv => Box::new(TableDir::tsv(out)),
+    };
+    profile.write_to(sink.as_ref())?;
+    if out != input::STDIO {
+        println!("Wrote {}", out);
+    }
 
-    // 3) Aggregate.
-    let data = view::build_report_data(
-        &nodes_by_name,
-        &roots,
-        &rules,
-        &fingerprint_to_node,
-        &log_index,
+    Ok(())
+}
+
+fn annotate(input: InputArgs, opts: &ParseOptions) -> Result<()> {
+    if input.source.is_none() {
+        bail!(Error::validation(
+            "annotate needs the program to annotate (--source program.dl)"
+        ));
+    }
+    let profile = input.load(opts)?;
+    print!("{}", source::render_text(&profile.data));
+    Ok(())
+}
+
+fn compare(args: CompareArgs, opts: &ParseOptions) -> Result<()> {
+    let base = Profile::load_runs(&as_strs(&args.base), &args.ops, opts)?;
+    let head = Profile::load_runs(&as_strs(&args.head), &args.ops, opts)?;
+    let cmp_opts = compare::CompareOptions {
+        alpha: args.alpha,
+        min_change: args.min_change / 100.0,
+        min_ms: args.min_ms,
+    };
+    let cmp = compare::compare(&base.data, &head.data, &cmp_opts);
+    match args.format {
+        CompareFormat::Text => print!("{}", compare::render_text(&cmp, &cmp_opts)),
+        CompareFormat::Json => println!("{}", serde_json::to_string_pretty(&cmp)?),
+        CompareFormat::Markdown => {
+            print!("{}", markdown::render_markdown_comparison(&cmp, &cmp_opts))
+        }
+    }
+    let regressions = cmp.regressions();
+    if args.fail_on_regression && regressions > 0 {
+        bail!(Error::validation(format!(
+            "{} node(s)/rule(s) regressed beyond noise",
+            regressions
+        )));
+    }
+    Ok(())
+}
+
+fn dashboard(args: DashboardArgs, opts: &ParseOptions) -> Result<()> {
+    let out = Path::new(&args.out);
+    let data = dashboard::write_dashboard(
+        Path::new(&args.runs),
+        out,
+        opts,
+        args.top,
+        args.sort_by.as_deref(),
     )?;
+    let failed = data.runs.iter().filter(|r| r.error.is_some()).count();
+    for diag in &data.diagnostics {
+        diagnostics::emit(diag);
+    }
+    println!(
+        "Wrote {} ({} runs, {} failed)",
+        out.join("index.html").display(),
+        data.runs.len(),
+        failed
+    );
+    Ok(())
+}
 
-    // 4) Render HTML.
-    let html = render::render_html_report(&data)?;
-    fs::write(&out, html)
-        .with_context(|| diagnostics::error_message(format!("write output file {}", out)))?;
-    println!("Wrote {}", out);
+fn export_sqlite(args: ExportSqliteArgs, opts: &ParseOptions) -> Result<()> {
+    let profile = args.input.load(opts)?;
+    let run_id = sqlite::export_run(
+        Path::new(&args.db),
+        &profile,
+        &args.input.log.join(", "),
+        &args.input.ops,
+        &args.meta,
+    )?;
+    println!("Wrote run {} to {}", run_id, args.db);
+    Ok(())
+}
+
+fn as_strs(paths: &[String]) -> Vec<&str> {
+    paths.iter().map(String::as_str).collect()
+}
 
+fn record(args: RecordArgs, opts: &ParseOptions) -> Result<()> {
+    let profile = args.input.load(opts)?;
+    let recorded_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let labels: BTreeMap<String, String> = args.labels.into_iter().collect();
+    let entry = history::HistoryEntry::new(&profile.data, labels, recorded_at);
+    let file = history::append(Path::new(&args.history), &entry)?;
+    println!("Recorded run in {}", file.display());
+    Ok(())
+}
+
+fn show_history(args: HistoryArgs) -> Result<()> {
+    let entries = history::read(Path::new(&args.history))?;
+    let data = history::build(&entries, &args.filter, args.top);
+    let text = match args.format {
+        HistoryFormat::Text => history::render_text(&data),
+        HistoryFormat::Json => serde_json::to_string_pretty(&data)? + "\n",
+        HistoryFormat::Html => render::render_history_html(&data)?,
+    };
+    if args.out == input::STDIO {
+        print!("{}", text);
+    } else {
+        std::fs::write(&args.out, text).map_err(|e| Error::io("write", args.out.clone(), e))?;
+        println!("Wrote {}", args.out);
+    }
     Ok(())
 }
+
+/// Modes that re-read their inputs on change cannot take them from stdin.
+fn reject_stdin(mode: &str, paths: &[&str]) -> Result<()> {
+    if paths.contains(&input::STDIO) {
+        bail!(Error::validation(format!(
+            "{} re-reads its inputs and cannot read from stdin (-)",
+            mode
+        )));
+    }
+    Ok(())
+}
+
+fn parse_key_value(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.trim().is_empty() => Ok((k.trim().to_string(), v.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got {:?}", s)),
+    }
+}
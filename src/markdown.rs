@@ -0,0 +1,675 @@
+//! Report rendering (GitHub-flavored Markdown).
+//!
+//! Produces a compact summary suitable for posting as a pull-request comment:
+//! totals, top-N nodes, rules and relations, their cost per tuple when `--sizes` is given,
+//! the largest arrangements when `--arrangements` is given, the heaviest DAG
+//! edges when the log has channel events, the critical path through the DAG
+//! with its bottlenecks, a per-block breakdown, coverage warnings, and the
+//! full operator tables folded into `<details>` sections.
+//! [`render_markdown_comparison`] renders a `compare` result the same way.
+
+use crate::compare::{Change, CompareOptions, Comparison, Subject, Verdict};
+use crate::diagnostics::Severity;
+use crate::sizes::SizeView;
+use crate::stats::Spread;
+use crate::view::{NameNodeView, ReportData};
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Render a Markdown summary, listing at most `top` nodes and rules.
+pub fn render_markdown_report(data: &ReportData, top: usize) -> String {
+    let mut out = String::new();
+    let total_ms = data.totals.total_mapped_ms;
+
+    out.push_str("## FlowLog profile\n\n");
+
+    // Totals.
+    let t = &data.totals;
+    out.push_str("| metric | value |\n|---|---:|\n");
+    let _ = writeln!(out, "| names | {} |", t.names);
+    let _ = writeln!(out, "| operators in log | {} |", t.operators_in_log);
+    let _ = writeln!(out, "| operators mapped | {} |", t.operators_mapped);
+    // Repeated runs: values are means, with the 95% CI half-width alongside.
+    let runs = t.runs > 1;
+    if runs {
+        let _ = writeln!(out, "| runs (means) | {} |", t.runs);
+    }
+    let _ = writeln!(
+        out,
+        "| mapped ms | {:.3}{} |",
+        t.total_mapped_ms,
+        plus_minus(t.total_mapped_ms_spread)
+    );
+    let _ = writeln!(
+        out,
+        "| mapped activations | {} |",
+        t.total_mapped_activations
+    );
+    let _ = writeln!(out, "| coverage | {} |", coverage(data));
+    out.push('\n');
+
+    // Top nodes.
+    let mut nodes: Vec<&NameNodeView> = data.nodes.values().collect();
+    nodes.sort_by(|a, b| {
+        b.self_total_active_ms
+            .total_cmp(&a.self_total_active_ms)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let _ = writeln!(out, "### Top {} nodes\n", top.min(nodes.len()));
+    if runs {
+        out.push_str("| # | node | block | ms | ± (95% CI) | % | activations |\n|---:|---|---|---:|---:|---:|---:|\n");
+    } else {
+        out.push_str(
+            "| # | node | block | ms | % | activations |\n|---:|---|---|---:|---:|---:|\n",
+        );
+    }
+    for (i, n) in nodes.iter().take(top).enumerate() {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {:.3} |{} {} | {} |",
+            i + 1,
+            cell(&n.label),
+            cell(&n.block),
+            n.self_total_active_ms,
+            ci_cell(runs, n.ms_spread),
+            pct(n.self_total_active_ms, total_ms),
+            n.self_activations
+        );
+    }
+    out.push('\n');
+
+    // Top rules.
+    if !data.rules.is_empty() {
+        let mut rules: Vec<_> = data.rules.iter().collect();
+        rules.sort_by(|a, b| {
+            b.total_active_ms
+                .total_cmp(&a.total_active_ms)
+                .then_with(|| a.text.cmp(&b.text))
+        });
+        let _ = writeln!(out, "### Top {} rules\n", top.min(rules.len()));
+        if runs {
+            out.push_str(
+                "| # | rule | ms | ± (95% CI) | % | activations |\n|---:|---|---:|---:|---:|---:|\n",
+            );
+        } else {
+            out.push_str("| # | rule | ms | % | activations |\n|---:|---|---:|---:|---:|\n");
+        }
+        for (i, r) in rules.iter().take(top).enumerate() {
+            let _ = writeln!(
+                out,
+                "| {} | `{}` | {:.3} |{} {} | {} |",
+                i + 1,
+                code_cell(&r.text),
+                r.total_active_ms,
+                ci_cell(runs, r.ms_spread),
+                pct(r.total_active_ms, total_ms),
+                r.activations
+            );
+        }
+        out.push('\n');
+    }
+
+    // Relations, when rules parse: cost of everything deriving each one.
+    let derived: Vec<_> = data
+        .relations
+        .iter()
+        .filter(|r| !r.rules.is_empty())
+        .collect();
+    if !derived.is_empty() {
+        let _ = writeln!(out, "### Top {} relations\n", top.min(derived.len()));
+        out.push_str(
+            "| # | relation | rules | ms | % | activations | depends on |\n|---:|---|---:|---:|---:|---:|---|\n",
+        );
+        for (i, r) in derived.iter().take(top).enumerate() {
+            let deps: Vec<String> = r
+                .depends_on
+                .iter()
+                .map(|d| {
+                    if r.negates.contains(d) {
+                        format!("!{}", d)
+                    } else {
+                        d.clone()
+                    }
+                })
+                .collect();
+            let _ = writeln!(
+                out,
+                "| {} | `{}` | {} | {:.3} | {} | {} | {} |",
+                i + 1,
+                code_cell(&r.name),
+                r.rules.len(),
+                r.total_active_ms,
+                pct(r.total_active_ms, total_ms),
+                r.activations,
+                cell(&deps.join(", "))
+            );
+        }
+        out.push('\n');
+    }
+
+    // Efficiency, when --sizes matched anything: slowest per tuple first.
+    let mut sized: Vec<(&str, String, f64, &SizeView)> = nodes
+        .iter()
+        .filter_map(|n| {
+            Some((
+                "node",
+                cell(&n.label),
+                n.self_total_active_ms,
+                n.sizes.as_ref()?,
+            ))
+        })
+        .chain(data.rules.iter().filter_map(|r| {
+            Some((
+                "rule",
+                format!("`{}`", code_cell(&r.text)),
+                r.total_active_ms,
+                r.sizes.as_ref()?,
+            ))
+        }))
+        .collect();
+    if !sized.is_empty() {
+        let per_tuple = |s: &SizeView| s.us_per_tuple.unwrap_or(f64::INFINITY);
+        sized.sort_by(|a, b| per_tuple(b.3).total_cmp(&per_tuple(a.3)));
+        let _ = writeln!(
+            out,
+            "### Efficiency (top {} by µs per tuple)\n",
+            top.min(sized.len())
+        );
+        out.push_str(
+            "| kind | name | ms | tuples | µs/tuple | tuples/s |\n|---|---|---:|---:|---:|---:|\n",
+        );
+        for (kind, name, ms, s) in sized.iter().take(top) {
+            let opt = |v: Option<f64>, digits: usize| {
+                v.map(|v| format!("{:.*}", digits, v))
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            let tuples = match (s.input, s.output) {
+                (Some(i), Some(o)) => format!("{} → {}", i, o),
+                (None, Some(o)) => o.to_string(),
+                (Some(i), None) => format!("{} in", i),
+                (None, None) => "-".to_string(),
+            };
+            let _ = writeln!(
+                out,
+                "| {} | {} | {:.3} | {} | {} | {} |",
+                kind,
+                name,
+                ms,
+                tuples,
+                opt(s.us_per_tuple, 3),
+                opt(s.tuples_per_s, 0)
+            );
+        }
+        out.push('\n');
+    }
+
+    // Arrangement memory, when --arrangements mapped anything: largest first.
+    let mut arranged: Vec<&NameNodeView> = nodes
+        .iter()
+        .copied()
+        .filter(|n| n.arrangement.is_some_and(|a| a.peak_records > 0))
+        .collect();
+    if !arranged.is_empty() {
+        let peak = |n: &NameNodeView| n.arrangement.map_or(0, |a| a.peak_records);
+        arranged.sort_by(|a, b| peak(b).cmp(&peak(a)).then_with(|| a.name.cmp(&b.name)));
+        let _ = writeln!(
+            out,
+            "### Top {} arrangements (by peak records)\n",
+            top.min(arranged.len())
+        );
+        out.push_str(
+            "| # | node | block | peak records | records | batches | ms |\n|---:|---|---|---:|---:|---:|---:|\n",
+        );
+        for (i, n) in arranged.iter().take(top).enumerate() {
+            let a = n.arrangement.unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {:.3} |",
+                i + 1,
+                cell(&n.label),
+                cell(&n.block),
+                a.peak_records,
+                a.records,
+                a.batches,
+                n.self_total_active_ms
+            );
+        }
+        out.push('\n');
+    }
+
+    // Data volume along DAG edges, when the log had channel events.
+    let heaviest: Vec<_> = data.edges.iter().filter(|e| e.records > 0).collect();
+    if !heaviest.is_empty() {
+        let label = |name: &str| {
+            data.nodes
+                .get(name)
+                .map_or_else(|| cell(name), |n| cell(&n.label))
+        };
+        let _ = writeln!(
+            out,
+            "### Top {} edges (by records sent)\n",
+            top.min(heaviest.len())
+        );
+        out.push_str("| # | from | to | records | messages |\n|---:|---|---|---:|---:|\n");
+        for (i, e) in heaviest.iter().take(top).enumerate() {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                i + 1,
+                label(&e.source),
+                label(&e.target),
+                e.records,
+                e.messages
+            );
+        }
+        out.push('\n');
+    }
+
+    // Critical path: the chain of dependent nodes bounding end-to-end time.
+    let critical = &data.critical_path;
+    if !critical.program.nodes.is_empty() {
+        let label = |name: &str| {
+            data.nodes
+                .get(name)
+                .map_or_else(|| cell(name), |n| cell(&n.label))
+        };
+        out.push_str("### Critical path\n\n");
+        let _ = writeln!(
+            out,
+            "{:.3} ms over {} node(s) ({} of mapped ms): {}\n",
+            critical.program.total_ms,
+            critical.program.nodes.len(),
+            pct(critical.program.total_ms, total_ms),
+            critical
+                .program
+                .nodes
+                .iter()
+                .map(|n| label(n))
+                .collect::<Vec<_>>()
+                .join(" → ")
+        );
+        if !critical.bottlenecks.is_empty() {
+            out.push_str(
+                "| # | node | block | self ms | % of path | on block path |\n|---:|---|---|---:|---:|:---:|\n",
+            );
+            for (i, b) in critical.bottlenecks.iter().take(top).enumerate() {
+                let block = data.nodes.get(&b.node).map_or("", |n| n.block.as_str());
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {:.3} | {:.1}% | {} |",
+                    i + 1,
+                    label(&b.node),
+                    cell(block),
+                    b.self_ms,
+                    100.0 * b.share,
+                    if b.on_block_path { "yes" } else { "" }
+                );
+            }
+            out.push('\n');
+        }
+        // The nodes that could grow the most before the path moves.
+        let mut slack: Vec<&NameNodeView> = data
+            .nodes
+            .values()
+            .filter(|n| n.slack_ms.is_some_and(|s| s > 0.0))
+            .collect();
+        slack.sort_by(|a, b| {
+            b.self_total_active_ms
+                .total_cmp(&a.self_total_active_ms)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        if !slack.is_empty() {
+            let _ = writeln!(
+                out,
+                "<details><summary>Off-path nodes with slack ({})</summary>\n",
+                slack.len()
+            );
+            out.push_str("| node | block | self ms | slack ms |\n|---|---|---:|---:|\n");
+            for n in slack.iter().take(top) {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {:.3} | {:.3} |",
+                    cell(&n.label),
+                    cell(&n.block),
+                    n.self_total_active_ms,
+                    n.slack_ms.unwrap_or(0.0)
+                );
+            }
+            out.push_str("\n</details>\n\n");
+        }
+    }
+
+    // Block breakdown.
+    let mut blocks: BTreeMap<&str, (usize, f64, u64)> = BTreeMap::new();
+    for n in data.nodes.values() {
+        let e = blocks.entry(n.block.as_str()).or_default();
+        e.0 += 1;
+        e.1 += n.self_total_active_ms;
+        e.2 += n.self_activations;
+    }
+    out.push_str("### Blocks\n\n");
+    let arrangements = data.arrangements.as_ref();
+    if arrangements.is_some() {
+        out.push_str("| block | nodes | ms | % | critical ms | activations | peak records |\n|---|---:|---:|---:|---:|---:|---:|\n");
+    } else {
+        out.push_str("| block | nodes | ms | % | critical ms | activations |\n|---|---:|---:|---:|---:|---:|\n");
+    }
+    for (block, (count, ms, act)) in &blocks {
+        let critical_ms = critical.blocks.get(*block).map_or(0.0, |p| p.total_ms);
+        let _ = write!(
+            out,
+            "| {} | {} | {:.3} | {} | {:.3} | {} |",
+            cell(block),
+            count,
+            ms,
+            pct(*ms, total_ms),
+            critical_ms,
+            act
+        );
+        if let Some(a) = arrangements {
+            let peak = a.blocks.get(*block).map_or(0, |b| b.peak_records);
+            let _ = write!(out, " {} |", peak);
+        }
+        out.push('\n');
+    }
+    out.push('\n');
+
+    // Warnings.
+    let mut warnings: Vec<String> = Vec::new();
+    let unmapped = t.operators_in_log.saturating_sub(t.operators_mapped);
+    if unmapped > 0 {
+        warnings.push(format!(
+            "{} of {} operators in the log are not mapped to any node (coverage {})",
+            unmapped,
+            t.operators_in_log,
+            coverage(data)
+        ));
+    }
+    for d in &data.diagnostics {
+        if d.severity == Severity::Warning {
+            warnings.push(format!("`{}` {}", d.code, cell(&d.message)));
+        }
+    }
+    if !warnings.is_empty() {
+        out.push_str("### Warnings\n\n");
+        for w in &warnings {
+            let _ = writeln!(out, "- :warning: {}", w);
+        }
+        out.push('\n');
+    }
+
+    // Full operator tables, folded.
+    out.push_str("<details>\n<summary>Operators by node</summary>\n\n");
+    for n in &nodes {
+        if n.operators.is_empty() {
+            continue;
+        }
+        let _ = writeln!(
+            out,
+            "**{}** ({}, {:.3} ms)\n",
+            cell(&n.label),
+            cell(&n.block),
+            n.self_total_active_ms
+        );
+        let has_dist = n.operators.iter().any(|op| op.activation.is_some());
+        if has_dist {
+            out.push_str(
+                "| addr | operator | ms | activations | p50 ms | p99 ms | max ms |\n\
+                 |---|---|---:|---:|---:|---:|---:|\n",
+            );
+        } else {
+            out.push_str("| addr | operator | ms | activations |\n|---|---|---:|---:|\n");
+        }
+        for op in &n.operators {
+            let tail = match &op.activation {
+                Some(act) if act.heavy_tail => " :warning: heavy tail",
+                _ => "",
+            };
+            let _ = write!(
+                out,
+                "| `{:?}` | {}{} | {:.3} | {} |",
+                op.addr,
+                cell(&op.op_name),
+                tail,
+                op.total_active_ms,
+                op.activations
+            );
+            match &op.activation {
+                Some(act) => {
+                    let _ = write!(
+                        out,
+                        " {:.3} | {:.3} | {:.3} |",
+                        act.p50_ms, act.p99_ms, act.max_ms
+                    );
+                }
+                None if has_dist => out.push_str("  |  |  |"),
+                None => {}
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    out.push_str("</details>\n");
+
+    out
+}
+
+/// Render a `compare` result as a Markdown table of deltas, for PR comments:
+/// ▲ for slower, ▼ for faster, with the % change per node and rule.
+pub fn render_markdown_comparison(cmp: &Comparison, opts: &CompareOptions) -> String {
+    let mut out = String::new();
+    out.push_str("## FlowLog profile: base → head\n\n");
+    let [base, head, delta] = delta_cells(&cmp.total);
+    let _ = writeln!(
+        out,
+        "{} base run(s), {} head run(s); total mapped ms {} → {} ({})\n",
+        cmp.base_runs, cmp.head_runs, base, head, delta
+    );
+    if cmp.base_runs < 2 || cmp.head_runs < 2 {
+        out.push_str(
+            "> With a single run on a side there is no noise estimate; only the thresholds apply.\n\n",
+        );
+    }
+
+    if cmp.changes.is_empty() {
+        out.push_str("No node or rule changed beyond noise.\n\n");
+    } else {
+        out.push_str("| | kind | node / rule | base ms | head ms | Δ | p |\n");
+        out.push_str("|:---:|---|---|---:|---:|---:|---:|\n");
+        for c in &cmp.changes {
+            let kind = match c.subject {
+                Subject::Node => "node",
+                Subject::Rule => "rule",
+                Subject::Total => "total",
+            };
+            let [base, head, delta] = delta_cells(c);
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {} |",
+                arrow(c.verdict),
+                kind,
+                cell(&c.label),
+                base,
+                head,
+                delta,
+                c.p_value
+                    .map_or_else(|| "-".to_string(), |p| format!("{:.3}", p))
+            );
+        }
+        out.push('\n');
+    }
+    let _ = writeln!(
+        out,
+        "{} regression(s), {} improvement(s); {} node(s)/rule(s) within noise or below thresholds (alpha {}, min change {:.0}%, min {} ms)",
+        cmp.regressions(),
+        cmp.changes.len() - cmp.regressions(),
+        cmp.unchanged,
+        opts.alpha,
+        opts.min_change * 100.0,
+        opts.min_ms
+    );
+    out
+}
+
+fn arrow(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::Slower => "▲",
+        Verdict::Faster => "▼",
+        Verdict::Unchanged => "",
+    }
+}
+
+/// Base and head means (± CI half-width over runs), and the arrowed % delta.
+fn delta_cells(c: &Change) -> [String; 3] {
+    let side = |s: &Spread| match ci_half(Some(*s)) {
+        Some(half) => format!("{:.3} ± {:.3}", s.mean, half),
+        None => format!("{:.3}", s.mean),
+    };
+    let delta = format!("{} {:+.1}%", arrow(c.verdict), c.change * 100.0);
+    [side(&c.base), side(&c.head), delta.trim_start().to_string()]
+}
+
+fn coverage(data: &ReportData) -> String {
+    let t = &data.totals;
+    if t.operators_in_log == 0 {
+        return "n/a".to_string();
+    }
+    format!(
+        "{:.1}%",
+        100.0 * t.operators_mapped as f64 / t.operators_in_log as f64
+    )
+}
+
+/// Half-width of the 95% CI, or nothing for a single run.
+fn ci_half(spread: Option<Spread>) -> Option<f64> {
+    spread
+        .filter(|s| s.runs > 1)
+        .map(|s| (s.ci_high - s.ci_low) / 2.0)
+}
+
+fn plus_minus(spread: Option<Spread>) -> String {
+    ci_half(spread)
+        .map(|h| format!(" ± {:.3}", h))
+        .unwrap_or_default()
+}
+
+/// An extra ` ± | ` table cell when the report aggregates several runs.
+fn ci_cell(runs: bool, spread: Option<Spread>) -> String {
+    if !runs {
+        return String::new();
+    }
+    format!(" {:.3} |", ci_half(spread).unwrap_or(0.0))
+}
+
+fn pct(part: f64, total: f64) -> String {
+    if total <= 0.0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", 100.0 * part / total)
+}
+
+/// Escape text for a Markdown table cell (pipes, inline HTML, newlines).
+fn cell(s: &str) -> String {
+    s.replace('|', "\\|")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace(['\r', '\n'], " ")
+}
+
+/// Escape text for an inline code span inside a table cell.
+fn code_cell(s: &str) -> String {
+    s.replace('|', "\\|")
+        .replace('`', "'")
+        .replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::parse_log;
+    use crate::ops::OpsSpec;
+    use crate::profile::Profile;
+    use pretty_assertions::assert_eq;
+
+    fn rows<'a>(markdown: &'a str, prefix: &str) -> Vec<&'a str> {
+        markdown.lines().filter(|l| l.starts_with(prefix)).collect()
+    }
+
+    #[test]
+    fn table_cells_are_escaped() {
+        let ops: OpsSpec = serde_json::from_value(serde_json::json!({
+            "nodes": [{
+                "id": 0,
+                "name": "a | b <i>x</i>\nline",
+                "block": "blk|x",
+                "fingerprint": "0xa",
+                "operators": [[0, 1]],
+                "parents": [],
+            }],
+            "rules": [{
+                "text": "r(x) :- s(x, \"|\"),\n  `t`(x).",
+                "plan_tree": [{"fingerprint": "0xa", "parents": []}],
+            }],
+        }))
+        .unwrap();
+        let index = parse_log("[0, 1] 2 4.000 Map\n".as_bytes(), "log").unwrap();
+        let data = Profile::from_parts(&ops, index).unwrap().data;
+        let md = render_markdown_report(&data, 10);
+
+        assert_eq!(
+            rows(&md, "| 1 |"),
+            [
+                r"| 1 | a \| b &lt;i&gt;x&lt;/i&gt; line | blk\|x | 4.000 | 100.0% | 2 |",
+                r#"| 1 | `r(x) :- s(x, "\|"),   't'(x).` | 4.000 | 100.0% | 2 |"#,
+                // Critical path.
+                r"| 1 | a \| b &lt;i&gt;x&lt;/i&gt; line | blk\|x | 4.000 | 100.0% | yes |",
+            ]
+        );
+    }
+
+    fn change(subject: Subject, label: &str, base: f64, head: f64, verdict: Verdict) -> Change {
+        Change {
+            subject,
+            key: label.to_string(),
+            label: label.to_string(),
+            base: Spread::of(&[base]).unwrap(),
+            head: Spread::of(&[head]).unwrap(),
+            change: head / base - 1.0,
+            p_value: None,
+            verdict,
+        }
+    }
+
+    #[test]
+    fn comparison_marks_slower_and_faster() {
+        let cmp = Comparison {
+            base_runs: 1,
+            head_runs: 1,
+            total: change(Subject::Total, "total", 30.0, 30.0, Verdict::Unchanged),
+            changes: vec![
+                change(Subject::Node, "x | y", 10.0, 12.0, Verdict::Slower),
+                change(Subject::Rule, "r(x) :-\ns(x).", 20.0, 15.0, Verdict::Faster),
+            ],
+            unchanged: 3,
+        };
+        let md = render_markdown_comparison(&cmp, &CompareOptions::default());
+
+        assert!(
+            md.contains("total mapped ms 30.000 → 30.000 (+0.0%)"),
+            "{md}"
+        );
+        assert!(md.contains("> With a single run on a side"), "{md}");
+        assert_eq!(
+            rows(&md, "| ▲ |")
+                .into_iter()
+                .chain(rows(&md, "| ▼ |"))
+                .collect::<Vec<_>>(),
+            [
+                r"| ▲ | node | x \| y | 10.000 | 12.000 | ▲ +20.0% | - |",
+                "| ▼ | rule | r(x) :- s(x). | 20.000 | 15.000 | ▼ -25.0% | - |",
+            ]
+        );
+        assert!(md.contains("1 regression(s), 1 improvement(s); 3 node(s)/rule(s)"));
+    }
+}
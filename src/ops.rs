@@ -18,21 +18,22 @@
 //! We validate ids, turn operator address arrays into Addr, and compute roots
 //! (nodes with no incoming edges).
 
-use crate::Result;
 use crate::addr::Addr;
+use crate::diagnostics::Diagnostic;
+use crate::error::Error;
 
-use crate::diagnostics;
-
-use anyhow::bail;
 use serde::Deserialize;
 use serde::de::Deserializer;
 use std::collections::{BTreeMap, BTreeSet};
 
+/// ops.json as written by FlowLog, before validation.
 #[derive(Debug, Clone, Deserialize)]
 pub struct OpsSpec {
+    /// Logical nodes.
     #[serde(default)]
     pub nodes: Vec<RawNode>,
 
+    /// Rules with their plan trees.
     #[serde(default)]
     pub rules: Vec<RawRule>,
 }
@@ -40,23 +41,30 @@ pub struct OpsSpec {
 /// Raw node shape as it appears in ops.json.
 #[derive(Debug, Clone, Deserialize)]
 pub struct RawNode {
+    /// Unique node id.
     pub id: u32,
 
+    /// Display label.
     #[serde(default)]
     pub name: String,
 
+    /// Block (`input`, `stratum N`, `inspect`, ...).
     #[serde(default)]
     pub block: Option<String>,
 
+    /// Plan fingerprint, e.g. `0x710ec2cd2932502a`.
     #[serde(default)]
     pub fingerprint: Option<String>,
 
+    /// Free-form tags.
     #[serde(default)]
     pub tags: Vec<String>,
 
+    /// Timely operator addresses owned by the node.
     #[serde(default)]
     pub operators: Vec<Addr>,
 
+    /// Ids of the DAG parents.
     #[serde(default)]
     pub parents: Vec<u32>,
 }
@@ -64,18 +72,23 @@ pub struct RawNode {
 /// Rule-level plan tree description keyed by fingerprints.
 #[derive(Debug, Clone, Deserialize)]
 pub struct RawRule {
+    /// Rule text as written in the program.
     #[serde(default)]
     pub text: String,
 
+    /// Plan nodes of the rule.
     #[serde(default)]
     pub plan_tree: Vec<RawPlanNode>,
 }
 
+/// One plan node of a rule, linked to its parents by fingerprint.
 #[derive(Debug, Clone, Deserialize)]
 pub struct RawPlanNode {
+    /// Plan fingerprint; never empty.
     #[serde(deserialize_with = "deserialize_fingerprint")]
     pub fingerprint: String,
 
+    /// Fingerprints of the parent plan nodes.
     #[serde(default)]
     pub parents: Vec<String>,
 }
@@ -83,24 +96,37 @@ pub struct RawPlanNode {
 /// Flattened, validated node ready for aggregation.
 #[derive(Debug, Clone)]
 pub struct NodeSpec {
+    /// Unique node id.
     pub id: u32,
+    /// Display label (the ops.json `name`).
     pub label: String,
+    /// Block, `other` when missing.
     pub block: String,
+    /// Plan fingerprint, trimmed; `None` when empty.
     pub fingerprint: Option<String>,
+    /// Free-form tags.
     pub tags: Vec<String>,
+    /// Sorted, deduplicated ids of the DAG parents.
     pub parents: Vec<u32>,
+    /// Timely operator addresses owned by the node.
     pub operators: BTreeSet<Addr>,
 }
 
+/// One plan node of a validated rule.
 #[derive(Debug, Clone)]
 pub struct RulePlanNodeSpec {
+    /// Fingerprints of the child plan nodes.
     pub children: Vec<String>,
 }
 
+/// A validated rule with its plan tree.
 #[derive(Debug, Clone)]
 pub struct RuleSpec {
+    /// Rule text.
     pub text: String,
+    /// Fingerprint of the plan root.
     pub root: String,
+    /// Plan nodes by fingerprint.
     pub nodes: BTreeMap<String, RulePlanNodeSpec>,
 }
 
@@ -111,21 +137,37 @@ impl OpsSpec {
     /// 1) Normalize node rows (dedup parents, normalize fingerprints).
     /// 2) Validate structural integrity (unique ids, parents exist, fingerprint rules).
     /// 3) Build rule plan trees (derive children, compute roots).
-    pub fn validate_and_build(&self) -> Result<ValidatedOps> {
+    pub fn validate_and_build(&self) -> Result<ValidatedOps, Error> {
         // Phase 1: build map keyed by id and normalize node fields.
         let mut nodes: BTreeMap<u32, NodeSpec> = BTreeMap::new();
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
         for raw in &self.nodes {
             if nodes.contains_key(&raw.id) {
-                bail!(
-                    "{}",
-                    diagnostics::error_message(format!(
-                        "duplicate node id in ops.json: {}",
-                        raw.id
-                    ))
-                );
+                return Err(Error::validation(format!(
+                    "duplicate node id in ops.json: {}",
+                    raw.id
+                )));
             }
 
-            let block = raw.block.clone().unwrap_or_else(|| "other".to_string());
+            let block = raw.block.clone().unwrap_or_else(|| {
+                diagnostics.push(
+                    Diagnostic::info(
+                        "node-without-block",
+                        format!("node {} has no block; grouped under 'other'", raw.id),
+                    )
+                    .with_node(raw.id.to_string()),
+                );
+                "other".to_string()
+            });
+            if raw.operators.is_empty() {
+                diagnostics.push(
+                    Diagnostic::warning(
+                        "node-without-operators",
+                        format!("node {} ('{}') maps no operators", raw.id, raw.name),
+                    )
+                    .with_node(raw.id.to_string()),
+                );
+            }
 
             let fingerprint = raw
                 .fingerprint
@@ -152,10 +194,7 @@ impl OpsSpec {
         }
 
         if nodes.is_empty() {
-            bail!(
-                "{}",
-                diagnostics::error_message("ops.json contained no nodes")
-            );
+            return Err(Error::validation("ops.json contained no nodes"));
         }
 
         // Phase 2a: enforce unique, non-empty fingerprints within the same block.
@@ -166,13 +205,10 @@ impl OpsSpec {
             if let Some(fp) = &node.fingerprint {
                 let key = (node.block.clone(), fp.clone());
                 if let Some(prev) = fingerprint_block_to_node.insert(key.clone(), *id) {
-                    bail!(
-                        "{}",
-                        diagnostics::error_message(format!(
-                            "fingerprint '{}' is used by multiple nodes in block '{}' ({} and {})",
-                            fp, key.0, prev, id
-                        ))
-                    );
+                    return Err(Error::validation(format!(
+                        "fingerprint '{}' is used by multiple nodes in block '{}' ({} and {})",
+                        fp, key.0, prev, id
+                    )));
                 }
                 fingerprint_to_node.entry(fp.clone()).or_insert(*id);
             }
@@ -192,13 +228,10 @@ impl OpsSpec {
         for node in nodes.values() {
             for pid in &node.parents {
                 if !nodes.contains_key(pid) {
-                    bail!(
-                        "{}",
-                        diagnostics::error_message(format!(
-                            "node {} references missing parent id {}",
-                            node.id, pid
-                        ))
-                    );
+                    return Err(Error::validation(format!(
+                        "node {} references missing parent id {}",
+                        node.id, pid
+                    )));
                 }
             }
         }
@@ -212,31 +245,22 @@ impl OpsSpec {
             for pn in &raw_rule.plan_tree {
                 let fp = pn.fingerprint.trim();
                 if fp.is_empty() {
-                    bail!(
-                        "{}",
-                        diagnostics::error_message(format!(
-                            "rule '{}' has an empty fingerprint entry",
-                            raw_rule.text
-                        ))
-                    );
+                    return Err(Error::validation(format!(
+                        "rule '{}' has an empty fingerprint entry",
+                        raw_rule.text
+                    )));
                 }
                 if nodes_map.contains_key(fp) {
-                    bail!(
-                        "{}",
-                        diagnostics::error_message(format!(
-                            "rule '{}' has duplicate fingerprint '{}' in plan tree",
-                            raw_rule.text, fp
-                        ))
-                    );
+                    return Err(Error::validation(format!(
+                        "rule '{}' has duplicate fingerprint '{}' in plan tree",
+                        raw_rule.text, fp
+                    )));
                 }
                 if !fingerprint_to_node.contains_key(fp) {
-                    bail!(
-                        "{}",
-                        diagnostics::error_message(format!(
-                            "rule '{}' references fingerprint '{}' not found in any node",
-                            raw_rule.text, fp
-                        ))
-                    );
+                    return Err(Error::validation(format!(
+                        "rule '{}' references fingerprint '{}' not found in any node",
+                        raw_rule.text, fp
+                    )));
                 }
 
                 let parents = normalize_parents(pn.parents.clone());
@@ -247,13 +271,10 @@ impl OpsSpec {
             for (fp, parents) in &raw_parents {
                 for parent in parents {
                     if !raw_parents.contains_key(parent) {
-                        bail!(
-                            "{}",
-                            diagnostics::error_message(format!(
-                                "rule '{}' references parent fingerprint '{}' not present in its plan tree",
-                                raw_rule.text, parent
-                            ))
-                        );
+                        return Err(Error::validation(format!(
+                            "rule '{}' references parent fingerprint '{}' not present in its plan tree",
+                            raw_rule.text, parent
+                        )));
                     }
                 }
                 nodes_map.entry(fp.clone()).or_insert(RulePlanNodeSpec {
@@ -281,18 +302,16 @@ impl OpsSpec {
             // Compute sink (node with no children) and ensure exactly one.
             let sinks: Vec<String> = nodes_map
                 .iter()
-                .filter_map(|(fp, node)| node.children.is_empty().then(|| fp.clone()))
+                .filter(|(_, node)| node.children.is_empty())
+                .map(|(fp, _)| fp.clone())
                 .collect();
 
             if sinks.len() != 1 {
-                bail!(
-                    "{}",
-                    diagnostics::error_message(format!(
-                        "rule '{}' plan tree must have exactly one sink fingerprint (found {})",
-                        raw_rule.text,
-                        sinks.len()
-                    ))
-                );
+                return Err(Error::validation(format!(
+                    "rule '{}' plan tree must have exactly one sink fingerprint (found {})",
+                    raw_rule.text,
+                    sinks.len()
+                )));
             }
 
             let root_fp = sinks[0].clone();
@@ -310,16 +329,13 @@ impl OpsSpec {
             rule_fps.extend(rule.nodes.keys().cloned());
         }
         for (id, node) in &nodes {
-            if let Some(fp) = &node.fingerprint {
-                if !rule_fps.contains(fp) {
-                    bail!(
-                        "{}",
-                        diagnostics::error_message(format!(
-                            "node {} has fingerprint '{}' but it is not recorded in rules",
-                            id, fp
-                        ))
-                    );
-                }
+            if let Some(fp) = &node.fingerprint
+                && !rule_fps.contains(fp)
+            {
+                return Err(Error::validation(format!(
+                    "node {} has fingerprint '{}' but it is not recorded in rules",
+                    id, fp
+                )));
             }
         }
 
@@ -328,6 +344,7 @@ impl OpsSpec {
             roots,
             rules: rules_out,
             fingerprint_to_node,
+            diagnostics,
         })
     }
 }
@@ -339,12 +356,19 @@ fn normalize_parents<T: Ord>(mut parents: Vec<T>) -> Vec<T> {
     parents
 }
 
+/// ops.json after validation: consistent ids, parents and fingerprints.
 #[derive(Debug, Clone)]
 pub struct ValidatedOps {
+    /// Nodes by id.
     pub nodes: BTreeMap<u32, NodeSpec>,
+    /// Nodes without parents.
     pub roots: Vec<u32>,
+    /// Rules with their plan trees.
     pub rules: Vec<RuleSpec>,
+    /// Node id per plan fingerprint.
     pub fingerprint_to_node: BTreeMap<String, u32>,
+    /// Non-fatal findings (e.g. nodes without operators).
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 fn deserialize_fingerprint<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
@@ -355,9 +379,7 @@ where
     let s = String::deserialize(deserializer)?;
 
     if s.trim().is_empty() {
-        return Err(serde::de::Error::custom(diagnostics::error_message(
-            "fingerprint cannot be empty",
-        )));
+        return Err(serde::de::Error::custom("fingerprint cannot be empty"));
     }
     Ok(s)
 }
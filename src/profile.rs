@@ -0,0 +1,311 @@
+//! High-level entry point: load FlowLog profiling artifacts into ReportData.
+//!
+//! ```no_run
+//! use flowlog_profile_viz::{HtmlFile, Profile};
+//!
+//! let profile = Profile::load("profile.log", "ops.json")?;
+//! println!("{:.3} ms mapped", profile.data.totals.total_mapped_ms);
+//! profile.write_to(&HtmlFile::new("report.html"))?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::Result;
+use crate::arrangements;
+use crate::diagnostics::{self, Diagnostic};
+use crate::edges;
+use crate::error::Error;
+use crate::events::ChannelVolume;
+use crate::input;
+use crate::log::{self, LogIndex, ParseOptions, ParseStats};
+use crate::ops::{OpsSpec, ValidatedOps};
+use crate::sink::Sink;
+use crate::sizes;
+use crate::source;
+use crate::view::{self, ReportData};
+
+use anyhow::bail;
+use std::collections::BTreeMap;
+
+/// Everything derived from one (profile log, ops.json) pair.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// Validated ops.json (nodes keyed by numeric id, rules, fingerprints).
+    pub ops: ValidatedOps,
+    /// Parsed profile log, keyed by operator address.
+    pub log: LogIndex,
+    /// Aggregated view consumed by every renderer.
+    pub data: ReportData,
+}
+
+/// Optional side inputs joined onto the report data after aggregation.
+#[derive(Debug, Clone, Default)]
+pub struct Overlays {
+    /// Relation cardinalities (see [`crate::sizes`]).
+    pub sizes: Option<String>,
+    /// Arrangement size table or Differential event capture (see [`crate::arrangements`]).
+    pub arrangements: Option<String>,
+    /// FlowLog program to annotate with rule costs (see [`crate::source`]).
+    pub source: Option<String>,
+}
+
+impl Overlays {
+    /// Overlay input paths, for modes that watch their inputs.
+    pub fn paths(&self) -> Vec<&str> {
+        self.sizes
+            .iter()
+            .chain(&self.arrangements)
+            .chain(&self.source)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+impl Profile {
+    /// Read, parse and validate `ops` (ops.json) and `log` (profile log), then aggregate.
+    ///
+    /// Either path may be `-` for stdin, and either may be gzip/zstd compressed.
+    pub fn load(log: &str, ops: &str) -> Result<Self> {
+        Self::load_with(log, ops, &ParseOptions::default())
+    }
+
+    /// [`Profile::load`] with explicit log parsing options.
+    pub fn load_with(log: &str, ops: &str, opts: &ParseOptions) -> Result<Self> {
+        Self::load_runs(&[log], ops, opts)
+    }
+
+    /// Load repeated runs of the same program: one profile log per run, one ops.json.
+    ///
+    /// With several logs, counts and times are means over the runs (see
+    /// [`log::mean_index`]) and nodes, operators, rules and totals carry their
+    /// spread across runs (see [`view::attach_spreads`]).
+    pub fn load_runs(logs: &[&str], ops: &str, opts: &ParseOptions) -> Result<Self> {
+        if logs.is_empty() {
+            bail!(Error::validation("at least one profile log is required"));
+        }
+        if ops == input::STDIO && logs.contains(&input::STDIO) {
+            bail!(Error::validation(
+                "--log and --ops cannot both be read from stdin"
+            ));
+        }
+        if logs.iter().filter(|l| **l == input::STDIO).count() > 1 {
+            bail!(Error::validation("only one --log can be read from stdin"));
+        }
+
+        // 1) Parse ops.json (contains both topology + operator mapping).
+        let ops_text = input::read_to_string(ops, "read ops file")?;
+        let ops_name = input::display_name(ops);
+        let ops_spec: OpsSpec =
+            serde_json::from_str(&ops_text).map_err(|e| Error::schema(ops_name, e))?;
+        let validated = ops_spec
+            .validate_and_build()
+            .map_err(|e| e.in_file(ops_name))?;
+
+        // 2) Parse log(s).
+        if let [log] = logs {
+            let parsed = log::parse_log_file_with(log, opts)?;
+            return Self::from_validated(
+                validated,
+                parsed.index,
+                parsed.stats,
+                &parsed.channels,
+                input::display_name(log),
+            );
+        }
+        let mut indexes = Vec::with_capacity(logs.len());
+        let mut runs = Vec::with_capacity(logs.len());
+        let mut channels = Vec::with_capacity(logs.len());
+        let mut findings = Vec::new();
+        let mut log_stats = ParseStats::default();
+        for log in logs {
+            let parsed = log::parse_log_file_with(log, opts)?;
+            findings.extend(log_stats_diagnostics(
+                &parsed.stats,
+                input::display_name(log),
+            ));
+            log_stats.merge_run(parsed.stats, input::display_name(log));
+            runs.push(aggregate(&validated, &parsed.index)?);
+            indexes.push(parsed.index);
+            channels.push(parsed.channels);
+        }
+
+        // 3) Aggregate the mean log, then attach the per-run spread. Aggregation
+        // findings (missing addrs, ...) come from the mean only, so each is reported once.
+        let mean = log::mean_index(&indexes);
+        let mut data = aggregate(&validated, &mean)?;
+        view::attach_spreads(&mut data, &runs);
+        let edge_findings =
+            edges::attach_edges(&mut data, &validated, &edges::mean_channels(&channels));
+        data.diagnostics.extend(edge_findings);
+        data.log_stats = log_stats;
+        data.diagnostics
+            .splice(0..0, validated.diagnostics.iter().cloned().chain(findings));
+        for diag in &data.diagnostics {
+            diagnostics::emit(diag);
+        }
+
+        Ok(Profile {
+            ops: validated,
+            log: mean,
+            data,
+        })
+    }
+
+    /// Validate an already-parsed ops spec and aggregate it with a log index.
+    ///
+    /// Useful when the inputs do not come from files (tests, other tools).
+    pub fn from_parts(ops_spec: &OpsSpec, log_index: LogIndex) -> Result<Self> {
+        Self::from_validated(
+            ops_spec.validate_and_build()?,
+            log_index,
+            ParseStats::default(),
+            &[],
+            "log",
+        )
+    }
+
+    fn from_validated(
+        validated: ValidatedOps,
+        log_index: LogIndex,
+        log_stats: ParseStats,
+        channels: &[ChannelVolume],
+        log_name: &str,
+    ) -> Result<Self> {
+        // 3) Aggregate, then put the channel volume on the DAG edges.
+        let mut data = aggregate(&validated, &log_index)?;
+        let edge_findings = edges::attach_edges(&mut data, &validated, channels);
+        data.diagnostics.extend(edge_findings);
+
+        // Validation findings come first, then log parsing, then aggregation.
+        let parse_findings = log_stats_diagnostics(&log_stats, log_name);
+        data.diagnostics.splice(
+            0..0,
+            validated.diagnostics.iter().cloned().chain(parse_findings),
+        );
+        data.log_stats = log_stats;
+        for diag in &data.diagnostics {
+            diagnostics::emit(diag);
+        }
+
+        Ok(Profile {
+            ops: validated,
+            log: log_index,
+            data,
+        })
+    }
+
+    /// Read `overlays` and join them onto the report data.
+    ///
+    /// Their findings (e.g. entries matching nothing) are emitted and appended
+    /// to the report diagnostics like those of [`Profile::load`].
+    pub fn apply_overlays(&mut self, overlays: &Overlays) -> Result<()> {
+        let mut findings = Vec::new();
+        if let Some(path) = &overlays.sizes {
+            let sizes = sizes::read_sizes(path)?;
+            findings.extend(sizes::attach_sizes(&mut self.data, &sizes));
+        }
+        if let Some(path) = &overlays.arrangements {
+            let arrangements = arrangements::read_arrangements(path)?;
+            findings.extend(arrangements::attach_arrangements(
+                &mut self.data,
+                &self.ops,
+                &arrangements,
+            ));
+        }
+        if let Some(path) = &overlays.source {
+            let program = source::read_source(path)?;
+            findings.extend(source::attach_source(&mut self.data, path, &program));
+        }
+        for diag in &findings {
+            diagnostics::emit(diag);
+        }
+        self.data.diagnostics.extend(findings);
+        Ok(())
+    }
+
+    /// Render this profile's report data through `sink`.
+    pub fn write_to(&self, sink: &dyn Sink) -> Result<()> {
+        sink.write(&self.data)
+    }
+}
+
+/// Build report data for one log index, without emitting diagnostics.
+fn aggregate(validated: &ValidatedOps, log_index: &LogIndex) -> Result<ReportData> {
+    // Prepare node map keyed by stringified id for downstream rendering.
+    let mut nodes_by_name = BTreeMap::new();
+    for (id, node) in &validated.nodes {
+        nodes_by_name.insert(id.to_string(), node.clone());
+    }
+
+    let roots: Vec<String> = validated.roots.iter().map(|id| id.to_string()).collect();
+
+    let fingerprint_to_node: BTreeMap<String, String> = validated
+        .fingerprint_to_node
+        .iter()
+        .map(|(fp, id)| (fp.clone(), id.to_string()))
+        .collect();
+
+    view::build_report_data(
+        &nodes_by_name,
+        &roots,
+        &validated.rules,
+        &fingerprint_to_node,
+        log_index,
+    )
+}
+
+/// One summary warning per kind of adjustment, plus the first skipped lines.
+fn log_stats_diagnostics(stats: &ParseStats, log_name: &str) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+    if stats.lines_skipped > 0 {
+        out.push(Diagnostic::warning(
+            "log-lines-skipped",
+            format!(
+                "skipped {} malformed line(s) in {} (--lenient)",
+                stats.lines_skipped, log_name
+            ),
+        ));
+        for skipped in &stats.skipped {
+            out.push(Diagnostic::warning(
+                "log-line-skipped",
+                format!("{}:{}: {}", log_name, skipped.line, skipped.message),
+            ));
+        }
+    }
+    if stats.negatives_clamped > 0 {
+        out.push(Diagnostic::warning(
+            "log-negative-values",
+            format!(
+                "clamped {} negative activation count(s) or time(s) in {} to zero",
+                stats.negatives_clamped, log_name
+            ),
+        ));
+    }
+    if let Some(events) = &stats.events {
+        out.push(Diagnostic::info(
+            "log-events",
+            format!(
+                "read {} Timely event(s) from {} worker(s) spanning {:.3} ms in {}",
+                events.events, events.workers, events.span_ms, log_name
+            ),
+        ));
+        if events.unmatched_schedules > 0 {
+            out.push(Diagnostic::warning(
+                "log-unmatched-schedules",
+                format!(
+                    "{} schedule start/stop event(s) in {} had no partner and were not counted",
+                    events.unmatched_schedules, log_name
+                ),
+            ));
+        }
+        if events.unknown_operators > 0 {
+            out.push(Diagnostic::warning(
+                "log-unknown-operators",
+                format!(
+                    "{} schedule event(s) in {} name operators no Operates event described",
+                    events.unknown_operators, log_name
+                ),
+            ));
+        }
+    }
+    out
+}
@@ -1,17 +1,219 @@
-//! Report rendering (HTML).
+//! Report rendering (HTML): single-run reports, the multi-run dashboard and
+//! the run history.
+//!
+//! The report data is embedded in a `<script type="application/json">` block,
+//! escaped so no label or rule text can end the script early. Large reports
+//! are embedded zlib-compressed and base64-encoded instead, and inflated in
+//! the page with `DecompressionStream`.
 
 use crate::Result;
+use crate::dashboard::DashboardData;
+use crate::history::HistoryData;
 use crate::view::ReportData;
 
-use anyhow::Context;
+use base64::Engine;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
 use serde_json::to_string;
-use std::fs;
+use std::io::Write;
+
+/// Report template, compiled in so the binary and library work from any directory.
+const TEMPLATE: &str = include_str!("../templates/report.html");
+const DASHBOARD_TEMPLATE: &str = include_str!("../templates/dashboard.html");
+const HISTORY_TEMPLATE: &str = include_str!("../templates/history.html");
+
+/// JSON payloads larger than this are compressed under [`Embed::Auto`].
+pub const COMPRESS_THRESHOLD: usize = 1 << 20;
+
+/// How the report data is embedded in the page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Embed {
+    /// Plain JSON up to [`COMPRESS_THRESHOLD`] bytes, compressed above it.
+    #[default]
+    Auto,
+    /// Always plain (escaped) JSON; readable with any browser and `grep`.
+    Json,
+    /// Always compressed; needs a browser with `DecompressionStream`.
+    Deflate,
+}
 
 /// Render a self-contained HTML report (data embedded as JSON).
 pub fn render_html_report(data: &ReportData) -> Result<String> {
-    let json = to_string(data)?; // embedded as JS object literal
-    let template = fs::read_to_string("templates/report.html")
-        .with_context(|| "read templates/report.html".to_string())?;
+    render(data, false, Embed::Auto)
+}
+
+/// [`render_html_report`] with an explicit data encoding.
+pub fn render_html_report_with(data: &ReportData, embed: Embed) -> Result<String> {
+    render(data, false, embed)
+}
+
+/// Render the report for `serve`: same page, plus live reload from `/events`.
+pub fn render_served_html_report(data: &ReportData) -> Result<String> {
+    render(data, true, Embed::Auto)
+}
+
+/// Render the multi-run dashboard index (see [`crate::dashboard`]).
+pub fn render_dashboard_html(data: &DashboardData) -> Result<String> {
+    let (encoding, payload) = embed_json(&to_string(data)?, Embed::Auto)?;
+    Ok(DASHBOARD_TEMPLATE
+        .replace("__DATA_ENCODING__", encoding)
+        .replace("__DATA__", &payload))
+}
+
+/// Render the trend charts of a run history (see [`crate::history`]).
+pub fn render_history_html(data: &HistoryData) -> Result<String> {
+    let (encoding, payload) = embed_json(&to_string(data)?, Embed::Auto)?;
+    Ok(HISTORY_TEMPLATE
+        .replace("__DATA_ENCODING__", encoding)
+        .replace("__DATA__", &payload))
+}
+
+fn render(data: &ReportData, live: bool, embed: Embed) -> Result<String> {
+    let (encoding, payload) = embed_json(&to_string(data)?, embed)?;
+    // The payload goes in last so its contents are never mistaken for placeholders.
+    Ok(TEMPLATE
+        .replace("__LIVE__", if live { "true" } else { "false" })
+        .replace("__DATA_ENCODING__", encoding)
+        .replace("__DATA__", &payload))
+}
+
+/// The `data-encoding` attribute and contents of a page's data block.
+fn embed_json(json: &str, embed: Embed) -> Result<(&'static str, String)> {
+    let compress = match embed {
+        Embed::Auto => json.len() > COMPRESS_THRESHOLD,
+        Embed::Json => false,
+        Embed::Deflate => true,
+    };
+    Ok(if compress {
+        ("deflate-base64", deflate_base64(json.as_bytes())?)
+    } else {
+        ("json", escape_script_json(json))
+    })
+}
+
+/// Escape serialized JSON for embedding inside a `<script>` element.
+///
+/// `<`, `>` and `&` become `\u003c`, `\u003e` and `\u0026`, so the text can
+/// never contain `</script>` or `<!--`; U+2028/U+2029 are escaped as well.
+/// The result is still valid JSON that parses to the same value:
+///
+/// ```
+/// use flowlog_profile_viz::render::escape_script_json;
+///
+/// let label = "</script><script>alert(1)</script><!-- & \u{2028}";
+/// let json = serde_json::to_string(&label).unwrap();
+/// let escaped = escape_script_json(&json);
+/// assert!(!escaped.contains("</script") && !escaped.contains("<!--"));
+/// assert!(!escaped.contains('\u{2028}'));
+/// assert_eq!(serde_json::from_str::<String>(&escaped).unwrap(), label);
+/// ```
+pub fn escape_script_json(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    for c in json.chars() {
+        match c {
+            '<' => out.push_str("\\u003c"),
+            '>' => out.push_str("\\u003e"),
+            '&' => out.push_str("\\u0026"),
+            '\u{2028}' => out.push_str("\\u2028"),
+            '\u{2029}' => out.push_str("\\u2029"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// zlib-compress (what `DecompressionStream("deflate")` reads) and base64-encode.
+fn deflate_base64(bytes: &[u8]) -> Result<String> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(encoder.finish()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::LogIndex;
+    use crate::ops::OpsSpec;
+    use crate::profile::Profile;
+    use flate2::read::ZlibDecoder;
+    use pretty_assertions::assert_eq;
+    use std::io::Read;
+
+    const LABELS: [&str; 4] = [
+        "</script><script>alert(1)</script>",
+        "<!-- not a comment -->",
+        "line\u{2028}separator\u{2029}",
+        "]]> & </SCRIPT >",
+    ];
+    const RULE: &str = "bad(x) :- arc(x, \"</script>\"), x != \"<!--]]>\u{2028}\"";
+
+    fn hostile_report() -> ReportData {
+        let nodes: Vec<serde_json::Value> = LABELS
+            .iter()
+            .enumerate()
+            .map(|(id, label)| {
+                serde_json::json!({
+                    "id": id,
+                    "name": label,
+                    "block": "b",
+                    "fingerprint": (id == LABELS.len() - 1).then_some("0xbad"),
+                    "operators": [[0, id]],
+                    "parents": if id == 0 { vec![] } else { vec![id - 1] },
+                })
+            })
+            .collect();
+        let ops: OpsSpec = serde_json::from_value(serde_json::json!({
+            "nodes": nodes,
+            "rules": [{"text": RULE, "plan_tree": [{"fingerprint": "0xbad", "parents": []}]}],
+        }))
+        .unwrap();
+        Profile::from_parts(&ops, LogIndex::new()).unwrap().data
+    }
+
+    /// The contents and `data-encoding` of the page's `#reportData` block.
+    fn data_block(html: &str) -> (&str, &str) {
+        let open = "<script id=\"reportData\" type=\"application/json\" data-encoding=\"";
+        let start = html.find(open).unwrap() + open.len();
+        let (encoding, rest) = html[start..].split_once("\">").unwrap();
+        let end = rest.find("</script>").unwrap();
+        (encoding, &rest[..end])
+    }
+
+    #[test]
+    fn hostile_labels_round_trip() {
+        let data = hostile_report();
+        for (embed, expected_encoding) in
+            [(Embed::Json, "json"), (Embed::Deflate, "deflate-base64")]
+        {
+            let html = render_html_report_with(&data, embed).unwrap();
+            let (encoding, payload) = data_block(&html);
+            assert_eq!(encoding, expected_encoding);
+            // The block ends at its own closing tag, not inside a label.
+            let after = &html[html.find(payload).unwrap() + payload.len()..];
+            assert!(after.starts_with("</script>"));
+            assert!(!payload.to_ascii_lowercase().contains("</script"));
+            assert!(!payload.contains("<!--"));
+            assert!(!payload.contains(['\u{2028}', '\u{2029}']));
 
-    Ok(template.replace("__DATA__", &json))
+            let json = match embed {
+                Embed::Deflate => {
+                    let bytes = base64::engine::general_purpose::STANDARD
+                        .decode(payload)
+                        .unwrap();
+                    let mut json = String::new();
+                    ZlibDecoder::new(&bytes[..])
+                        .read_to_string(&mut json)
+                        .unwrap();
+                    json
+                }
+                _ => payload.to_string(),
+            };
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+            let labels: Vec<&str> = (0..LABELS.len())
+                .map(|id| value["nodes"][id.to_string()]["label"].as_str().unwrap())
+                .collect();
+            assert_eq!(labels, LABELS);
+            assert_eq!(value["rules"][0]["text"].as_str(), Some(RULE));
+        }
+    }
 }
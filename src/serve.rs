@@ -0,0 +1,212 @@
+//! Local HTTP server with a live-reloading report.
+//!
+//! Routes:
+//! - `/`           the HTML report (same template as the file output)
+//! - `/data.json`  the current ReportData
+//! - `/events`     server-sent events; a `reload` event fires whenever the
+//!   watched inputs change and reload successfully
+//!
+//! The page refetches `/data.json` on `reload` and re-renders in place, so the
+//! current selection, expanded rows and graph zoom survive. Everything is served
+//! from memory; no external assets are involved.
+
+use crate::Profile;
+use crate::Result;
+use crate::diagnostics;
+use crate::error::Error;
+use crate::log::ParseOptions;
+use crate::profile::Overlays;
+use crate::render;
+use crate::watch::Watcher;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Interval between SSE keep-alive comments.
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+struct Snapshot {
+    version: u64,
+    html: String,
+    json: String,
+}
+
+struct Shared {
+    snapshot: Mutex<Snapshot>,
+    changed: Condvar,
+}
+
+impl Shared {
+    /// Swap in freshly loaded data; clients are only notified if it differs.
+    fn update(&self, html: String, json: String) {
+        let mut snap = self.snapshot.lock().unwrap();
+        if snap.json == json {
+            return;
+        }
+        snap.version += 1;
+        snap.html = html;
+        snap.json = json;
+        self.changed.notify_all();
+    }
+}
+
+/// Serve the report for (`logs`, `ops`) on `addr`, reloading on input changes.
+///
+/// Several logs are aggregated as repeated runs ([`Profile::load_runs`]);
+/// `overlays` are joined on every load and watched too.
+/// The initial load must succeed; later failures keep the last good data.
+pub fn serve(
+    logs: &[&str],
+    ops: &str,
+    overlays: &Overlays,
+    addr: &str,
+    interval: Duration,
+    opts: &ParseOptions,
+) -> Result<()> {
+    let mut paths = logs.to_vec();
+    paths.push(ops);
+    paths.extend(overlays.paths());
+    // Watch from before the initial load, which the watcher does not repeat.
+    let watcher = Watcher::new(&paths);
+    let (html, json) = load(logs, ops, overlays, opts)?;
+    let shared = Arc::new(Shared {
+        snapshot: Mutex::new(Snapshot {
+            version: 0,
+            html,
+            json,
+        }),
+        changed: Condvar::new(),
+    });
+
+    let listener = TcpListener::bind(addr).map_err(|e| Error::io("bind", addr, e))?;
+    println!("Serving report on http://{}/", listener.local_addr()?);
+
+    {
+        let shared = Arc::clone(&shared);
+        let logs: Vec<String> = logs.iter().map(|l| l.to_string()).collect();
+        let ops = ops.to_string();
+        let overlays = overlays.clone();
+        let opts = *opts;
+        thread::spawn(move || {
+            let logs: Vec<&str> = logs.iter().map(String::as_str).collect();
+            watcher.run(interval, || {
+                let (html, json) = load(&logs, &ops, &overlays, &opts)?;
+                shared.update(html, json);
+                Ok(())
+            })
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(err) => {
+                diagnostics::warn(format!("accept failed: {}", err));
+                continue;
+            }
+        };
+        let shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            // Client disconnects surface as write errors; nothing to report.
+            let _ = handle(stream, &shared);
+        });
+    }
+
+    Ok(())
+}
+
+fn load(
+    logs: &[&str],
+    ops: &str,
+    overlays: &Overlays,
+    opts: &ParseOptions,
+) -> Result<(String, String)> {
+    let mut profile = Profile::load_runs(logs, ops, opts)?;
+    profile.apply_overlays(overlays)?;
+    let html = render::render_served_html_report(&profile.data)?;
+    let json = serde_json::to_string(&profile.data)?;
+    Ok((html, json))
+}
+
+fn handle(mut stream: TcpStream, shared: &Shared) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain headers; none of them affect the response.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("/");
+    let path = path.split('?').next().unwrap_or(path);
+
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"");
+    }
+
+    match path {
+        "/" | "/index.html" => {
+            let html = shared.snapshot.lock().unwrap().html.clone();
+            respond(
+                &mut stream,
+                "200 OK",
+                "text/html; charset=utf-8",
+                html.as_bytes(),
+            )
+        }
+        "/data.json" => {
+            let json = shared.snapshot.lock().unwrap().json.clone();
+            respond(&mut stream, "200 OK", "application/json", json.as_bytes())
+        }
+        "/events" => events(&mut stream, shared),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Hold the connection open and emit `reload` whenever the snapshot changes.
+fn events(stream: &mut TcpStream, shared: &Shared) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: keep-alive\r\n\r\n"
+    )?;
+    stream.flush()?;
+
+    let mut seen = shared.snapshot.lock().unwrap().version;
+    loop {
+        let guard = shared.snapshot.lock().unwrap();
+        let (guard, _) = shared
+            .changed
+            .wait_timeout_while(guard, KEEPALIVE, |s| s.version == seen)
+            .unwrap();
+        let version = guard.version;
+        drop(guard);
+
+        if version == seen {
+            stream.write_all(b": keep-alive\n\n")?;
+        } else {
+            seen = version;
+            write!(stream, "event: reload\ndata: {}\n\n", version)?;
+        }
+        stream.flush()?;
+    }
+}
@@ -0,0 +1,140 @@
+//! Output sinks: where and how a ReportData is written.
+//!
+//! Implement [`Sink`] to plug in a custom output; the built-in sinks back the
+//! CLI's `--format` options. The single-file sinks write to stdout for `-`.
+
+use crate::Result;
+use crate::csv;
+use crate::error::Error;
+use crate::input;
+use crate::markdown;
+use crate::render::{self, Embed};
+use crate::view::ReportData;
+
+use anyhow::bail;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// A destination for rendered report data.
+pub trait Sink {
+    /// Render `data` and write it out. Implementations should not leave
+    /// partial output behind when rendering fails.
+    fn write(&self, data: &ReportData) -> Result<()>;
+}
+
+/// Self-contained interactive HTML report.
+#[derive(Debug, Clone)]
+pub struct HtmlFile {
+    /// Output file, `-` for stdout.
+    pub path: PathBuf,
+    /// How the data is embedded; compressed automatically for large reports.
+    pub embed: Embed,
+}
+
+impl HtmlFile {
+    /// Write to `path`, embedding the data as [`Embed::Auto`] does.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            embed: Embed::default(),
+        }
+    }
+
+    /// Choose how the report data is embedded.
+    pub fn with_embed(mut self, embed: Embed) -> Self {
+        self.embed = embed;
+        self
+    }
+}
+
+impl Sink for HtmlFile {
+    fn write(&self, data: &ReportData) -> Result<()> {
+        write_file(
+            &self.path,
+            &render::render_html_report_with(data, self.embed)?,
+        )
+    }
+}
+
+/// GitHub-flavored Markdown summary listing the `top` nodes and rules.
+#[derive(Debug, Clone)]
+pub struct MarkdownFile {
+    /// Output file, `-` for stdout.
+    pub path: PathBuf,
+    /// Number of nodes and rules listed.
+    pub top: usize,
+}
+
+impl MarkdownFile {
+    /// Write the summary to `path`, listing `top` nodes and rules.
+    pub fn new(path: impl Into<PathBuf>, top: usize) -> Self {
+        Self {
+            path: path.into(),
+            top,
+        }
+    }
+}
+
+impl Sink for MarkdownFile {
+    fn write(&self, data: &ReportData) -> Result<()> {
+        write_file(
+            &self.path,
+            &markdown::render_markdown_report(data, self.top),
+        )
+    }
+}
+
+/// `nodes` and `operators` tables written into a directory.
+#[derive(Debug, Clone)]
+pub struct TableDir {
+    /// Output directory, created if missing.
+    pub dir: PathBuf,
+    /// ',' for CSV, '\t' for TSV.
+    pub delimiter: char,
+}
+
+impl TableDir {
+    /// Comma-separated `.csv` tables in `dir`.
+    pub fn csv(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            delimiter: ',',
+        }
+    }
+
+    /// Tab-separated `.tsv` tables in `dir`.
+    pub fn tsv(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            delimiter: '\t',
+        }
+    }
+}
+
+impl Sink for TableDir {
+    fn write(&self, data: &ReportData) -> Result<()> {
+        if self.dir == Path::new(input::STDIO) {
+            bail!(Error::validation(
+                "csv/tsv output is a directory of tables and cannot be written to stdout",
+            ));
+        }
+        csv::write_tables(data, &self.dir, self.delimiter)?;
+        Ok(())
+    }
+}
+
+/// Write `text` to `path`, or to stdout when `path` is `-`.
+fn write_file(path: &Path, text: &str) -> Result<()> {
+    if path == Path::new(input::STDIO) {
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(text.as_bytes())
+            .and_then(|()| stdout.flush())
+            .map_err(|e| Error::io("write output file", "<stdout>", e))?;
+        return Ok(());
+    }
+    fs::write(path, text)
+        .map_err(|e| Error::io("write output file", path.display().to_string(), e))?;
+    Ok(())
+}
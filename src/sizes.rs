@@ -0,0 +1,183 @@
+//! Relation cardinalities (`--sizes`): tuple counts joined onto nodes and
+//! rules, so a rule that is slow because it has a lot of data can be told
+//! apart from one that is slow per tuple.
+//!
+//! Two formats are accepted. A JSON object keyed by relation name or node
+//! fingerprint:
+//!
+//! ```json
+//! { "tc": { "input": 1200, "output": 5400 }, "0x710ec2cd2932502a": { "output": 5400 } }
+//! ```
+//!
+//! or a whitespace-separated table with `#` comments, one `KEY OUTPUT` or
+//! `KEY INPUT OUTPUT` line per relation or fingerprint:
+//!
+//! ```text
+//! # relation  input  output
+//! arc         1200   1200
+//! tc          1200   5400
+//! ```
+//!
+//! A node matches by fingerprint, else by the relation prefix of its label
+//! (`tc: concat & dedup` is relation `tc`). A rule matches by its head
+//! relation, else by the fingerprint of its root plan node.
+
+use crate::Result;
+use crate::diagnostics::Diagnostic;
+use crate::error::Error;
+use crate::input;
+use crate::view::ReportData;
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Tuple counts of one relation or node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(expecting = r#"an object like {"input": N, "output": N}"#)]
+pub struct Cardinality {
+    /// Tuples read, when known.
+    pub input: Option<u64>,
+    /// Tuples produced, when known.
+    pub output: Option<u64>,
+}
+
+impl Cardinality {
+    /// Tuples the per-tuple metrics divide by: the output, else the input.
+    pub fn tuples(&self) -> Option<u64> {
+        self.output.or(self.input)
+    }
+}
+
+/// Cardinalities keyed by relation name or node fingerprint.
+#[derive(Debug, Clone, Default)]
+pub struct Sizes {
+    /// Cardinalities by relation name or fingerprint.
+    pub entries: BTreeMap<String, Cardinality>,
+}
+
+/// Cardinality joined onto a node or rule, with derived efficiency metrics.
+#[derive(Debug, Clone, Serialize)]
+pub struct SizeView {
+    /// The `--sizes` key that matched (relation or fingerprint).
+    pub key: String,
+    /// Tuples read, when known.
+    pub input: Option<u64>,
+    /// Tuples produced, when known.
+    pub output: Option<u64>,
+    /// Microseconds of active time per tuple; `None` without tuples.
+    pub us_per_tuple: Option<f64>,
+    /// Tuples per second of active time; `None` without time.
+    pub tuples_per_s: Option<f64>,
+}
+
+impl SizeView {
+    fn new(key: &str, card: Cardinality, ms: f64) -> Self {
+        let tuples = card.tuples().unwrap_or(0);
+        Self {
+            key: key.to_string(),
+            input: card.input,
+            output: card.output,
+            us_per_tuple: (tuples > 0).then(|| ms * 1000.0 / tuples as f64),
+            tuples_per_s: (ms > 0.0).then(|| tuples as f64 / (ms / 1000.0)),
+        }
+    }
+}
+
+/// Read a sizes file (`-` for stdin, may be .gz/.zst).
+pub fn read_sizes(path: &str) -> Result<Sizes> {
+    let text = input::read_to_string(path, "read sizes file")?;
+    let name = input::display_name(path);
+    if text.trim_start().starts_with('{') {
+        let entries = serde_json::from_str(&text).map_err(|e| Error::schema(name, e))?;
+        return Ok(Sizes { entries });
+    }
+
+    let mut entries = BTreeMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let count = |s: &str| {
+            s.parse::<u64>().map_err(|_| {
+                Error::parse(name, i + 1, format!("expected a tuple count, got {:?}", s))
+            })
+        };
+        let card = match fields[..] {
+            [_, output] => Cardinality {
+                input: None,
+                output: Some(count(output)?),
+            },
+            [_, input, output] => Cardinality {
+                input: Some(count(input)?),
+                output: Some(count(output)?),
+            },
+            _ => bail!(Error::parse(
+                name,
+                i + 1,
+                format!(
+                    "expected `KEY OUTPUT` or `KEY INPUT OUTPUT`, got {:?}",
+                    line
+                )
+            )),
+        };
+        entries.insert(fields[0].to_string(), card);
+    }
+    Ok(Sizes { entries })
+}
+
+/// The relation a node label belongs to, e.g. `tc` for `tc: concat & dedup`.
+fn label_relation(label: &str) -> Option<&str> {
+    let (rel, _) = label.split_once(": ")?;
+    let rel = rel.trim();
+    (!rel.is_empty() && rel.chars().all(|c| c.is_alphanumeric() || c == '_')).then_some(rel)
+}
+
+/// Set `sizes` on the nodes and rules of `data` that match an entry.
+///
+/// Returns a warning for entries that matched nothing, which usually means
+/// the sizes come from a different program version.
+pub fn attach_sizes(data: &mut ReportData, sizes: &Sizes) -> Vec<Diagnostic> {
+    let mut used: BTreeSet<&str> = BTreeSet::new();
+    let mut find = |keys: [Option<&str>; 2]| {
+        keys.into_iter().flatten().find_map(|k| {
+            let (key, card) = sizes.entries.get_key_value(k)?;
+            used.insert(key.as_str());
+            Some((key.as_str(), *card))
+        })
+    };
+
+    for node in data.nodes.values_mut() {
+        if let Some((key, card)) = find([node.fingerprint.as_deref(), label_relation(&node.label)])
+        {
+            node.sizes = Some(SizeView::new(key, card, node.self_total_active_ms));
+        }
+    }
+    for rule in &mut data.rules {
+        let head = rule.parsed.as_ref().map(|p| p.head.relation.as_str());
+        if let Some((key, card)) = find([head, Some(rule.root.as_str())]) {
+            rule.sizes = Some(SizeView::new(key, card, rule.total_active_ms));
+        }
+    }
+
+    let unmatched: Vec<&str> = sizes
+        .entries
+        .keys()
+        .map(String::as_str)
+        .filter(|k| !used.contains(k))
+        .collect();
+    if unmatched.is_empty() {
+        return Vec::new();
+    }
+    vec![Diagnostic::warning(
+        "sizes-unmatched",
+        format!(
+            "{} --sizes entr{} matched no node or rule: {}",
+            unmatched.len(),
+            if unmatched.len() == 1 { "y" } else { "ies" },
+            unmatched.join(", ")
+        ),
+    )]
+}
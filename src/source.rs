@@ -0,0 +1,464 @@
+//! The FlowLog program (`--source program.dl`) annotated with rule costs,
+//! like `perf annotate`: each rule's first line is prefixed with its ms, share
+//! of the mapped total and activations.
+//!
+//! The program is split into statements at top-level `.` terminators, with
+//! `//` and `/* */` comments removed; `.decl`-style directive lines are
+//! skipped and statements without `:-` (facts) are not rules. A statement
+//! matches a profile rule when both texts are equal once comments, whitespace
+//! and the trailing `.` are removed.
+
+use crate::Result;
+use crate::diagnostics::Diagnostic;
+use crate::input;
+use crate::view::ReportData;
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// A rule statement of the program.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceRule {
+    /// 1-based line the statement starts on.
+    pub line: usize,
+    /// The statement without comments, whitespace collapsed.
+    pub text: String,
+}
+
+/// A program read for annotation: its lines and the rules found in them.
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    /// Program text, line by line.
+    pub lines: Vec<String>,
+    /// Rule statements, in program order.
+    pub rules: Vec<SourceRule>,
+}
+
+/// One program line; `rules` are indices into [`ReportData::rules`] of the
+/// rules whose statement starts here.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceLine {
+    /// 1-based line number.
+    pub number: usize,
+    /// The line as written.
+    pub text: String,
+    /// Rules annotated on this line.
+    pub rules: Vec<usize>,
+}
+
+/// The annotated program.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceView {
+    /// File name as given to `--source`.
+    pub path: String,
+    /// Every program line, annotated or not.
+    pub lines: Vec<SourceLine>,
+    /// Rule statements that match no profile rule.
+    pub unmatched: Vec<SourceRule>,
+    /// Indices of profile rules found nowhere in the program.
+    pub missing: Vec<usize>,
+    /// Indices of profile rules with no activations and no active time.
+    pub zero_cost: Vec<usize>,
+}
+
+/// Read a program file (`-` for stdin, may be .gz/.zst).
+pub fn read_source(path: &str) -> Result<Program> {
+    let text = input::read_to_string(path, "read source file")?;
+    Ok(parse_program(&text))
+}
+
+/// Split program text into lines and rule statements.
+///
+/// ```
+/// use flowlog_profile_viz::source::parse_program;
+///
+/// let program = parse_program(".decl arc(x: number, y: number)\n\
+///     arc(1, 2).\n\
+///     tc(x, y) :- arc(x, y). // base case\n\
+///     tc(x, y) :-\n    tc(x, z), /* step */ arc(z, y).\n");
+/// let lines: Vec<usize> = program.rules.iter().map(|r| r.line).collect();
+/// assert_eq!(lines, [3, 4]);
+/// assert_eq!(program.rules[1].text, "tc(x, y) :- tc(x, z), arc(z, y).");
+/// ```
+pub fn parse_program(text: &str) -> Program {
+    let mut rules = Vec::new();
+    let mut stmt = String::new();
+    let mut start = 0;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    let (mut depth, mut quoted) = (0i32, false);
+    while let Some(c) = chars.next() {
+        if quoted {
+            stmt.push(c);
+            match c {
+                '\\' => stmt.extend(chars.next()),
+                '"' => quoted = false,
+                '\n' => line += 1,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                // A comment separates tokens like whitespace does.
+                stmt.push(' ');
+            }
+            // Directives (`.decl`, `.input`, ...) run to the end of their line.
+            '.' if stmt.trim().is_empty() => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                stmt.clear();
+            }
+            '.' if depth == 0 && !chars.peek().is_some_and(char::is_ascii_digit) => {
+                if stmt.contains(":-") {
+                    rules.push(SourceRule {
+                        line: start,
+                        text: format!("{}.", collapse(&stmt)),
+                    });
+                }
+                stmt.clear();
+            }
+            _ => {
+                match c {
+                    '"' => quoted = true,
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    '\n' => line += 1,
+                    _ => {}
+                }
+                if stmt.trim().is_empty() && !c.is_whitespace() {
+                    start = line;
+                }
+                stmt.push(c);
+            }
+        }
+    }
+    Program {
+        lines: text.lines().map(str::to_string).collect(),
+        rules,
+    }
+}
+
+/// Whitespace runs collapsed to one space, ends trimmed.
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The matching key: comments, whitespace and the trailing `.` removed.
+fn normalize(text: &str) -> String {
+    let stripped = parse_program(&format!("{}.\n", text.trim().trim_end_matches('.')))
+        .rules
+        .first()
+        .map_or_else(|| text.to_string(), |r| r.text.clone());
+    stripped
+        .trim_end_matches('.')
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+/// Set `source` on `data`: `program` with each line's matching rules.
+///
+/// Warns about program rules that match no profile rule and profile rules
+/// missing from the program, which usually means the two are from different
+/// program versions; rules that never ran are reported as info.
+pub fn attach_source(data: &mut ReportData, path: &str, program: &Program) -> Vec<Diagnostic> {
+    let mut by_text: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, rule) in data.rules.iter().enumerate() {
+        by_text.entry(normalize(&rule.text)).or_default().push(i);
+    }
+
+    let mut at_line: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut found = BTreeSet::new();
+    let mut unmatched = Vec::new();
+    for stmt in &program.rules {
+        match by_text.get(&normalize(&stmt.text)) {
+            Some(rules) => {
+                // A rule repeated in the program is annotated where it first appears.
+                let fresh = rules.iter().copied().filter(|&i| found.insert(i));
+                at_line.entry(stmt.line).or_default().extend(fresh);
+            }
+            None => unmatched.push(stmt.clone()),
+        }
+    }
+    let missing: Vec<usize> = (0..data.rules.len())
+        .filter(|i| !found.contains(i))
+        .collect();
+    let zero_cost: Vec<usize> = data
+        .rules
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.activations == 0 && r.total_active_ms == 0.0)
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut findings = Vec::new();
+    if !unmatched.is_empty() {
+        let lines: Vec<String> = unmatched.iter().map(|r| r.line.to_string()).collect();
+        findings.push(Diagnostic::warning(
+            "source-unmatched-rules",
+            format!(
+                "{} rule(s) in {} match no profiled rule (line {})",
+                unmatched.len(),
+                input::display_name(path),
+                lines.join(", ")
+            ),
+        ));
+    }
+    if !missing.is_empty() {
+        findings.push(Diagnostic::warning(
+            "source-rules-missing",
+            format!(
+                "{} profiled rule(s) not found in {}, e.g. `{}`",
+                missing.len(),
+                input::display_name(path),
+                data.rules[missing[0]].text
+            ),
+        ));
+    }
+    if !zero_cost.is_empty() {
+        findings.push(Diagnostic::info(
+            "source-zero-cost",
+            format!(
+                "{} rule(s) have no activations or active time",
+                zero_cost.len()
+            ),
+        ));
+    }
+
+    data.source = Some(SourceView {
+        path: input::display_name(path).to_string(),
+        lines: program
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, text)| SourceLine {
+                number: i + 1,
+                text: text.clone(),
+                rules: at_line.remove(&(i + 1)).unwrap_or_default(),
+            })
+            .collect(),
+        unmatched,
+        missing,
+        zero_cost,
+    });
+    findings
+}
+
+/// The annotated listing as plain text; empty without a source view.
+pub fn render_text(data: &ReportData) -> String {
+    let mut out = String::new();
+    let Some(source) = &data.source else {
+        return out;
+    };
+    let total = data.totals.total_mapped_ms;
+    let matched = data.rules.len() - source.missing.len();
+    let annotated_ms: f64 = source
+        .lines
+        .iter()
+        .flat_map(|l| &l.rules)
+        .map(|&i| data.rules[i].total_active_ms)
+        .sum();
+    let _ = writeln!(
+        out,
+        "{}: {} of {} profiled rule(s) matched, {:.3} ms of {:.3} ms mapped",
+        source.path,
+        matched,
+        data.rules.len(),
+        annotated_ms,
+        total
+    );
+    if data.totals.runs > 1 {
+        let _ = writeln!(out, "means over {} runs", data.totals.runs);
+    }
+    let _ = writeln!(
+        out,
+        "note: rules can share plan nodes, so percentages may add up to more than 100%\n"
+    );
+
+    let _ = writeln!(
+        out,
+        "{:>12} {:>7} {:>11} │ {:>5} │",
+        "ms", "%", "activations", "line"
+    );
+    for line in &source.lines {
+        let prefix = if line.rules.is_empty() {
+            format!("{:>12} {:>7} {:>11}", "", "", "")
+        } else {
+            let (ms, activations) = line.rules.iter().fold((0.0, 0), |(ms, a), &i| {
+                let r = &data.rules[i];
+                (ms + r.total_active_ms, a + r.activations)
+            });
+            format!("{:>12.3} {:>7} {:>11}", ms, pct(ms, total), activations)
+        };
+        let row = format!("{} │ {:>5} │ {}", prefix, line.number, line.text);
+        let _ = writeln!(out, "{}", row.trim_end());
+    }
+
+    if !source.unmatched.is_empty() {
+        let _ = writeln!(
+            out,
+            "\nProgram rules matching no profiled rule ({}):",
+            source.unmatched.len()
+        );
+        for rule in &source.unmatched {
+            let _ = writeln!(out, "  line {:<5} {}", rule.line, rule.text);
+        }
+    }
+    for (title, indices) in [
+        ("Profiled rules not in the program", &source.missing),
+        ("Rules with no cost", &source.zero_cost),
+    ] {
+        if indices.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "\n{} ({}):", title, indices.len());
+        for &i in indices {
+            let _ = writeln!(out, "  {}", data.rules[i].text);
+        }
+    }
+    out
+}
+
+fn pct(part: f64, total: f64) -> String {
+    if total <= 0.0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", 100.0 * part / total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn rules(text: &str) -> Vec<(usize, String)> {
+        parse_program(text)
+            .rules
+            .into_iter()
+            .map(|r| (r.line, r.text))
+            .collect()
+    }
+
+    #[test]
+    fn decimals_do_not_end_statements() {
+        assert_eq!(
+            rules("near(x) :- dist(x, d), d < 1.5.\nfar(x) :- dist(x, d), d > 10.25.\n"),
+            [
+                (1, "near(x) :- dist(x, d), d < 1.5.".to_string()),
+                (2, "far(x) :- dist(x, d), d > 10.25.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn directives_after_comments_are_skipped() {
+        let text = "/* inputs */ .decl arc(x: number, y: number)\n\
+                    // output\n\
+                    .output tc\n\
+                    /* base\n   case */ tc(x, y) :- arc(x, y).\n";
+        assert_eq!(rules(text), [(5, "tc(x, y) :- arc(x, y).".to_string())]);
+    }
+
+    #[test]
+    fn quoted_text_is_not_syntax() {
+        let text = "label(x, \"a.b // not a comment /* nor this */\") :- node(x).\n\
+                    path(x) :- name(x, \"C:\\\\dir\\\"quoted.\\\"\").\n";
+        assert_eq!(
+            rules(text),
+            [
+                (
+                    1,
+                    "label(x, \"a.b // not a comment /* nor this */\") :- node(x).".to_string()
+                ),
+                (
+                    2,
+                    "path(x) :- name(x, \"C:\\\\dir\\\"quoted.\\\"\").".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn facts_are_not_rules() {
+        assert_eq!(
+            rules("arc(1, 2).\narc(2, 3). tc(x, y) :- arc(x, y).\n"),
+            [(2, "tc(x, y) :- arc(x, y).".to_string())]
+        );
+    }
+
+    fn report() -> ReportData {
+        use crate::log::parse_log;
+        use crate::ops::OpsSpec;
+        use crate::profile::Profile;
+
+        let ops: OpsSpec = serde_json::from_str(
+            r#"{
+            "nodes": [
+                {"id": 0, "name": "scan", "block": "s", "fingerprint": "0xa", "operators": [[0, 1]], "parents": []},
+                {"id": 1, "name": "join", "block": "s", "fingerprint": "0xb", "operators": [[0, 2]], "parents": [0]},
+                {"id": 2, "name": "idle", "block": "s", "fingerprint": "0xc", "operators": [[0, 3]], "parents": []}
+            ],
+            "rules": [
+                {"text": "tc(x, y) :- arc(x, y).", "plan_tree": [{"fingerprint": "0xa", "parents": []}]},
+                {"text": "tc(x, y) :- tc(x, z), arc(z, y).", "plan_tree": [{"fingerprint": "0xb", "parents": []}]},
+                {"text": "never(x) :- arc(x, x).", "plan_tree": [{"fingerprint": "0xc", "parents": []}]}
+            ]}"#,
+        )
+        .unwrap();
+        let log = "[0, 1] 2 10 Scan\n[0, 2] 3 30 Join\n";
+        Profile::from_parts(&ops, parse_log(log.as_bytes(), "log").unwrap())
+            .unwrap()
+            .data
+    }
+
+    #[test]
+    fn attach_source_buckets_rules() {
+        let mut data = report();
+        let program = parse_program(
+            "tc(x, y) :-\n    arc(x, y).\n\
+             tc(x, y) :- tc(x, z), /* step */ arc(z, y).\n\
+             tc(x, y) :- arc(x, y).\n\
+             odd(x) :- arc(x, y), !arc(y, x).\n",
+        );
+        let findings = attach_source(&mut data, "prog.dl", &program);
+        let source = data.source.as_ref().unwrap();
+
+        let annotated: Vec<(usize, &[usize])> = source
+            .lines
+            .iter()
+            .map(|l| (l.number, l.rules.as_slice()))
+            .collect();
+        // The repeated base case is annotated where it first appears.
+        assert_eq!(
+            annotated,
+            [(1, &[0][..]), (2, &[]), (3, &[1]), (4, &[]), (5, &[])]
+        );
+        let unmatched: Vec<usize> = source.unmatched.iter().map(|r| r.line).collect();
+        assert_eq!(unmatched, [5]);
+        assert_eq!(source.missing, [2]);
+        assert_eq!(source.zero_cost, [2]);
+
+        let codes: Vec<&str> = findings.iter().map(|d| d.code.as_str()).collect();
+        assert_eq!(
+            codes,
+            [
+                "source-unmatched-rules",
+                "source-rules-missing",
+                "source-zero-cost"
+            ]
+        );
+    }
+}
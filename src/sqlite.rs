@@ -0,0 +1,333 @@
+//! SQLite export for querying many profiling runs together.
+//!
+//! Each call appends one run. Every table except `runs` carries a `run_id`
+//! column so runs can be compared with plain SQL, e.g.:
+//!
+//! ```sql
+//! SELECT m.value AS commit_id, r.ms
+//! FROM rules r JOIN run_meta m ON m.run_id = r.run_id AND m.key = 'commit'
+//! WHERE r.text = 'tc(x, y) :- tc(x, z), arc(z, y)'
+//! ORDER BY r.run_id DESC LIMIT 20;
+//! ```
+
+use crate::Profile;
+use crate::Result;
+use crate::addr::Addr;
+
+use anyhow::Context;
+use rusqlite::{Connection, Transaction, params};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS runs (
+    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at_unix          INTEGER NOT NULL,
+    log_path                 TEXT NOT NULL,
+    ops_path                 TEXT NOT NULL,
+    names                    INTEGER NOT NULL,
+    operators_in_log         INTEGER NOT NULL,
+    operators_mapped         INTEGER NOT NULL,
+    total_mapped_ms          REAL NOT NULL,
+    total_mapped_activations INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS run_meta (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    key    TEXT NOT NULL,
+    value  TEXT NOT NULL,
+    PRIMARY KEY (run_id, key)
+);
+CREATE TABLE IF NOT EXISTS nodes (
+    run_id          INTEGER NOT NULL REFERENCES runs(id),
+    node_id         INTEGER NOT NULL,
+    label           TEXT NOT NULL,
+    block           TEXT NOT NULL,
+    fingerprint     TEXT,
+    tags            TEXT NOT NULL,
+    self_ms         REAL NOT NULL,
+    activations     INTEGER NOT NULL,
+    PRIMARY KEY (run_id, node_id)
+);
+CREATE TABLE IF NOT EXISTS operators (
+    run_id      INTEGER NOT NULL REFERENCES runs(id),
+    addr        TEXT NOT NULL,
+    op_name     TEXT NOT NULL,
+    node_id     INTEGER,
+    ms          REAL NOT NULL,
+    activations INTEGER NOT NULL,
+    PRIMARY KEY (run_id, addr)
+);
+CREATE TABLE IF NOT EXISTS operator_metrics (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    addr   TEXT NOT NULL,
+    name   TEXT NOT NULL,
+    value  REAL NOT NULL,
+    PRIMARY KEY (run_id, addr, name)
+);
+CREATE TABLE IF NOT EXISTS rules (
+    run_id      INTEGER NOT NULL REFERENCES runs(id),
+    rule_idx    INTEGER NOT NULL,
+    text        TEXT NOT NULL,
+    root        TEXT NOT NULL,
+    ms          REAL NOT NULL,
+    activations INTEGER NOT NULL,
+    PRIMARY KEY (run_id, rule_idx)
+);
+CREATE TABLE IF NOT EXISTS rule_plan_nodes (
+    run_id      INTEGER NOT NULL REFERENCES runs(id),
+    rule_idx    INTEGER NOT NULL,
+    fingerprint TEXT NOT NULL,
+    node_id     INTEGER,
+    shared      INTEGER NOT NULL,
+    PRIMARY KEY (run_id, rule_idx, fingerprint)
+);
+CREATE TABLE IF NOT EXISTS dag_edges (
+    run_id    INTEGER NOT NULL REFERENCES runs(id),
+    parent_id INTEGER NOT NULL,
+    child_id  INTEGER NOT NULL,
+    PRIMARY KEY (run_id, parent_id, child_id)
+);
+"#;
+
+/// Append one run to the database at `db_path`, creating the schema if needed.
+///
+/// Returns the new run id.
+pub fn export_run(
+    db_path: &Path,
+    profile: &Profile,
+    log_path: &str,
+    ops_path: &str,
+    meta: &[(String, String)],
+) -> Result<i64> {
+    let mut conn = Connection::open(db_path)
+        .with_context(|| format!("open sqlite database {}", db_path.display()))?;
+    conn.execute_batch(SCHEMA).context("create sqlite schema")?;
+
+    let tx = conn.transaction()?;
+    let run_id = insert_run(&tx, profile, log_path, ops_path, meta)
+        .context("insert run into sqlite database")?;
+    tx.commit()?;
+
+    Ok(run_id)
+}
+
+fn insert_run(
+    tx: &Transaction,
+    profile: &Profile,
+    log_path: &str,
+    ops_path: &str,
+    meta: &[(String, String)],
+) -> Result<i64> {
+    let Profile { ops, log, data } = profile;
+    let t = &data.totals;
+
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    tx.execute(
+        "INSERT INTO runs (created_at_unix, log_path, ops_path, names, operators_in_log,
+                           operators_mapped, total_mapped_ms, total_mapped_activations)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            created_at,
+            log_path,
+            ops_path,
+            t.names as i64,
+            t.operators_in_log as i64,
+            t.operators_mapped as i64,
+            t.total_mapped_ms,
+            t.total_mapped_activations as i64,
+        ],
+    )?;
+    let run_id = tx.last_insert_rowid();
+
+    let mut stmt = tx.prepare("INSERT OR REPLACE INTO run_meta VALUES (?1, ?2, ?3)")?;
+    for (key, value) in meta {
+        stmt.execute(params![run_id, key, value])?;
+    }
+
+    let mut stmt = tx.prepare("INSERT INTO nodes VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")?;
+    let mut edges = tx.prepare("INSERT INTO dag_edges VALUES (?1, ?2, ?3)")?;
+    for (id, spec) in &ops.nodes {
+        let view = data.nodes.get(&id.to_string());
+        stmt.execute(params![
+            run_id,
+            id,
+            spec.label,
+            spec.block,
+            spec.fingerprint,
+            spec.tags.join(";"),
+            view.map(|v| v.self_total_active_ms).unwrap_or(0.0),
+            view.map(|v| v.self_activations as i64).unwrap_or(0),
+        ])?;
+        for parent in &spec.parents {
+            edges.execute(params![run_id, parent, id])?;
+        }
+    }
+
+    // Every log row is exported, with its owning node when one is mapped.
+    let owner: BTreeMap<&Addr, u32> = ops
+        .nodes
+        .iter()
+        .flat_map(|(id, spec)| spec.operators.iter().map(move |a| (a, *id)))
+        .collect();
+    let mut stmt = tx.prepare("INSERT INTO operators VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+    let mut metrics = tx.prepare("INSERT INTO operator_metrics VALUES (?1, ?2, ?3, ?4)")?;
+    for (addr, row) in log {
+        let addr_text = format!("{:?}", addr.0);
+        stmt.execute(params![
+            run_id,
+            addr_text,
+            row.op_name,
+            owner.get(addr),
+            row.total_active_ms,
+            row.activations as i64,
+        ])?;
+        for (name, value) in &row.metrics {
+            metrics.execute(params![run_id, addr_text, name.as_ref(), value])?;
+        }
+    }
+
+    let mut stmt = tx.prepare("INSERT INTO rules VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+    let mut plan = tx.prepare("INSERT INTO rule_plan_nodes VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    for (idx, rule) in data.rules.iter().enumerate() {
+        stmt.execute(params![
+            run_id,
+            idx as i64,
+            rule.text,
+            rule.root,
+            rule.total_active_ms,
+            rule.activations as i64,
+        ])?;
+        for (fp, pn) in &rule.nodes {
+            let node_id = pn.node.as_deref().and_then(|n| n.parse::<u32>().ok());
+            plan.execute(params![run_id, idx as i64, fp, node_id, pn.shared])?;
+        }
+    }
+
+    Ok(run_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::parse_log;
+    use crate::ops::OpsSpec;
+    use pretty_assertions::assert_eq;
+
+    fn profile(log: &str) -> Profile {
+        let ops: OpsSpec = serde_json::from_value(serde_json::json!({
+            "nodes": [
+                {"id": 0, "name": "e: input", "block": "in", "tags": ["Input"],
+                 "operators": [[0, 1]], "parents": []},
+                {"id": 1, "name": "r: map", "block": "s0", "fingerprint": "0xa",
+                 "operators": [[0, 2]], "parents": [0]},
+            ],
+            "rules": [{"text": "r(x) :- e(x).", "plan_tree": [{"fingerprint": "0xa", "parents": []}]}],
+        }))
+        .unwrap();
+        Profile::from_parts(&ops, parse_log(log.as_bytes(), "log").unwrap()).unwrap()
+    }
+
+    fn rows<T: rusqlite::types::FromSql>(conn: &Connection, sql: &str) -> Vec<Vec<T>> {
+        let mut stmt = conn.prepare(sql).unwrap();
+        let columns = stmt.column_count();
+        stmt.query_map([], |row| (0..columns).map(|i| row.get(i)).collect())
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn exported_runs_read_back() {
+        let db = std::env::temp_dir().join(format!("sqlite-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db);
+        let header = "addr activations total_active_ms records_out name\n";
+        let first = profile(&format!(
+            "{}[0, 1] 1 1 10 Input\n[0, 2] 2 3 20 Map\n",
+            header
+        ));
+        let second = profile(&format!(
+            "{}[0, 1] 1 2 - Input\n[0, 2] 4 5 - Map\n[0, 9] 1 1 - X\n",
+            header
+        ));
+        let meta = [("commit".to_string(), "abc".to_string())];
+        let ids = [
+            export_run(&db, &first, "a.log", "ops.json", &meta).unwrap(),
+            export_run(&db, &second, "b.log", "ops.json", &[]).unwrap(),
+        ];
+        let conn = Connection::open(&db).unwrap();
+        assert_eq!(ids, [1, 2]);
+
+        let runs: Vec<Vec<String>> = rows(
+            &conn,
+            "SELECT log_path, CAST(operators_in_log AS TEXT), CAST(total_mapped_ms AS TEXT)
+             FROM runs ORDER BY id",
+        );
+        assert_eq!(runs, [["a.log", "2", "4.0"], ["b.log", "3", "7.0"]]);
+        let meta: Vec<Vec<String>> = rows(
+            &conn,
+            "SELECT CAST(run_id AS TEXT), key, value FROM run_meta",
+        );
+        assert_eq!(meta, [["1", "commit", "abc"]]);
+
+        let nodes: Vec<Vec<String>> = rows(
+            &conn,
+            "SELECT label, block, COALESCE(fingerprint, ''), tags, CAST(self_ms AS TEXT)
+             FROM nodes WHERE run_id = 2 ORDER BY node_id",
+        );
+        assert_eq!(
+            nodes,
+            [
+                ["e: input", "in", "", "Input", "2.0"],
+                ["r: map", "s0", "0xa", "", "5.0"]
+            ]
+        );
+        let edges: Vec<Vec<i64>> = rows(&conn, "SELECT run_id, parent_id, child_id FROM dag_edges");
+        assert_eq!(edges, [[1, 0, 1], [2, 0, 1]]);
+
+        // Unmapped log rows are kept without a node.
+        let operators: Vec<Vec<String>> = rows(
+            &conn,
+            "SELECT addr, op_name, COALESCE(CAST(node_id AS TEXT), '-') FROM operators
+             WHERE run_id = 2 ORDER BY addr",
+        );
+        assert_eq!(
+            operators,
+            [
+                ["[0, 1]", "Input", "0"],
+                ["[0, 2]", "Map", "1"],
+                ["[0, 9]", "X", "-"]
+            ]
+        );
+        let metrics: Vec<Vec<String>> = rows(
+            &conn,
+            "SELECT CAST(run_id AS TEXT), addr, name, CAST(value AS TEXT) FROM operator_metrics ORDER BY run_id, addr",
+        );
+        assert_eq!(
+            metrics,
+            [
+                ["1", "[0, 1]", "records_out", "10.0"],
+                ["1", "[0, 2]", "records_out", "20.0"]
+            ]
+        );
+
+        let rules: Vec<Vec<String>> = rows(
+            &conn,
+            "SELECT r.text, CAST(r.ms AS TEXT), p.fingerprint, CAST(p.node_id AS TEXT)
+             FROM rules r JOIN rule_plan_nodes p USING (run_id, rule_idx) ORDER BY r.run_id",
+        );
+        assert_eq!(
+            rules,
+            [
+                ["r(x) :- e(x).", "3.0", "0xa", "1"],
+                ["r(x) :- e(x).", "5.0", "0xa", "1"]
+            ]
+        );
+
+        drop(conn);
+        std::fs::remove_file(&db).unwrap();
+    }
+}
@@ -0,0 +1,309 @@
+//! Summary statistics over repeated runs, and Welch's t-test between two sets.
+//!
+//! Student's t distribution is evaluated through the regularized incomplete
+//! beta function (Lentz's continued fraction), which is plenty accurate for
+//! confidence intervals and p-values at profiling sample sizes.
+
+use serde::Serialize;
+
+/// Spread of one measurement (e.g. a node's ms) over repeated runs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Spread {
+    /// Number of runs measured.
+    pub runs: usize,
+    /// Mean over the runs.
+    pub mean: f64,
+    /// Sample standard deviation; 0 for a single run.
+    pub stddev: f64,
+    /// Smallest value.
+    pub min: f64,
+    /// Largest value.
+    pub max: f64,
+    /// 95% confidence interval of the mean (Student's t); the mean itself for one run.
+    pub ci_low: f64,
+    /// Upper end of the 95% confidence interval.
+    pub ci_high: f64,
+}
+
+impl Spread {
+    /// `None` for no values.
+    pub fn of(values: &[f64]) -> Option<Self> {
+        let n = values.len();
+        if n == 0 {
+            return None;
+        }
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let variance = if n > 1 {
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+        let stddev = variance.sqrt();
+        let half = if n > 1 {
+            t_quantile(0.975, (n - 1) as f64) * stddev / (n as f64).sqrt()
+        } else {
+            0.0
+        };
+        Some(Self {
+            runs: n,
+            mean,
+            stddev,
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            ci_low: mean - half,
+            ci_high: mean + half,
+        })
+    }
+}
+
+/// Two-sided p-value of Welch's t-test for a difference in means.
+///
+/// `None` when either side has fewer than two runs (no noise estimate). Two
+/// noiseless sides give 1 for equal means and 0 otherwise.
+pub fn welch_p_value(a: &Spread, b: &Spread) -> Option<f64> {
+    if a.runs < 2 || b.runs < 2 {
+        return None;
+    }
+    let (va, vb) = (
+        a.stddev.powi(2) / a.runs as f64,
+        b.stddev.powi(2) / b.runs as f64,
+    );
+    let se2 = va + vb;
+    if se2 == 0.0 {
+        return Some(if a.mean == b.mean { 1.0 } else { 0.0 });
+    }
+    let t = (a.mean - b.mean) / se2.sqrt();
+    // Welch–Satterthwaite degrees of freedom.
+    let df = se2.powi(2) / (va.powi(2) / (a.runs - 1) as f64 + vb.powi(2) / (b.runs - 1) as f64);
+    Some(2.0 * (1.0 - t_cdf(t.abs(), df)))
+}
+
+/// CDF of Student's t distribution with `df` degrees of freedom.
+fn t_cdf(t: f64, df: f64) -> f64 {
+    let x = df / (df + t * t);
+    let tail = 0.5 * incomplete_beta(df / 2.0, 0.5, x);
+    if t >= 0.0 { 1.0 - tail } else { tail }
+}
+
+/// Inverse of [`t_cdf`] for `p` in (0.5, 1), by bisection.
+fn t_quantile(p: f64, df: f64) -> f64 {
+    let (mut lo, mut hi) = (0.0, 1e3);
+    for _ in 0..100 {
+        let mid = 0.5 * (lo + hi);
+        if t_cdf(mid, df) < p {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+/// Regularized incomplete beta function I_x(a, b).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges fast only below the mean; use symmetry above it.
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut f = d;
+    for m in 1..300 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            f *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    f
+}
+
+/// ln Γ(x) for x > 0 (Lanczos approximation, g = 7).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection formula.
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    /// A spread with the given runs, mean and stddev (min/max/CI unused).
+    fn spread(runs: usize, mean: f64, stddev: f64) -> Spread {
+        Spread {
+            runs,
+            mean,
+            stddev,
+            min: mean,
+            max: mean,
+            ci_low: mean,
+            ci_high: mean,
+        }
+    }
+
+    #[test]
+    fn ln_gamma_matches_known_values() {
+        let pi = std::f64::consts::PI;
+        for (x, expected) in [
+            (1.0, 0.0),
+            (2.0, 0.0),
+            (5.0, 24f64.ln()),
+            (0.5, pi.sqrt().ln()),
+            (0.25, 3.625_609_908_221_908_f64.ln()),
+            (10.5, 1_133_278.388_948_441_f64.ln()),
+        ] {
+            close(ln_gamma(x), expected, 1e-10);
+        }
+    }
+
+    #[test]
+    fn incomplete_beta_matches_closed_forms() {
+        for x in [0.1, 0.3, 0.5, 0.9] {
+            close(incomplete_beta(1.0, 1.0, x), x, 1e-12);
+            close(incomplete_beta(2.0, 1.0, x), x * x, 1e-12);
+            close(incomplete_beta(1.0, 3.0, x), 1.0 - (1.0 - x).powi(3), 1e-12);
+            // Both sides of the symmetry switch agree.
+            close(
+                incomplete_beta(4.0, 2.5, x),
+                1.0 - incomplete_beta(2.5, 4.0, 1.0 - x),
+                1e-12,
+            );
+        }
+        assert_eq!(incomplete_beta(2.0, 3.0, 0.0), 0.0);
+        assert_eq!(incomplete_beta(2.0, 3.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn t_cdf_matches_tables() {
+        close(t_cdf(0.0, 5.0), 0.5, 1e-12);
+        // df = 1 is the Cauchy distribution.
+        close(t_cdf(1.0, 1.0), 0.75, 1e-12);
+        close(t_cdf(-1.0, 1.0), 0.25, 1e-12);
+        close(t_cdf(2.776, 4.0), 0.975, 1e-4);
+        close(t_cdf(2.0, 8.0), 0.959_75, 1e-4);
+    }
+
+    #[test]
+    fn confidence_interval_uses_student_t() {
+        close(t_quantile(0.975, 1.0), 12.706, 1e-3);
+        close(t_quantile(0.975, 4.0), 2.776, 1e-3);
+
+        // Two runs: df = 1, stddev √2, so the half-width is t itself.
+        let s = Spread::of(&[0.0, 2.0]).unwrap();
+        assert_eq!((s.runs, s.mean, s.min, s.max), (2, 1.0, 0.0, 2.0));
+        close(s.stddev, 2f64.sqrt(), 1e-12);
+        close(s.ci_high - s.mean, 12.706, 1e-3);
+        close(s.mean - s.ci_low, 12.706, 1e-3);
+
+        // Five runs: df = 4.
+        let s = Spread::of(&[1.0, 2.0, 3.0, 4.0, 5.0]).unwrap();
+        close(
+            s.ci_high - s.mean,
+            2.776 * 2.5f64.sqrt() / 5f64.sqrt(),
+            1e-3,
+        );
+    }
+
+    #[test]
+    fn single_run_has_no_spread() {
+        assert_eq!(Spread::of(&[]), None);
+        let s = Spread::of(&[7.5]).unwrap();
+        assert_eq!((s.runs, s.stddev, s.ci_low, s.ci_high), (1, 0.0, 7.5, 7.5));
+    }
+
+    #[test]
+    fn welch_p_value_matches_tables() {
+        // Equal sizes and variances: df = 2n - 2 = 8, and a mean difference of
+        // two standard errors gives t = -2.
+        let se = (2.0f64 / 5.0).sqrt();
+        let p = welch_p_value(&spread(5, 10.0, 1.0), &spread(5, 10.0 + 2.0 * se, 1.0));
+        close(p.unwrap(), 0.0805, 1e-4);
+
+        let (a, b) = (spread(4, 10.0, 2.0), spread(6, 12.0, 0.5));
+        assert_eq!(welch_p_value(&a, &b), welch_p_value(&b, &a));
+    }
+
+    #[test]
+    fn welch_p_value_edge_cases() {
+        // Fewer than two runs on either side: no noise estimate.
+        assert_eq!(
+            welch_p_value(&spread(1, 10.0, 0.0), &spread(5, 12.0, 1.0)),
+            None
+        );
+        assert_eq!(
+            welch_p_value(&spread(5, 10.0, 1.0), &spread(1, 12.0, 0.0)),
+            None
+        );
+        // No noise on either side (se² = 0).
+        assert_eq!(
+            welch_p_value(&spread(3, 10.0, 0.0), &spread(3, 10.0, 0.0)),
+            Some(1.0)
+        );
+        assert_eq!(
+            welch_p_value(&spread(3, 10.0, 0.0), &spread(3, 10.5, 0.0)),
+            Some(0.0)
+        );
+    }
+}
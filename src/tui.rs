@@ -0,0 +1,878 @@
+//! Interactive terminal UI over ReportData (for headless machines / SSH).
+//!
+//! Two tabs mirror the HTML report:
+//! - Nodes: collapsible tree of `roots`/`children` with ms, % and activations.
+//! - Rules: rule list, each expandable into its plan tree (sink first, then parents).
+//!
+//! The right pane shows details for the row under the cursor: node metadata,
+//! DAG parents and the operator table. `/` starts an incremental search over
+//! labels; `s` cycles the sort key and `r` reverses it.
+
+use crate::Result;
+use crate::arrangements::ArrangementView;
+use crate::error::Error;
+use crate::sizes::SizeView;
+use crate::stats::Spread;
+use crate::view::{ActivationStats, NameNodeView, ReportData, RuleView};
+
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+/// Open the TUI and block until the user quits.
+///
+/// Fails (instead of panicking) when there is no terminal to draw on, e.g.
+/// with stdin or stdout redirected.
+pub fn run(data: ReportData) -> Result<()> {
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            // Raw mode (and the alternate screen) may be on if a later step failed.
+            if ratatui::crossterm::terminal::is_raw_mode_enabled().unwrap_or(false) {
+                ratatui::restore();
+            }
+            return Err(Error::io("initialize terminal", "stdout", e).into());
+        }
+    };
+    let result = App::new(data).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Nodes,
+    Rules,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Id,
+    Ms,
+    Activations,
+    Label,
+    /// µs per tuple from `--sizes`; items without sizes sort as lowest.
+    PerTuple,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Id => SortKey::Ms,
+            SortKey::Ms => SortKey::Activations,
+            SortKey::Activations => SortKey::Label,
+            SortKey::Label => SortKey::PerTuple,
+            SortKey::PerTuple => SortKey::Id,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SortKey::Id => "id",
+            SortKey::Ms => "ms",
+            SortKey::Activations => "activations",
+            SortKey::Label => "label",
+            SortKey::PerTuple => "µs/tuple",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum RowKind {
+    Node(String),
+    Rule(usize),
+    Plan { rule: usize, fp: String },
+}
+
+/// One visible line of the left pane.
+struct TreeRow {
+    kind: RowKind,
+    depth: usize,
+    expandable: bool,
+    expanded: bool,
+    label: String,
+    ms: f64,
+    activations: u64,
+}
+
+struct App {
+    data: ReportData,
+    tab: Tab,
+    sort: SortKey,
+    descending: bool,
+    search: String,
+    searching: bool,
+    /// Expanded node names (Nodes tab) and rule/plan keys (Rules tab).
+    expanded: BTreeSet<String>,
+    rows: Vec<TreeRow>,
+    table: TableState,
+}
+
+impl App {
+    fn new(data: ReportData) -> Self {
+        let mut app = App {
+            data,
+            tab: Tab::Nodes,
+            sort: SortKey::Ms,
+            descending: true,
+            search: String::new(),
+            searching: false,
+            expanded: BTreeSet::new(),
+            rows: Vec::new(),
+            table: TableState::default(),
+        };
+        app.rebuild();
+        app.table.select(Some(0));
+        app
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if self.searching {
+                match key.code {
+                    KeyCode::Enter => self.searching = false,
+                    KeyCode::Esc => {
+                        self.searching = false;
+                        self.search.clear();
+                    }
+                    KeyCode::Backspace => {
+                        self.search.pop();
+                    }
+                    KeyCode::Char(c) => self.search.push(c),
+                    _ => {}
+                }
+                self.rebuild();
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(());
+                }
+                KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1),
+                KeyCode::PageDown => self.move_cursor(10),
+                KeyCode::PageUp => self.move_cursor(-10),
+                KeyCode::Home | KeyCode::Char('g') => self.table.select(Some(0)),
+                KeyCode::End | KeyCode::Char('G') => {
+                    self.table.select(Some(self.rows.len().saturating_sub(1)))
+                }
+                KeyCode::Right | KeyCode::Char('l') => self.set_expanded(true),
+                KeyCode::Left | KeyCode::Char('h') => self.set_expanded(false),
+                KeyCode::Enter | KeyCode::Char(' ') => self.toggle(),
+                KeyCode::Tab => {
+                    self.tab = match self.tab {
+                        Tab::Nodes => Tab::Rules,
+                        Tab::Rules => Tab::Nodes,
+                    };
+                    self.rebuild();
+                    self.table.select(Some(0));
+                }
+                KeyCode::Char('/') => self.searching = true,
+                KeyCode::Char('s') => {
+                    self.sort = self.sort.next();
+                    self.rebuild();
+                }
+                KeyCode::Char('r') => {
+                    self.descending = !self.descending;
+                    self.rebuild();
+                }
+                KeyCode::Char('e') => {
+                    self.expand_all();
+                    self.rebuild();
+                }
+                KeyCode::Char('c') => {
+                    self.expanded.clear();
+                    self.rebuild();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let cur = self.table.selected().unwrap_or(0) as isize;
+        let next = (cur + delta).clamp(0, self.rows.len() as isize - 1);
+        self.table.select(Some(next as usize));
+    }
+
+    fn selected_row(&self) -> Option<&TreeRow> {
+        self.table.selected().and_then(|i| self.rows.get(i))
+    }
+
+    fn set_expanded(&mut self, open: bool) {
+        let Some(row) = self.selected_row() else {
+            return;
+        };
+        if !row.expandable {
+            return;
+        }
+        let key = expand_key(&row.kind);
+        if open {
+            self.expanded.insert(key);
+        } else {
+            self.expanded.remove(&key);
+        }
+        self.rebuild();
+    }
+
+    fn toggle(&mut self) {
+        if let Some(row) = self.selected_row() {
+            let open = !row.expanded;
+            self.set_expanded(open);
+        }
+    }
+
+    fn expand_all(&mut self) {
+        match self.tab {
+            Tab::Nodes => {
+                for (name, node) in &self.data.nodes {
+                    if !node.children.is_empty() {
+                        self.expanded.insert(name.clone());
+                    }
+                }
+            }
+            Tab::Rules => {
+                for (idx, rule) in self.data.rules.iter().enumerate() {
+                    self.expanded.insert(expand_key(&RowKind::Rule(idx)));
+                    for (fp, pn) in &rule.nodes {
+                        if !pn.parents.is_empty() {
+                            self.expanded.insert(expand_key(&RowKind::Plan {
+                                rule: idx,
+                                fp: fp.clone(),
+                            }));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Recompute visible rows, keeping the cursor on the same item if possible.
+    fn rebuild(&mut self) {
+        let prev = self.selected_row().map(|r| r.kind.clone());
+
+        let mut rows = Vec::new();
+        match self.tab {
+            Tab::Nodes => {
+                let mut roots = self.data.roots.clone();
+                self.sort_names(&mut roots);
+                for name in &roots {
+                    self.push_node(&mut rows, name, 0, &mut BTreeSet::new());
+                }
+            }
+            Tab::Rules => {
+                let mut order: Vec<usize> = (0..self.data.rules.len()).collect();
+                order.sort_by(|&a, &b| self.cmp_rules(&self.data.rules[a], &self.data.rules[b]));
+                for idx in order {
+                    self.push_rule(&mut rows, idx);
+                }
+            }
+        }
+        self.rows = rows;
+
+        let pos = prev
+            .and_then(|k| self.rows.iter().position(|r| r.kind == k))
+            .unwrap_or(0);
+        self.table
+            .select((!self.rows.is_empty()).then_some(pos.min(self.rows.len() - 1)));
+    }
+
+    fn push_node(
+        &self,
+        rows: &mut Vec<TreeRow>,
+        name: &str,
+        depth: usize,
+        visiting: &mut BTreeSet<String>,
+    ) {
+        let Some(node) = self.data.nodes.get(name) else {
+            return;
+        };
+        if !visiting.insert(name.to_string()) || !self.subtree_matches(name, &mut BTreeSet::new()) {
+            return;
+        }
+
+        let searching = !self.search.is_empty();
+        let expandable = !node.children.is_empty();
+        let expanded = expandable && (searching || self.expanded.contains(name));
+        rows.push(TreeRow {
+            kind: RowKind::Node(name.to_string()),
+            depth,
+            expandable,
+            expanded,
+            label: node.label.clone(),
+            ms: node.self_total_active_ms,
+            activations: node.self_activations,
+        });
+
+        if expanded {
+            let mut kids = node.children.clone();
+            self.sort_names(&mut kids);
+            for kid in &kids {
+                self.push_node(rows, kid, depth + 1, visiting);
+            }
+        }
+        visiting.remove(name);
+    }
+
+    fn push_rule(&self, rows: &mut Vec<TreeRow>, idx: usize) {
+        let rule = &self.data.rules[idx];
+        let searching = !self.search.is_empty();
+        let rule_hit = self.matches(&rule.text);
+        if searching
+            && !rule_hit
+            && !rule
+                .nodes
+                .values()
+                .any(|pn| self.matches(pn.label.as_deref().unwrap_or(&pn.fingerprint)))
+        {
+            return;
+        }
+
+        let kind = RowKind::Rule(idx);
+        let expanded = searching || self.expanded.contains(&expand_key(&kind));
+        rows.push(TreeRow {
+            kind,
+            depth: 0,
+            expandable: !rule.nodes.is_empty(),
+            expanded,
+            label: rule.text.clone(),
+            ms: rule.total_active_ms,
+            activations: rule.activations,
+        });
+        if expanded {
+            self.push_plan(rows, idx, &rule.root, 1, &mut BTreeSet::new());
+        }
+    }
+
+    fn push_plan(
+        &self,
+        rows: &mut Vec<TreeRow>,
+        idx: usize,
+        fp: &str,
+        depth: usize,
+        visiting: &mut BTreeSet<String>,
+    ) {
+        let rule = &self.data.rules[idx];
+        let Some(pn) = rule.nodes.get(fp) else {
+            return;
+        };
+        if !visiting.insert(fp.to_string()) {
+            return;
+        }
+
+        let node = pn.node.as_deref().and_then(|n| self.data.nodes.get(n));
+        let mut label = pn.label.clone().unwrap_or_else(|| pn.fingerprint.clone());
+        if pn.shared {
+            label.push_str(" [shared]");
+        }
+
+        let kind = RowKind::Plan {
+            rule: idx,
+            fp: fp.to_string(),
+        };
+        let expandable = !pn.parents.is_empty();
+        let expanded =
+            expandable && (!self.search.is_empty() || self.expanded.contains(&expand_key(&kind)));
+        rows.push(TreeRow {
+            kind,
+            depth,
+            expandable,
+            expanded,
+            label,
+            ms: node.map(|n| n.self_total_active_ms).unwrap_or(0.0),
+            activations: node.map(|n| n.self_activations).unwrap_or(0),
+        });
+
+        if expanded {
+            for parent in &pn.parents {
+                self.push_plan(rows, idx, parent, depth + 1, visiting);
+            }
+        }
+        visiting.remove(fp);
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        self.search.is_empty() || text.to_lowercase().contains(&self.search.to_lowercase())
+    }
+
+    /// A node is shown while searching if it or any tree descendant matches.
+    fn subtree_matches(&self, name: &str, visiting: &mut BTreeSet<String>) -> bool {
+        if self.search.is_empty() {
+            return true;
+        }
+        let Some(node) = self.data.nodes.get(name) else {
+            return false;
+        };
+        if self.matches(&node.label) || self.matches(name) {
+            return true;
+        }
+        if !visiting.insert(name.to_string()) {
+            return false;
+        }
+        node.children
+            .iter()
+            .any(|kid| self.subtree_matches(kid, visiting))
+    }
+
+    fn sort_names(&self, names: &mut [String]) {
+        let nodes = &self.data.nodes;
+        names.sort_by(|a, b| match (nodes.get(a), nodes.get(b)) {
+            (Some(x), Some(y)) => self.cmp_nodes(x, y),
+            _ => a.cmp(b),
+        });
+    }
+
+    fn cmp_nodes(&self, a: &NameNodeView, b: &NameNodeView) -> Ordering {
+        let by_id = || {
+            let ia = a.name.parse::<u64>().unwrap_or(u64::MAX);
+            let ib = b.name.parse::<u64>().unwrap_or(u64::MAX);
+            ia.cmp(&ib).then_with(|| a.name.cmp(&b.name))
+        };
+        let ord = match self.sort {
+            SortKey::Id => by_id(),
+            SortKey::Ms => a.self_total_active_ms.total_cmp(&b.self_total_active_ms),
+            SortKey::Activations => a.self_activations.cmp(&b.self_activations),
+            SortKey::Label => a.label.cmp(&b.label),
+            SortKey::PerTuple => per_tuple(&a.sizes).total_cmp(&per_tuple(&b.sizes)),
+        };
+        let ord = if self.descending { ord.reverse() } else { ord };
+        ord.then_with(by_id)
+    }
+
+    fn cmp_rules(&self, a: &RuleView, b: &RuleView) -> Ordering {
+        let ord = match self.sort {
+            SortKey::Id => Ordering::Equal,
+            SortKey::Ms => a.total_active_ms.total_cmp(&b.total_active_ms),
+            SortKey::Activations => a.activations.cmp(&b.activations),
+            SortKey::Label => a.text.cmp(&b.text),
+            SortKey::PerTuple => per_tuple(&a.sizes).total_cmp(&per_tuple(&b.sizes)),
+        };
+        if self.descending { ord.reverse() } else { ord }
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(58), Constraint::Percentage(42)])
+                .areas(body);
+
+        frame.render_widget(Paragraph::new(self.header_line()), header);
+
+        let total = self.data.totals.total_mapped_ms;
+        let rows: Vec<Row> = self
+            .rows
+            .iter()
+            .map(|r| {
+                let marker = match (r.expandable, r.expanded) {
+                    (false, _) => "  ",
+                    (true, false) => "▸ ",
+                    (true, true) => "▾ ",
+                };
+                let label = format!("{}{}{}", "  ".repeat(r.depth), marker, r.label);
+                Row::new(vec![
+                    Cell::from(label),
+                    Cell::from(format!("{:.3}", r.ms)),
+                    Cell::from(pct(r.ms, total)),
+                    Cell::from(r.activations.to_string()),
+                ])
+                .style(heat_style(r.ms, total))
+            })
+            .collect();
+
+        let title = match self.tab {
+            Tab::Nodes => " Nodes ",
+            Tab::Rules => " Rules ",
+        };
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(20),
+                Constraint::Length(11),
+                Constraint::Length(7),
+                Constraint::Length(11),
+            ],
+        )
+        .header(
+            Row::new(vec!["label", "ms", "%", "activations"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, left, &mut self.table);
+
+        let detail = Paragraph::new(self.detail_lines())
+            .block(Block::default().borders(Borders::ALL).title(" Details "))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(detail, right);
+
+        let help = if self.searching {
+            "type to search · Enter keep · Esc clear".to_string()
+        } else {
+            "↑↓ move · ←→ collapse/expand · Tab nodes/rules · / search · s sort · r reverse · e/c expand/collapse all · q quit".to_string()
+        };
+        frame.render_widget(
+            Paragraph::new(help).style(Style::default().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+
+    fn header_line(&self) -> Line<'static> {
+        let t = &self.data.totals;
+        let mut spans = vec![
+            Span::styled(
+                "FlowLog profile",
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                "  names {} · ops {}/{} mapped · {:.3} ms · {} activations  │ sort: {} {}",
+                t.names,
+                t.operators_mapped,
+                t.operators_in_log,
+                t.total_mapped_ms,
+                t.total_mapped_activations,
+                self.sort.name(),
+                if self.descending { "↓" } else { "↑" },
+            )),
+        ];
+        if self.searching || !self.search.is_empty() {
+            spans.push(Span::styled(
+                format!("  /{}", self.search),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        Line::from(spans)
+    }
+
+    fn detail_lines(&self) -> Vec<Line<'static>> {
+        let Some(row) = self.selected_row() else {
+            return vec![Line::from("Nothing selected")];
+        };
+        match &row.kind {
+            RowKind::Node(name) => self.node_detail(name),
+            RowKind::Plan { rule, fp } => {
+                let pn = &self.data.rules[*rule].nodes[fp];
+                let mut lines = vec![
+                    kv("fingerprint", fp.clone()),
+                    kv("shared", pn.shared.to_string()),
+                    Line::from(""),
+                ];
+                match &pn.node {
+                    Some(name) => lines.extend(self.node_detail(name)),
+                    None => lines.push(Line::from("no node for this fingerprint")),
+                }
+                lines
+            }
+            RowKind::Rule(idx) => {
+                let rule = &self.data.rules[*idx];
+                let total = self.data.totals.total_mapped_ms;
+                let mut lines = vec![
+                    Line::from(Span::styled(
+                        rule.text.clone(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    )),
+                    Line::from(""),
+                    kv(
+                        "time",
+                        format!(
+                            "{:.3}{} ms ({})",
+                            rule.total_active_ms,
+                            spread_suffix(rule.ms_spread),
+                            pct(rule.total_active_ms, total)
+                        ),
+                    ),
+                    kv("activations", rule.activations.to_string()),
+                ];
+                if let Some(sizes) = &rule.sizes {
+                    lines.push(kv("tuples", sizes_summary(sizes)));
+                }
+                if let Some(a) = &rule.arrangement {
+                    lines.push(kv("arranged", arrangement_summary(a)));
+                }
+                if let Some(parsed) = &rule.parsed {
+                    lines.push(kv("head", parsed.head.relation.clone()));
+                    let mut body: Vec<String> =
+                        parsed.body.iter().map(|a| a.relation.clone()).collect();
+                    body.extend(parsed.negated.iter().map(|a| format!("!{}", a.relation)));
+                    lines.push(kv("body", body.join(", ")));
+                    if !parsed.aggregates.is_empty() {
+                        let aggs: Vec<String> = parsed
+                            .aggregates
+                            .iter()
+                            .map(|a| format!("{}({})", a.function, a.expr))
+                            .collect();
+                        lines.push(kv("aggregates", aggs.join(", ")));
+                    }
+                }
+                lines.extend([
+                    kv("plan nodes", rule.nodes.len().to_string()),
+                    kv("root", rule.root.clone()),
+                ]);
+                let shared: Vec<&str> = rule
+                    .nodes
+                    .values()
+                    .filter(|pn| pn.shared)
+                    .map(|pn| pn.fingerprint.as_str())
+                    .collect();
+                if !shared.is_empty() {
+                    lines.push(kv("shared", shared.join(", ")));
+                }
+                lines
+            }
+        }
+    }
+
+    fn node_detail(&self, name: &str) -> Vec<Line<'static>> {
+        let Some(node) = self.data.nodes.get(name) else {
+            return vec![Line::from(format!("unknown node {}", name))];
+        };
+        let total = self.data.totals.total_mapped_ms;
+        let label_of = |n: &String| -> String {
+            match self.data.nodes.get(n) {
+                Some(v) => format!("{} ({})", n, v.label),
+                None => n.clone(),
+            }
+        };
+
+        let mut lines = vec![
+            Line::from(Span::styled(
+                node.label.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+            kv("id", node.name.clone()),
+            kv("block", node.block.clone()),
+            kv(
+                "fingerprint",
+                node.fingerprint.clone().unwrap_or_else(|| "-".to_string()),
+            ),
+            kv("tags", node.tags.join(", ")),
+            kv(
+                "time",
+                format!(
+                    "{:.3}{} ms ({})",
+                    node.self_total_active_ms,
+                    spread_suffix(node.ms_spread),
+                    pct(node.self_total_active_ms, total)
+                ),
+            ),
+            kv("activations", node.self_activations.to_string()),
+        ];
+        if let Some(act) = &node.activation {
+            lines.push(kv("durations", activation_summary(act)));
+        }
+        if let Some(sizes) = &node.sizes {
+            lines.push(kv("tuples", sizes_summary(sizes)));
+        }
+        if let Some(a) = &node.arrangement {
+            lines.push(kv("arranged", arrangement_summary(a)));
+        }
+        if let Some(slack) = node.slack_ms {
+            let program = if slack > 0.0 {
+                format!("{:.3} ms", slack)
+            } else {
+                "on critical path".to_string()
+            };
+            let block = node
+                .block_slack_ms
+                .filter(|&b| b != slack)
+                .map(|b| format!(" ({:.3} ms in block)", b))
+                .unwrap_or_default();
+            lines.push(kv("slack", format!("{}{}", program, block)));
+        }
+
+        lines.push(Line::from(""));
+        lines.push(section("DAG parents"));
+        if node.dag_parents.is_empty() {
+            lines.push(Line::from("  (none)"));
+        }
+        for p in &node.dag_parents {
+            // Records received along the edge, when the log had channel events.
+            let volume = self
+                .data
+                .edges
+                .iter()
+                .find(|e| &e.source == p && e.target == node.name)
+                .map(|e| format!("  ({} records in {} msgs)", e.records, e.messages))
+                .unwrap_or_default();
+            lines.push(Line::from(format!("  {}{}", label_of(p), volume)));
+        }
+
+        lines.push(Line::from(""));
+        lines.push(section("Operators"));
+        if node.operators.is_empty() {
+            lines.push(Line::from("  (none in log)"));
+        }
+        let mut ops: Vec<_> = node.operators.iter().collect();
+        ops.sort_by(|a, b| b.total_active_ms.total_cmp(&a.total_active_ms));
+        for op in ops {
+            lines.push(Line::from(format!(
+                "  {:<14} {:>11.3} ms {:>7} act  {}",
+                format!("{:?}", op.addr),
+                op.total_active_ms,
+                op.activations,
+                op.op_name
+            )));
+            if let Some(act) = &op.activation {
+                let style = if act.heavy_tail {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                lines.push(Line::styled(
+                    format!("  {:<14} {}", "", activation_summary(act)),
+                    style,
+                ));
+            }
+            if !op.metrics.is_empty() {
+                let metrics: Vec<String> = op
+                    .metrics
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+                lines.push(Line::styled(
+                    format!("  {:<14} {}", "", metrics.join("  ")),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+        }
+        lines
+    }
+}
+
+fn per_tuple(sizes: &Option<SizeView>) -> f64 {
+    sizes.as_ref().and_then(|s| s.us_per_tuple).unwrap_or(-1.0)
+}
+
+/// Tuple counts and per-tuple cost, e.g. `in 1200 out 5400 · 3.210 µs/tuple (311526 tuples/s) [tc]`.
+fn sizes_summary(sizes: &SizeView) -> String {
+    let mut parts = Vec::new();
+    if let Some(i) = sizes.input {
+        parts.push(format!("in {}", i));
+    }
+    if let Some(o) = sizes.output {
+        parts.push(format!("out {}", o));
+    }
+    let mut text = parts.join(" ");
+    if let Some(us) = sizes.us_per_tuple {
+        let _ = write!(text, " · {:.3} µs/tuple", us);
+    }
+    if let Some(tps) = sizes.tuples_per_s {
+        let _ = write!(text, " ({:.0} tuples/s)", tps);
+    }
+    let _ = write!(text, " [{}]", sizes.key);
+    text
+}
+
+/// Arrangement size, e.g. `5400 records in 3 batches (peak 7200)`.
+fn arrangement_summary(a: &ArrangementView) -> String {
+    format!(
+        "{} records in {} batches (peak {})",
+        a.records, a.batches, a.peak_records
+    )
+}
+
+/// ` ± <95% CI half-width> (min–max over N runs)` for aggregated runs.
+fn spread_suffix(spread: Option<Spread>) -> String {
+    match spread {
+        Some(s) if s.runs > 1 => format!(
+            " ± {:.3} ({:.3}–{:.3} over {} runs)",
+            (s.ci_high - s.ci_low) / 2.0,
+            s.min,
+            s.max,
+            s.runs
+        ),
+        _ => String::new(),
+    }
+}
+
+/// Percentiles plus a one-line histogram, e.g. `p50 0.012 p99 3.100 max 2000.000 ms ▃█▁ ▁`.
+fn activation_summary(act: &ActivationStats) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let peak = act.histogram.iter().copied().max().unwrap_or(0).max(1) as f64;
+    let spark: String = act
+        .histogram
+        .iter()
+        .map(|&c| {
+            if c == 0 {
+                ' '
+            } else {
+                BARS[((c as f64 / peak).sqrt() * 7.0).round() as usize]
+            }
+        })
+        .collect();
+    format!(
+        "p50 {:.3} p90 {:.3} p99 {:.3} max {:.3} ms {}{}",
+        act.p50_ms,
+        act.p90_ms,
+        act.p99_ms,
+        act.max_ms,
+        spark,
+        if act.heavy_tail { "  heavy tail" } else { "" }
+    )
+}
+
+fn expand_key(kind: &RowKind) -> String {
+    match kind {
+        RowKind::Node(name) => name.clone(),
+        RowKind::Rule(idx) => format!("rule:{}", idx),
+        RowKind::Plan { rule, fp } => format!("rule:{}:{}", rule, fp),
+    }
+}
+
+fn kv(key: &str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{:<12}", key), Style::default().fg(Color::DarkGray)),
+        Span::raw(value),
+    ])
+}
+
+fn section(title: &str) -> Line<'static> {
+    Line::from(Span::styled(
+        title.to_string(),
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    ))
+}
+
+fn pct(part: f64, total: f64) -> String {
+    if total <= 0.0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", 100.0 * part / total)
+}
+
+/// Color rows by share of total time, like the heat map in the HTML report.
+fn heat_style(ms: f64, total: f64) -> Style {
+    let share = if total > 0.0 { ms / total } else { 0.0 };
+    match share {
+        s if s >= 0.25 => Style::default().fg(Color::Red),
+        s if s >= 0.05 => Style::default().fg(Color::Yellow),
+        _ => Style::default(),
+    }
+}
@@ -1,30 +1,100 @@
 //! Aggregation model: combine UI tree (from ops.json) + log rows.
 
+use crate::Result;
 use crate::addr::Addr;
-use crate::diagnostics;
-use crate::log::{LogIndex, LogRow};
+use crate::arrangements::{ArrangementSummary, ArrangementView};
+use crate::critical::{self, CriticalPathView};
+use crate::datalog::{self, ParsedRule, RelationView};
+use crate::diagnostics::Diagnostic;
+use crate::edges::EdgeView;
+use crate::error::Error;
+use crate::histogram::Histogram;
+use crate::log::{LogIndex, LogRow, ParseStats};
 use crate::ops::{NodeSpec, RuleSpec};
-use crate::Result;
+use crate::sizes::SizeView;
+use crate::source::SourceView;
+use crate::stats::Spread;
 
 use anyhow::bail;
 use serde::Serialize;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 
+/// One Timely operator of a node, with its cost.
 #[derive(Debug, Clone, Serialize)]
 pub struct OperatorView {
+    /// Operator address.
     pub addr: Vec<u32>,
+    /// Timely operator name.
     pub op_name: String,
+    /// Times the operator was scheduled.
     pub activations: u64,
+    /// Time spent in those activations, in ms.
     pub total_active_ms: f64,
+    /// Extra numeric log columns (e.g. `records_in`, `peak_bytes`), by name.
+    pub metrics: BTreeMap<String, f64>,
+    /// Activation duration distribution, when the log has one.
+    pub activation: Option<ActivationStats>,
+    /// `total_active_ms` over repeated runs; `None` for a single run.
+    pub ms_spread: Option<Spread>,
+    /// Arrangement size from `--arrangements`, when this operator has one.
+    pub arrangement: Option<ArrangementView>,
+}
+
+/// Activations shorter than this never count as a heavy tail.
+const HEAVY_TAIL_MIN_MS: f64 = 1.0;
+/// Fewer activations than this are too few to call a distribution heavy-tailed.
+const HEAVY_TAIL_MIN_ACTIVATIONS: u64 = 10;
+
+/// Percentiles of activation durations, estimated from a log-bucketed histogram.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivationStats {
+    /// Median activation, in ms.
+    pub p50_ms: f64,
+    /// 90th percentile activation, in ms.
+    pub p90_ms: f64,
+    /// 99th percentile activation, in ms.
+    pub p99_ms: f64,
+    /// Exact when the log records `max_activation_ms`, else the top bucket's bound.
+    pub max_ms: f64,
+    /// Counts per bucket; see [`crate::histogram`] for the bucket layout.
+    pub histogram: Vec<u64>,
+    /// A few activations dominate: p99 is 10× the median or the maximum 100×.
+    pub heavy_tail: bool,
+}
+
+impl ActivationStats {
+    /// `None` for an empty histogram. `max_ms` also caps the estimated percentiles.
+    fn new(histogram: &Histogram, max_ms: Option<f64>) -> Option<Self> {
+        let max_ms = max_ms.or_else(|| histogram.upper_bound_ms())?;
+        let at = |q| histogram.quantile_ms(q).map(|v: f64| v.min(max_ms));
+        let (p50_ms, p90_ms, p99_ms) = (at(0.5)?, at(0.9)?, at(0.99)?);
+        let heavy_tail = histogram.count() >= HEAVY_TAIL_MIN_ACTIVATIONS
+            && max_ms >= HEAVY_TAIL_MIN_MS
+            && (p99_ms >= 10.0 * p50_ms || max_ms >= 100.0 * p50_ms);
+        Some(Self {
+            p50_ms,
+            p90_ms,
+            p99_ms,
+            max_ms,
+            histogram: histogram.counts().to_vec(),
+            heavy_tail,
+        })
+    }
 }
 
+/// A logical node (ops.json entry) with the cost of its operators.
 #[derive(Debug, Clone, Serialize)]
 pub struct NameNodeView {
+    /// ops.json node id, as a string.
     pub name: String,
+    /// Display label.
     pub label: String,
+    /// Block (`input`, `stratum N`, `inspect`, ...).
     pub block: String,
+    /// Plan fingerprint, when the node has one.
     pub fingerprint: Option<String>,
+    /// Free-form tags from ops.json.
     pub tags: Vec<String>,
 
     /// Primary tree children (spanning tree derived from DAG).
@@ -38,49 +108,124 @@ pub struct NameNodeView {
 
     /// Aggregated over operators owned by this name.
     pub self_activations: u64,
+    /// Active time of those operators, in ms.
     pub self_total_active_ms: f64,
 
     /// Operators owned by this name (sorted by total_active_ms desc).
     pub operators: Vec<OperatorView>,
+
+    /// Activation durations over all operators of this name, when known.
+    pub activation: Option<ActivationStats>,
+    /// `self_total_active_ms` over repeated runs; `None` for a single run.
+    pub ms_spread: Option<Spread>,
+    /// Tuple counts from `--sizes`, when an entry matches this node.
+    pub sizes: Option<SizeView>,
+    /// Arrangement sizes summed over this node's operators (`--arrangements`).
+    pub arrangement: Option<ArrangementView>,
+    /// How much lighter the heaviest DAG path through this node is than the
+    /// program critical path, in ms; `None` on or below a DAG cycle.
+    pub slack_ms: Option<f64>,
+    /// Like `slack_ms`, within this node's block.
+    pub block_slack_ms: Option<f64>,
 }
 
+/// One node of a rule's plan tree, resolved to a report node when possible.
 #[derive(Debug, Clone, Serialize)]
 pub struct RulePlanNodeView {
+    /// Plan fingerprint.
     pub fingerprint: String,
+    /// Report node with this fingerprint, if any.
     pub node: Option<String>,
+    /// That node's label.
     pub label: Option<String>,
+    /// Fingerprints of the child plan nodes.
     pub children: Vec<String>,
+    /// Fingerprints of the parent plan nodes.
     pub parents: Vec<String>,
+    /// Whether other rules' plans use this node too.
     pub shared: bool,
 }
 
+/// A rule with its plan tree and combined cost.
 #[derive(Debug, Clone, Serialize)]
 pub struct RuleView {
+    /// Rule text.
     pub text: String,
+    /// `text` split into head and body relations; `None` if it does not parse.
+    pub parsed: Option<ParsedRule>,
+    /// Fingerprint of the plan root.
     pub root: String,
+    /// Plan nodes by fingerprint.
     pub nodes: BTreeMap<String, RulePlanNodeView>,
+
+    /// Aggregated over the distinct nodes in this rule's plan tree.
+    pub activations: u64,
+    /// Self time of those nodes, in ms.
+    pub total_active_ms: f64,
+    /// `total_active_ms` over repeated runs; `None` for a single run.
+    pub ms_spread: Option<Spread>,
+    /// Tuple counts from `--sizes`, when an entry matches this rule.
+    pub sizes: Option<SizeView>,
+    /// Arrangement sizes summed over the distinct nodes in the plan tree.
+    pub arrangement: Option<ArrangementView>,
 }
 
+/// Everything a report shows; the input to every [`crate::Sink`].
 #[derive(Debug, Clone, Serialize)]
 pub struct ReportData {
+    /// Top-level nodes of the tree view.
     pub roots: Vec<String>,
+    /// Nodes by name (ops.json id).
     pub nodes: BTreeMap<String, NameNodeView>,
+    /// Rules in ops.json order.
     pub rules: Vec<RuleView>,
+    /// Relations of the parsed rules with their cost, most expensive first.
+    pub relations: Vec<RelationView>,
+    /// Heaviest root-to-sink DAG paths by self time, and their bottlenecks.
+    pub critical_path: CriticalPathView,
+    /// Program-wide totals.
     pub totals: TotalsView,
+
+    /// Warnings/info collected while parsing, validating and aggregating.
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// Lines the log parser skipped (`--lenient`) or adjusted.
+    pub log_stats: ParseStats,
+
+    /// Program and per-block arrangement sizes, with `--arrangements`.
+    pub arrangements: Option<ArrangementSummary>,
+
+    /// DAG edges with the data sent along them, heaviest first; empty unless
+    /// the log is an event capture with channel events.
+    pub edges: Vec<EdgeView>,
+
+    /// The program annotated with rule costs, with `--source`.
+    pub source: Option<SourceView>,
 }
 
+/// Program-wide counts and totals.
 #[derive(Debug, Clone, Serialize)]
 pub struct TotalsView {
+    /// Nodes in ops.json.
     pub names: usize,
+    /// Operators with a row in the log.
     pub operators_in_log: usize,
+    /// Of those, operators owned by a node.
     pub operators_mapped: usize,
+    /// Active time of the mapped operators, in ms.
     pub total_mapped_ms: f64,
+    /// Activations of the mapped operators.
     pub total_mapped_activations: u64,
+    /// Number of profile logs aggregated; with more than one, counts and
+    /// times throughout are means and the `*spread` fields are set.
+    pub runs: usize,
+    /// `total_mapped_ms` over repeated runs; `None` for a single run.
+    pub total_mapped_ms_spread: Option<Spread>,
 }
 
 /// Build report data. Performs:
 /// - detect operator addr assigned to multiple names (error)
-/// - warn (stderr) about mapped addrs missing from log
+/// - record diagnostics for mapped addrs missing from log and unmapped log rows
 pub fn build_report_data(
     nodes_spec: &BTreeMap<String, NodeSpec>,
     roots: &[String],
@@ -93,15 +238,10 @@ pub fn build_report_data(
     for (name, spec) in nodes_spec {
         for addr in &spec.operators {
             if let Some(prev) = owner.insert(addr, name.as_str()) {
-                bail!(
-                    "{}",
-                    diagnostics::error_message(format!(
-                        "operator addr {:?} is assigned to multiple names: {} and {}",
-                        addr.0,
-                        prev,
-                        name
-                    ))
-                );
+                bail!(Error::mapping(format!(
+                    "operator addr {:?} is assigned to multiple names: {} and {}",
+                    addr.0, prev, name
+                )));
             }
         }
     }
@@ -158,6 +298,7 @@ pub fn build_report_data(
     // Phase 3: build per-name operator lists + aggregates.
     let mut nodes_view: BTreeMap<String, NameNodeView> = BTreeMap::new();
 
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
     let mut total_mapped_ms = 0.0f64;
     let mut total_mapped_activations = 0u64;
     let mut operators_mapped = 0usize;
@@ -166,6 +307,8 @@ pub fn build_report_data(
         let mut operators: Vec<OperatorView> = Vec::new();
         let mut self_ms = 0.0f64;
         let mut self_act = 0u64;
+        let mut histogram: Option<Histogram> = None;
+        let mut max_ms: Option<f64> = None;
 
         for addr in &spec.operators {
             match log.get(addr) {
@@ -174,12 +317,32 @@ pub fn build_report_data(
                     activations,
                     total_active_ms,
                     op_name,
+                    metrics,
+                    histogram: op_histogram,
+                    ..
                 }) => {
+                    let op_max = metrics.get("max_activation_ms").copied();
+                    let activation = op_histogram
+                        .as_ref()
+                        .and_then(|h| ActivationStats::new(h, op_max));
+                    if let Some(h) = op_histogram {
+                        histogram.get_or_insert_default().merge(h);
+                    }
+                    if let Some(stats) = &activation {
+                        max_ms = Some(max_ms.map_or(stats.max_ms, |m| m.max(stats.max_ms)));
+                    }
                     operators.push(OperatorView {
                         addr: addr.0.clone(),
                         op_name: op_name.clone(),
                         activations: *activations,
                         total_active_ms: *total_active_ms,
+                        metrics: metrics
+                            .iter()
+                            .map(|(name, value)| (name.to_string(), *value))
+                            .collect(),
+                        activation,
+                        ms_spread: None,
+                        arrangement: None,
                     });
                     self_ms += *total_active_ms;
                     self_act += *activations;
@@ -188,10 +351,17 @@ pub fn build_report_data(
                     total_mapped_activations += *activations;
                     operators_mapped += 1;
                 }
-                None => diagnostics::warn(format!(
-                    "ops.json maps name '{}' to addr {:?}, but addr not found in log",
-                    name, addr.0
-                )),
+                None => diagnostics.push(
+                    Diagnostic::warning(
+                        "addr-missing-from-log",
+                        format!(
+                            "ops.json maps name '{}' to addr {:?}, but addr not found in log",
+                            name, addr.0
+                        ),
+                    )
+                    .with_node(name.clone())
+                    .with_addr(&addr.0),
+                ),
             }
         }
 
@@ -212,10 +382,45 @@ pub fn build_report_data(
                 self_activations: self_act,
                 self_total_active_ms: self_ms,
                 operators,
+                activation: histogram.and_then(|h| ActivationStats::new(&h, max_ms)),
+                ms_spread: None,
+                sizes: None,
+                arrangement: None,
+                slack_ms: None,
+                block_slack_ms: None,
             },
         );
     }
 
+    for (addr, row) in log {
+        if !owner.contains_key(addr) {
+            diagnostics.push(
+                Diagnostic::info(
+                    "log-operator-unmapped",
+                    format!(
+                        "log operator {:?} ({}) is not mapped to any node",
+                        addr.0, row.op_name
+                    ),
+                )
+                .with_addr(&addr.0),
+            );
+        }
+    }
+
+    let rules = build_rule_views(rules_spec, nodes_spec, &nodes_view, fingerprint_to_node);
+    // Re-parse the failures only, for the message.
+    for rule in rules.iter().filter(|r| r.parsed.is_none()) {
+        if let Err(message) = datalog::parse_rule(&rule.text) {
+            diagnostics.push(Diagnostic::info(
+                "rule-unparsed",
+                format!("rule `{}` is shown as text only: {}", rule.text, message),
+            ));
+        }
+    }
+    let relations = datalog::build_relations(&rules, &nodes_view);
+    let (critical_path, findings) = critical::attach_critical_path(&mut nodes_view);
+    diagnostics.extend(findings);
+
     Ok(ReportData {
         roots,
         totals: TotalsView {
@@ -224,15 +429,25 @@ pub fn build_report_data(
             operators_mapped,
             total_mapped_ms,
             total_mapped_activations,
+            runs: 1,
+            total_mapped_ms_spread: None,
         },
         nodes: nodes_view,
-        rules: build_rule_views(rules_spec, nodes_spec, fingerprint_to_node),
+        rules,
+        relations,
+        critical_path,
+        diagnostics,
+        log_stats: ParseStats::default(),
+        arrangements: None,
+        edges: Vec::new(),
+        source: None,
     })
 }
 
 fn build_rule_views(
     rules_spec: &[RuleSpec],
     nodes_spec: &BTreeMap<String, NodeSpec>,
+    nodes_view: &BTreeMap<String, NameNodeView>,
     fingerprint_to_node: &BTreeMap<String, String>,
 ) -> Vec<RuleView> {
     let mut views = Vec::new();
@@ -246,7 +461,8 @@ fn build_rule_views(
             }
         }
 
-        let mut nodes_view: BTreeMap<String, RulePlanNodeView> = BTreeMap::new();
+        let mut plan_view: BTreeMap<String, RulePlanNodeView> = BTreeMap::new();
+        let mut owned: BTreeSet<String> = BTreeSet::new();
         for (fp, node) in &rule.nodes {
             let node_name = fingerprint_to_node.get(fp).cloned();
             let label = node_name
@@ -257,7 +473,11 @@ fn build_rule_views(
             let parent_list = parents.get(fp).cloned().unwrap_or_default();
             let shared = parent_list.len() > 1;
 
-            nodes_view.insert(
+            if let Some(n) = &node_name {
+                owned.insert(n.clone());
+            }
+
+            plan_view.insert(
                 fp.clone(),
                 RulePlanNodeView {
                     fingerprint: fp.clone(),
@@ -270,16 +490,82 @@ fn build_rule_views(
             );
         }
 
+        // Count each node once, even if several fingerprints resolve to it.
+        let mut activations = 0u64;
+        let mut total_active_ms = 0.0f64;
+        for n in owned.iter().filter_map(|n| nodes_view.get(n)) {
+            activations += n.self_activations;
+            total_active_ms += n.self_total_active_ms;
+        }
+
         views.push(RuleView {
             text: rule.text.clone(),
+            parsed: datalog::parse_rule(&rule.text).ok(),
             root: rule.root.clone(),
-            nodes: nodes_view,
+            nodes: plan_view,
+            activations,
+            total_active_ms,
+            ms_spread: None,
+            sizes: None,
+            arrangement: None,
         });
     }
 
     views
 }
 
+/// Set the `*spread` fields of `data` (built from the mean of `runs`' logs)
+/// from the per-run report data of the same ops.json.
+///
+/// A node, operator or rule missing from a run counts as 0 ms there, the same
+/// as in the mean log.
+pub fn attach_spreads(data: &mut ReportData, runs: &[ReportData]) {
+    let values = |get: &dyn Fn(&ReportData) -> Option<f64>| -> Option<Spread> {
+        let values: Vec<f64> = runs.iter().map(|r| get(r).unwrap_or(0.0)).collect();
+        Spread::of(&values)
+    };
+
+    data.totals.runs = runs.len();
+    data.totals.total_mapped_ms_spread = values(&|r| Some(r.totals.total_mapped_ms));
+    for (name, node) in &mut data.nodes {
+        node.ms_spread = values(&|r| r.nodes.get(name).map(|n| n.self_total_active_ms));
+        for op in &mut node.operators {
+            op.ms_spread = values(&|r| {
+                r.nodes
+                    .get(name)?
+                    .operators
+                    .iter()
+                    .find(|o| o.addr == op.addr)
+                    .map(|o| o.total_active_ms)
+            });
+        }
+    }
+    let rule_ms: Vec<BTreeMap<&str, f64>> = runs
+        .iter()
+        .map(|r| {
+            r.rules
+                .iter()
+                .map(|x| (x.text.as_str(), x.total_active_ms))
+                .collect()
+        })
+        .collect();
+    for rule in &mut data.rules {
+        let values: Vec<f64> = rule_ms
+            .iter()
+            .map(|ms| ms.get(rule.text.as_str()).copied().unwrap_or(0.0))
+            .collect();
+        rule.ms_spread = Spread::of(&values);
+    }
+    for relation in &mut data.relations {
+        relation.ms_spread = values(&|r| {
+            r.relations
+                .iter()
+                .find(|x| x.name == relation.name)
+                .map(|x| x.total_active_ms)
+        });
+    }
+}
+
 fn normalize_parents<T: Ord>(mut parents: Vec<T>) -> Vec<T> {
     parents.sort();
     parents.dedup();
@@ -0,0 +1,98 @@
+//! Watch mode: poll input files and rerun the pipeline when they change.
+//!
+//! Polling (mtime + length) keeps this dependency-free and works on network
+//! filesystems. A change is only acted on once the files have been stable for
+//! one full interval, so a log that is still being written is not parsed half-way.
+
+use crate::Result;
+use crate::diagnostics;
+
+use std::fs;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+type Snapshot = Vec<Option<(SystemTime, u64)>>;
+
+/// Run `job` once, then again whenever any of `paths` changes, until the
+/// process is interrupted.
+///
+/// Failures are reported to stderr and do not stop the loop; since `job` is
+/// expected to write its output only after the whole pipeline succeeds, the
+/// last good output stays in place.
+pub fn watch(
+    paths: &[&str],
+    interval: Duration,
+    mut job: impl FnMut() -> Result<()>,
+) -> Result<()> {
+    let watcher = Watcher::new(paths);
+    run_job(&mut job);
+    watcher.run(interval, job)
+}
+
+/// Changes to a set of files, relative to when the watcher was created.
+///
+/// For callers that run the first job themselves (e.g. `serve`, which must
+/// fail if the initial load does): create the watcher before that run so
+/// changes made during it are not missed, then [`Watcher::run`].
+pub struct Watcher {
+    paths: Vec<String>,
+    last: Snapshot,
+}
+
+impl Watcher {
+    /// Start watching `paths` from their current state.
+    pub fn new(paths: &[&str]) -> Self {
+        Self {
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            last: snapshot(paths),
+        }
+    }
+
+    /// Run `job` whenever any of the paths changes, until the process is
+    /// interrupted; failures are reported like in [`watch`].
+    pub fn run(mut self, interval: Duration, mut job: impl FnMut() -> Result<()>) -> Result<()> {
+        let paths: Vec<&str> = self.paths.iter().map(String::as_str).collect();
+        eprintln!("Watching {} for changes (Ctrl-C to stop)", paths.join(", "));
+
+        loop {
+            thread::sleep(interval);
+            let current = snapshot(&paths);
+            if current == self.last {
+                continue;
+            }
+
+            // Wait for the writer to finish before reparsing.
+            let mut settled = current;
+            loop {
+                thread::sleep(interval);
+                let next = snapshot(&paths);
+                if next == settled {
+                    break;
+                }
+                settled = next;
+            }
+            self.last = settled;
+
+            eprintln!("Change detected, regenerating");
+            run_job(&mut job);
+        }
+    }
+}
+
+fn run_job(job: &mut impl FnMut() -> Result<()>) {
+    if let Err(err) = job() {
+        diagnostics::error(&err);
+        diagnostics::warn("inputs failed to load; keeping the previous output");
+    }
+}
+
+fn snapshot(paths: &[&str]) -> Snapshot {
+    paths
+        .iter()
+        .map(|p| {
+            fs::metadata(p)
+                .ok()
+                .and_then(|m| Some((m.modified().ok()?, m.len())))
+        })
+        .collect()
+}